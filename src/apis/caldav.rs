@@ -0,0 +1,465 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::{CONTENT_TYPE, ETAG, HeaderMap, HeaderValue, IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Client, Method, RequestBuilder, Url};
+
+use crate::apis::http_client::BasicAuth;
+use crate::error::Error;
+use crate::utils::date_time_range::DateTimeRange;
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+
+/// CalDAVサーバー (RFC 4791)。URLは操作ごとに指定する
+#[derive(Clone)]
+pub struct CalDavClient {
+    client: Client,
+    basic_auth: Option<BasicAuth>,
+}
+
+/// カレンダーコレクション内のリソース
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CalDavObject {
+    /// サーバーが返したパス
+    pub href: String,
+    pub etag: Option<String>,
+    pub calendar_data: String,
+}
+
+/// PUTで上書きしてよい条件
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precondition<'a> {
+    /// 同じURLのリソースがない場合だけ作成する
+    Absent,
+    /// 指定したETagから変わっていない場合だけ上書きする
+    Matches(&'a str),
+    /// 条件を付けずに上書きする
+    Any,
+}
+
+impl CalDavClient {
+    pub fn new() -> Self {
+        Self { client: Client::new(), basic_auth: None }
+    }
+
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_basic_auth(mut self, basic_auth: Option<BasicAuth>) -> Self {
+        self.basic_auth = basic_auth;
+        self
+    }
+
+    /// `url`がカレンダーコレクションならそのまま返す。そうでなければプリンシパルからカレンダーホームをたどり、
+    /// `calendar`(パスの末尾か表示名)に一致するカレンダー、省略時は最初のカレンダーのURLを返す (RFC 6764 6)
+    pub async fn discover_calendar(&self, url: &str, calendar: Option<&str>) -> Result<String, Error> {
+        let props = "<d:resourcetype/><d:current-user-principal/><c:calendar-home-set/>";
+        let response = self.propfind(url, "0", props).await?;
+        let document = parse(&response)?;
+        if let Some(node) = document.descendants().find(|node| node.has_tag_name((DAV, "resourcetype"))) {
+            if has_child(node, (CALDAV, "calendar")) {
+                return Ok(url.to_string());
+            }
+        }
+
+        let home = match href_in(&document, (CALDAV, "calendar-home-set")) {
+            Some(home) => resolve(url, &home)?,
+            None => {
+                let principal = href_in(&document, (DAV, "current-user-principal"))
+                    .ok_or_else(|| Error::CalDav(format!("{} has neither calendar-home-set nor current-user-principal", url)))?;
+                let principal = resolve(url, &principal)?;
+                let response = self.propfind(&principal, "0", "<c:calendar-home-set/>").await?;
+                let home = href_in(&parse(&response)?, (CALDAV, "calendar-home-set"))
+                    .ok_or_else(|| Error::CalDav(format!("{} has no calendar-home-set", principal)))?;
+                resolve(&principal, &home)?
+            }
+        };
+
+        let response = self.propfind(&home, "1", "<d:resourcetype/><d:displayname/>").await?;
+        let document = parse(&response)?;
+        let calendars: Vec<(String, Option<String>)> = document
+            .descendants()
+            .filter(|node| node.has_tag_name((DAV, "response")))
+            .filter(|response| {
+                response
+                    .descendants()
+                    .any(|node| node.has_tag_name((DAV, "resourcetype")) && has_child(node, (CALDAV, "calendar")))
+            })
+            .filter_map(|response| {
+                let href = child_text(response, (DAV, "href"))?;
+                let display_name = response.descendants().find(|node| node.has_tag_name((DAV, "displayname"))).map(text);
+                Some((href, display_name))
+            })
+            .collect();
+
+        let found = match calendar {
+            Some(name) => calendars.into_iter().find(|(href, display_name)| {
+                href.trim_end_matches('/').rsplit('/').next() == Some(name) || display_name.as_deref() == Some(name)
+            }),
+            None => calendars.into_iter().next(),
+        };
+        let (href, _) = found.ok_or_else(|| match calendar {
+            Some(name) => Error::CalDav(format!("calendar {} is not found in {}", name, home)),
+            None => Error::CalDav(format!("{} has no calendars", home)),
+        })?;
+
+        resolve(&home, &href)
+    }
+
+    /// `period`と重なるVEVENTを含むリソース。`expand`を指定すると、繰り返し予定はサーバーで回ごとに展開させる
+    pub async fn calendar_query(&self, collection_url: &str, period: &DateTimeRange, expand: bool) -> Result<Vec<CalDavObject>, Error> {
+        let (start, end) = (utc(&period.start), utc(&period.end));
+        let calendar_data = match expand {
+            true => format!(
+                r#"<c:calendar-data>
+      <c:expand start="{start}" end="{end}"/>
+    </c:calendar-data>"#
+            ),
+            false => "<c:calendar-data/>".to_string(),
+        };
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="{DAV}" xmlns:c="{CALDAV}">
+  <d:prop>
+    <d:getetag/>
+    {calendar_data}
+  </d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT">
+        <c:time-range start="{start}" end="{end}"/>
+      </c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#
+        );
+
+        let response = self
+            .request(Method::from_bytes(b"REPORT").unwrap(), collection_url)
+            .headers(xml_headers("1"))
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        objects(&response)
+    }
+
+    /// iCalendarのリソースを作成・上書きし、サーバーが返した新しいETagを返す。
+    /// 条件に合わない場合は412 Precondition Failedのエラーになる
+    pub async fn put(&self, resource_url: &str, calendar: String, precondition: Precondition<'_>) -> Result<Option<String>, Error> {
+        let mut request = self
+            .request(Method::PUT, resource_url)
+            .header(CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(calendar);
+        request = match precondition {
+            Precondition::Absent => request.header(IF_NONE_MATCH, "*"),
+            Precondition::Matches(etag) => request.header(IF_MATCH, etag),
+            Precondition::Any => request,
+        };
+        let response = request.send().await?.error_for_status()?;
+
+        Ok(response.headers().get(ETAG).and_then(|etag| etag.to_str().ok()).map(str::to_string))
+    }
+
+    /// `etag`を指定すると、そのETagから変わっていない場合だけ削除する
+    pub async fn delete(&self, resource_url: &str, etag: Option<&str>) -> Result<(), Error> {
+        let mut request = self.request(Method::DELETE, resource_url);
+        if let Some(etag) = etag {
+            request = request.header(IF_MATCH, etag);
+        }
+        request.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn propfind(&self, url: &str, depth: &'static str, props: &str) -> Result<String, Error> {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="{DAV}" xmlns:c="{CALDAV}">
+  <d:prop>{props}</d:prop>
+</d:propfind>"#
+        );
+
+        Ok(self
+            .request(Method::from_bytes(b"PROPFIND").unwrap(), url)
+            .headers(xml_headers(depth))
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.basic_auth {
+            Some(auth) => request.basic_auth(&auth.user, Some(&auth.password)),
+            None => request,
+        }
+    }
+}
+impl Default for CalDavClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn xml_headers(depth: &'static str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/xml; charset=utf-8"));
+    headers.insert("Depth", HeaderValue::from_static(depth));
+
+    headers
+}
+
+fn parse(xml: &str) -> Result<roxmltree::Document<'_>, Error> {
+    roxmltree::Document::parse(xml).map_err(|e| Error::CalDav(e.to_string()))
+}
+
+/// マルチステータスの応答から、リソースごとのパス・ETag・`calendar-data`を取り出す
+fn objects(multistatus: &str) -> Result<Vec<CalDavObject>, Error> {
+    let document = parse(multistatus)?;
+
+    Ok(document
+        .descendants()
+        .filter(|node| node.has_tag_name((DAV, "response")))
+        .filter_map(|response| {
+            let href = child_text(response, (DAV, "href"))?;
+            let etag = response.descendants().find(|node| node.has_tag_name((DAV, "getetag"))).map(text);
+            let calendar_data = response.descendants().find(|node| node.has_tag_name((CALDAV, "calendar-data"))).map(text)?;
+            Some(CalDavObject { href, etag, calendar_data })
+        })
+        .collect())
+}
+
+/// 子孫のテキストをつなげた内容。`&amp;`などの実体参照は展開済み
+fn text(node: roxmltree::Node) -> String {
+    node.descendants().filter(|child| child.is_text()).filter_map(|text| text.text()).collect()
+}
+
+fn child_text(node: roxmltree::Node, name: (&str, &str)) -> Option<String> {
+    node.children().find(|child| child.has_tag_name(name)).map(|child| text(child).trim().to_string())
+}
+
+fn has_child(node: roxmltree::Node, name: (&str, &str)) -> bool {
+    node.children().any(|child| child.has_tag_name(name))
+}
+
+/// `name`のプロパティが指す`href`
+fn href_in(document: &roxmltree::Document, name: (&str, &str)) -> Option<String> {
+    document
+        .descendants()
+        .find(|node| node.has_tag_name(name))
+        .and_then(|node| child_text(node, (DAV, "href")))
+}
+
+/// 応答の`href`は多くの場合パスだけなので、問い合わせたURLを基準に解決する
+fn resolve(base: &str, href: &str) -> Result<String, Error> {
+    let base = Url::parse(base).map_err(|e| Error::CalDav(format!("{}: {}", base, e)))?;
+    let url = base.join(href).map_err(|e| Error::CalDav(format!("{}: {}", href, e)))?;
+
+    Ok(url.to_string())
+}
+
+fn utc(date_time: &DateTime<Utc>) -> String {
+    date_time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::matchers::header_exists;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::apis::caldav::{objects, CalDavClient, Precondition};
+    use crate::apis::http_client::BasicAuth;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    const MULTISTATUS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/calendars/taro/work/meeting.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:getetag>"1"</d:getetag>
+        <cal:calendar-data>BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:meeting@example.com
+DTSTART:20240510T000000Z
+DTEND:20240510T010000Z
+SUMMARY:定例 &amp; 共有
+END:VEVENT
+END:VCALENDAR
+</cal:calendar-data>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+</d:multistatus>"#;
+
+    #[tokio::test]
+    async fn calendar_query_正常系() {
+        // Setup: 期間を指定したREPORTに、予定を1件返すよう設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("REPORT"))
+            .and(path("/calendars/taro/work/"))
+            .and(header("Depth", "1"))
+            .and(header("Authorization", "Basic dGFybzpzZWNyZXQ="))  // base64("taro:secret")
+            .and(body_string_contains(r#"<c:time-range start="20240501T000000Z" end="20240601T000000Z"/>"#))
+            .respond_with(ResponseTemplate::new(207).set_body_string(MULTISTATUS))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = CalDavClient::new()
+            .with_basic_auth(Some(BasicAuth { user: "taro".to_string(), password: "secret".to_string() }));
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+        );
+
+        // Exercise
+        let result = client.calendar_query(&format!("{}/calendars/taro/work/", mock_server.uri()), &period, true).await;
+
+        // Asserts
+        let objects = result.unwrap();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].href, "/calendars/taro/work/meeting.ics");
+        assert_eq!(objects[0].etag.as_deref(), Some("\"1\""));
+        assert!(objects[0].calendar_data.contains("SUMMARY:定例 & 共有\n"));
+    }
+
+    #[tokio::test]
+    async fn calendar_query_認証エラー() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("REPORT"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+        let period = DateTimeRange::new(Utc::now(), Utc::now() + chrono::Duration::days(1));
+
+        let result = CalDavClient::new().calendar_query(&mock_server.uri(), &period, true).await;
+
+        assert!(matches!(result, Err(crate::error::Error::Auth { .. })));
+    }
+
+    #[tokio::test]
+    async fn discover_calendar_プリンシパルからたどる() {
+        // Setup: ルート → プリンシパル → カレンダーホーム → カレンダーの順に応答するよう設定する
+        let mock_server = MockServer::start().await;
+        let responses = [
+            ("/", "0", r#"<d:response><d:href>/</d:href><d:propstat><d:prop>
+  <d:resourcetype><d:collection/></d:resourcetype>
+  <d:current-user-principal><d:href>/principals/taro/</d:href></d:current-user-principal>
+</d:prop></d:propstat></d:response>"#),
+            ("/principals/taro/", "0", r#"<d:response><d:href>/principals/taro/</d:href><d:propstat><d:prop>
+  <cal:calendar-home-set><d:href>/calendars/taro/</d:href></cal:calendar-home-set>
+</d:prop></d:propstat></d:response>"#),
+            ("/calendars/taro/", "1", r#"<d:response><d:href>/calendars/taro/</d:href><d:propstat><d:prop>
+  <d:resourcetype><d:collection/></d:resourcetype>
+</d:prop></d:propstat></d:response>
+<d:response><d:href>/calendars/taro/personal/</d:href><d:propstat><d:prop>
+  <d:resourcetype><d:collection/><cal:calendar/></d:resourcetype><d:displayname>個人</d:displayname>
+</d:prop></d:propstat></d:response>
+<d:response><d:href>/calendars/taro/work/</d:href><d:propstat><d:prop>
+  <d:resourcetype><d:collection/><cal:calendar/></d:resourcetype><d:displayname>仕事</d:displayname>
+</d:prop></d:propstat></d:response>"#),
+        ];
+        for (request_path, depth, responses) in responses {
+            Mock::given(method("PROPFIND"))
+                .and(path(request_path))
+                .and(header("Depth", depth))
+                .respond_with(ResponseTemplate::new(207).set_body_string(format!(
+                    r#"<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#,
+                    responses
+                )))
+                .mount(&mock_server)
+                .await;
+        }
+        let client = CalDavClient::new();
+
+        // Exercise: 表示名と、省略時のそれぞれで探す
+        let named = client.discover_calendar(&mock_server.uri(), Some("仕事")).await;
+        let first = client.discover_calendar(&mock_server.uri(), None).await;
+
+        // Asserts
+        assert_eq!(named.unwrap(), format!("{}/calendars/taro/work/", mock_server.uri()));
+        assert_eq!(first.unwrap(), format!("{}/calendars/taro/personal/", mock_server.uri()));
+    }
+
+    #[tokio::test]
+    async fn discover_calendar_カレンダーのurlはそのまま使う() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PROPFIND"))
+            .respond_with(ResponseTemplate::new(207).set_body_string(r#"<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response><d:href>/work/</d:href><d:propstat><d:prop>
+    <d:resourcetype><d:collection/><cal:calendar/></d:resourcetype>
+  </d:prop></d:propstat></d:response>
+</d:multistatus>"#))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let url = format!("{}/work/", mock_server.uri());
+
+        let result = CalDavClient::new().discover_calendar(&url, Some("personal")).await;
+
+        assert_eq!(result.unwrap(), url);
+    }
+
+    #[tokio::test]
+    async fn put_前提条件() {
+        // Setup: 新規作成はIf-None-Match、上書きはIf-Matchを付けることを設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(path("/work/new.ics"))
+            .and(header("If-None-Match", "*"))
+            .and(header("Content-Type", "text/calendar; charset=utf-8"))
+            .respond_with(ResponseTemplate::new(201).insert_header("ETag", "\"1\""))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/work/old.ics"))
+            .and(header("If-Match", "\"1\""))
+            .respond_with(ResponseTemplate::new(412))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let client = CalDavClient::new();
+
+        // Exercise
+        let created = client.put(&format!("{}/work/new.ics", mock_server.uri()), "BEGIN:VCALENDAR".to_string(), Precondition::Absent).await;
+        let updated = client.put(&format!("{}/work/old.ics", mock_server.uri()), "BEGIN:VCALENDAR".to_string(), Precondition::Matches("\"1\"")).await;
+
+        // Asserts: 他で変更されていた場合は412のエラーになる
+        assert_eq!(created.unwrap().as_deref(), Some("\"1\""));
+        assert!(matches!(updated, Err(crate::error::Error::Transport(e)) if e.status() == Some(reqwest::StatusCode::PRECONDITION_FAILED)));
+    }
+
+    #[tokio::test]
+    async fn delete_etagを指定() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/work/old.ics"))
+            .and(header_exists("If-Match"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = CalDavClient::new().delete(&format!("{}/work/old.ics", mock_server.uri()), Some("\"1\"")).await;
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_objects_不正なxml() {
+        assert!(objects("<d:multistatus").is_err());
+        assert!(objects(r#"<d:multistatus xmlns:d="DAV:"/>"#).unwrap().is_empty());
+    }
+}
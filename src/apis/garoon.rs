@@ -1,4 +1,9 @@
+use std::fmt;
+use std::str::FromStr;
+
 use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 #[cfg(test)]
 use mockall::automock;
@@ -7,29 +12,554 @@ use crate::utils::date_time_range::DateTimeRange;
 #[cfg_attr(test, automock)]
 #[async_trait]
 pub trait GaroonGetEventsClient {
-    async fn get(&self, request: GaroonGetEventsRequest) -> Result<Vec<GaroonEvent>, reqwest::Error>;
+    async fn get(&self, request: GaroonGetEventsRequest) -> Result<Vec<GaroonEvent>, crate::error::Error>;
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct GaroonGetEventsRequest {
     pub(crate) period: DateTimeRange,
+    /// 省略時はログインユーザーの予定
+    pub(crate) target: Option<GaroonTarget>,
+    pub(crate) options: GaroonGetEventsOptions,
 }
+impl GaroonGetEventsRequest {
+    pub fn new(period: DateTimeRange) -> Self {
+        Self { period, target: None, options: GaroonGetEventsOptions::default() }
+    }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+    pub fn with_target(mut self, target: Option<GaroonTarget>) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn with_options(mut self, options: GaroonGetEventsOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// Garoon側で絞り込む条件。省略した項目はGaroonの既定値になる
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GaroonGetEventsOptions {
+    /// 件名や本文などに含まれる文字列
+    pub keyword: Option<String>,
+    /// `keyword`で検索しない項目
+    pub exclude_from_search: Vec<GaroonSearchField>,
+    /// 取得する項目。予定に変換するため、`id`・`start`・`end`などは含める
+    pub fields: Vec<String>,
+    pub order_by: Option<GaroonOrderBy>,
+    /// 非公開の予定を含めるか
+    pub show_private: Option<bool>,
+    /// 取得する予定の最大件数。ページングの1回あたりの件数ではなく全体の件数
+    pub limit: Option<usize>,
+}
+
+/// `keyword`で検索する項目
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GaroonSearchField {
+    Subject,
+    Company,
+    Notes,
+    Comments,
+}
+impl GaroonSearchField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GaroonSearchField::Subject => "subject",
+            GaroonSearchField::Company => "company",
+            GaroonSearchField::Notes => "notes",
+            GaroonSearchField::Comments => "comments",
+        }
+    }
+}
+
+/// 予定の並び順 (`start asc`など)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GaroonOrderBy {
+    pub field: GaroonOrderField,
+    pub descending: bool,
+}
+impl FromStr for GaroonOrderBy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut words = value.split_whitespace();
+        let field = match words.next() {
+            Some("start") => GaroonOrderField::Start,
+            Some("createdAt") => GaroonOrderField::CreatedAt,
+            Some("updatedAt") => GaroonOrderField::UpdatedAt,
+            _ => return Err(format!("order must start with start, createdAt or updatedAt, got `{}`", value)),
+        };
+        let descending = match (words.next(), words.next()) {
+            (None | Some("asc"), None) => false,
+            (Some("desc"), None) => true,
+            _ => return Err(format!("order direction must be asc or desc, got `{}`", value)),
+        };
+
+        Ok(Self { field, descending })
+    }
+}
+impl fmt::Display for GaroonOrderBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self.field {
+            GaroonOrderField::Start => "start",
+            GaroonOrderField::CreatedAt => "createdAt",
+            GaroonOrderField::UpdatedAt => "updatedAt",
+        };
+        write!(f, "{} {}", field, if self.descending { "desc" } else { "asc" })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GaroonOrderField {
+    Start,
+    CreatedAt,
+    UpdatedAt,
+}
+
+/// 予定を取得する対象のユーザー・組織・施設
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GaroonTarget {
+    pub(crate) target_type: GaroonTargetType,
+    pub(crate) id: String,
+}
+impl GaroonTarget {
+    pub fn new(target_type: GaroonTargetType, id: String) -> Self {
+        Self { target_type, id }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GaroonTargetType {
+    #[default]
+    User,
+    Organization,
+    Facility,
+}
+impl GaroonTargetType {
+    /// `targetType`パラメーターの値
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GaroonTargetType::User => "user",
+            GaroonTargetType::Organization => "organization",
+            GaroonTargetType::Facility => "facility",
+        }
+    }
+}
+
+/// 基本情報API (`/api/v1/base/users`など) が返すユーザー・組織・施設の1件
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GaroonEntity {
+    pub(crate) id: String,
+    /// ユーザーならログイン名、組織・施設ならコード
+    pub(crate) code: String,
+    pub(crate) name: String,
+}
+
+/// Garoonの予定 (`/api/v1/schedule/events`のレスポンスの1件)。
+/// 予定ID・件名・開始日時は必須とし、欠けた予定は空のIDなどで補わずにエラーにする
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub struct GaroonEvent {
+    pub(crate) id: String,
+    pub(crate) creator: Option<GaroonUser>,
+    pub(crate) created_at: Option<DateTime<Utc>>,
+    pub(crate) updater: Option<GaroonUser>,
+    pub(crate) updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub(crate) event_type: GaroonEventType,
+    #[serde(default)]
+    pub(crate) event_menu: String,
     pub(crate) subject: String,
+    #[serde(default)]
+    pub(crate) notes: String,
+    #[serde(default)]
+    pub(crate) visibility_type: GaroonVisibilityType,
+    #[serde(default)]
+    pub(crate) is_all_day: bool,
+    #[serde(default)]
+    pub(crate) is_start_only: bool,
+    #[serde(default)]
     pub(crate) attendees: Vec<GaroonAttendee>,
+    #[serde(default)]
+    pub(crate) watchers: Vec<GaroonWatcher>,
+    #[serde(default)]
+    pub(crate) facilities: Vec<GaroonFacility>,
+    pub(crate) company_info: Option<GaroonCompanyInfo>,
+    #[serde(default)]
+    pub(crate) attachments: Vec<GaroonAttachment>,
+    pub(crate) repeat_info: Option<GaroonRepeatInfo>,
+    pub(crate) additional_items: Option<GaroonAdditionalItems>,
     pub(crate) start: GaroonDateTime,
-    pub(crate) end: GaroonDateTime,
+    /// 開始時刻のみの予定では返らないことがある
+    pub(crate) end: Option<GaroonDateTime>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GaroonEventType {
+    #[default]
+    Regular,
+    Repeating,
+    Temporary,
+    AllDay,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GaroonVisibilityType {
+    #[default]
+    Public,
+    Private,
+    SetPrivateWatchers,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GaroonUser {
+    pub(crate) id: String,
+    pub(crate) code: String,
+    pub(crate) name: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GaroonMemberType {
+    #[default]
+    User,
+    Organization,
+    Role,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
 pub struct GaroonAttendee {
+    pub(crate) id: String,
+    pub(crate) code: String,
+    pub(crate) name: String,
+    #[serde(rename = "type")]
+    pub(crate) attendee_type: GaroonMemberType,
+    pub(crate) attendance_response: Option<GaroonAttendanceResponse>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GaroonAttendanceResponse {
+    pub(crate) status: GaroonAttendanceStatus,
+    pub(crate) comment: String,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GaroonAttendanceStatus {
+    #[default]
+    Pending,
+    Accepted,
+    Declined,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GaroonWatcher {
+    pub(crate) id: String,
+    pub(crate) code: String,
+    pub(crate) name: String,
+    #[serde(rename = "type")]
+    pub(crate) watcher_type: GaroonMemberType,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GaroonFacility {
+    pub(crate) id: String,
+    pub(crate) code: String,
     pub(crate) name: String,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GaroonCompanyInfo {
+    pub(crate) name: String,
+    pub(crate) zip_code: String,
+    pub(crate) address: String,
+    pub(crate) route: String,
+    pub(crate) route_time: String,
+    pub(crate) route_fare: String,
+    pub(crate) phone: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GaroonAttachment {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) content_type: String,
+    pub(crate) size: String,
+}
+
+/// 繰り返し予定の条件
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GaroonRepeatInfo {
+    #[serde(rename = "type")]
+    pub(crate) repeat_type: GaroonRepeatType,
+    pub(crate) period: GaroonRepeatPeriod,
+    #[serde(default)]
+    pub(crate) time: Option<GaroonRepeatTime>,
+    #[serde(default)]
+    pub(crate) time_zone: String,
+    #[serde(default)]
+    pub(crate) is_all_day: bool,
+    #[serde(default)]
+    pub(crate) is_start_only: bool,
+    #[serde(default)]
+    pub(crate) day_of_week: Option<GaroonDayOfWeek>,
+    #[serde(default)]
+    pub(crate) day_of_month: Option<GaroonDayOfMonth>,
+    #[serde(default)]
+    pub(crate) exclusive_date_times: Vec<GaroonExclusiveDateTime>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GaroonRepeatType {
+    #[serde(rename = "EVERY_DAY")]
+    EveryDay,
+    #[serde(rename = "EVERY_WEEKDAY")]
+    EveryWeekday,
+    #[serde(rename = "EVERY_WEEK")]
+    EveryWeek,
+    #[serde(rename = "EVERY_1STWEEK")]
+    Every1stWeek,
+    #[serde(rename = "EVERY_2NDWEEK")]
+    Every2ndWeek,
+    #[serde(rename = "EVERY_3RDWEEK")]
+    Every3rdWeek,
+    #[serde(rename = "EVERY_4THWEEK")]
+    Every4thWeek,
+    #[serde(rename = "EVERY_LASTWEEK")]
+    EveryLastWeek,
+    #[serde(rename = "EVERY_MONTH")]
+    EveryMonth,
+    #[serde(rename = "EVERY_YEAR")]
+    EveryYear,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GaroonRepeatPeriod {
+    pub(crate) start: NaiveDate,
+    pub(crate) end: NaiveDate,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GaroonRepeatTime {
+    pub(crate) start: NaiveTime,
+    #[serde(default)]
+    pub(crate) end: Option<NaiveTime>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GaroonDayOfWeek {
+    Sun,
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+}
+
+/// 毎月の繰り返しの日付。`"1"`〜`"31"`または`"END_OF_MONTH"`
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub enum GaroonDayOfMonth {
+    Day(u32),
+    EndOfMonth,
+}
+impl TryFrom<String> for GaroonDayOfMonth {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if value == "END_OF_MONTH" {
+            return Ok(Self::EndOfMonth);
+        }
+
+        match value.parse::<u32>() {
+            Ok(day) if (1..=31).contains(&day) => Ok(Self::Day(day)),
+            _ => Err(format!("invalid dayOfMonth: {}", value)),
+        }
+    }
+}
+impl From<GaroonDayOfMonth> for String {
+    fn from(value: GaroonDayOfMonth) -> Self {
+        match value {
+            GaroonDayOfMonth::Day(day) => day.to_string(),
+            GaroonDayOfMonth::EndOfMonth => "END_OF_MONTH".to_string(),
+        }
+    }
+}
+
+/// 繰り返し予定から除外された日時
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GaroonExclusiveDateTime {
+    pub(crate) start: DateTime<FixedOffset>,
+    pub(crate) end: DateTime<FixedOffset>,
+}
+
+/// 予定の任意項目
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GaroonAdditionalItems {
+    pub(crate) item: Option<GaroonAdditionalItem>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GaroonAdditionalItem {
+    pub(crate) value: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub struct GaroonDateTime {
-    pub(crate) dateTime: String,
-    pub(crate) timeZone: String,
+    pub(crate) date_time: String,
+    pub(crate) time_zone: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+
+    use crate::apis::garoon::*;
+
+    #[test]
+    fn test_deserialize_garoon_event() {
+        let json = r#"{
+            "id": "123",
+            "creator": {"id": "1", "code": "yamada", "name": "山田太郎"},
+            "createdAt": "2024-05-01T01:00:00Z",
+            "updater": {"id": "2", "code": "suzuki", "name": "鈴木花子"},
+            "updatedAt": "2024-05-02T01:00:00Z",
+            "eventType": "REPEATING",
+            "eventMenu": "会議",
+            "subject": "定例",
+            "notes": "議事録は共有フォルダへ",
+            "visibilityType": "SET_PRIVATE_WATCHERS",
+            "useAttendanceCheck": false,
+            "companyInfo": {"name": "サイボウズ", "zipCode": "103-0027", "address": "東京都", "route": "", "routeTime": "", "routeFare": "", "phone": ""},
+            "attachments": [{"id": "10", "name": "資料.pdf", "contentType": "application/pdf", "size": "1024"}],
+            "start": {"dateTime": "2024-05-10T09:00:00+09:00", "timeZone": "Asia/Tokyo"},
+            "end": {"dateTime": "2024-05-10T10:00:00+09:00", "timeZone": "Asia/Tokyo"},
+            "isAllDay": false,
+            "isStartOnly": false,
+            "originalStartTimeZone": "Asia/Tokyo",
+            "originalEndTimeZone": "Asia/Tokyo",
+            "attendees": [
+                {"id": "1", "code": "yamada", "name": "山田太郎", "type": "USER", "attendanceResponse": {"status": "ACCEPTED", "comment": ""}},
+                {"id": "5", "code": "dev", "name": "開発部", "type": "ORGANIZATION"}
+            ],
+            "watchers": [{"id": "3", "code": "sato", "name": "佐藤", "type": "USER"}],
+            "facilities": [{"id": "7", "code": "room1", "name": "会議室A"}],
+            "repeatInfo": {
+                "type": "EVERY_WEEK",
+                "period": {"start": "2024-04-01", "end": "2024-09-30"},
+                "time": {"start": "09:00:00", "end": "10:00:00"},
+                "timeZone": "Asia/Tokyo",
+                "isAllDay": false,
+                "isStartOnly": false,
+                "dayOfWeek": "FRI",
+                "exclusiveDateTimes": [{"start": "2024-05-03T00:00:00+09:00", "end": "2024-05-04T00:00:00+09:00"}]
+            },
+            "additionalItems": {"item": {"value": "任意項目"}}
+        }"#;
+
+        let event: GaroonEvent = serde_json::from_str(json).unwrap();
+
+        assert_eq!(event.id, "123".to_string());
+        assert_eq!(event.creator.unwrap().code, "yamada".to_string());
+        assert_eq!(event.updated_at, Some(Utc.with_ymd_and_hms(2024, 5, 2, 1, 0, 0).unwrap()));
+        assert_eq!(event.event_type, GaroonEventType::Repeating);
+        assert_eq!(event.event_menu, "会議".to_string());
+        assert_eq!(event.notes, "議事録は共有フォルダへ".to_string());
+        assert_eq!(event.visibility_type, GaroonVisibilityType::SetPrivateWatchers);
+        assert_eq!(event.company_info.unwrap().zip_code, "103-0027".to_string());
+        assert_eq!(event.attachments[0].content_type, "application/pdf".to_string());
+        assert_eq!(event.attendees[0].attendance_response.as_ref().unwrap().status, GaroonAttendanceStatus::Accepted);
+        assert_eq!(event.attendees[1].attendee_type, GaroonMemberType::Organization);
+        assert_eq!(event.watchers[0].name, "佐藤".to_string());
+        assert_eq!(event.facilities[0].name, "会議室A".to_string());
+        assert_eq!(event.additional_items.unwrap().item.unwrap().value, "任意項目".to_string());
+
+        let repeat_info = event.repeat_info.unwrap();
+        assert_eq!(repeat_info.repeat_type, GaroonRepeatType::EveryWeek);
+        assert_eq!(repeat_info.period.end, NaiveDate::from_ymd_opt(2024, 9, 30).unwrap());
+        assert_eq!(repeat_info.time.unwrap().start, NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+        assert_eq!(repeat_info.day_of_week, Some(GaroonDayOfWeek::Fri));
+        assert_eq!(repeat_info.exclusive_date_times.len(), 1);
+    }
+
+    #[test]
+    fn test_deserialize_garoon_event_未知の値() {
+        let json = r#"{
+            "id": "1",
+            "eventType": "NEW_TYPE",
+            "subject": "会議",
+            "start": {"dateTime": "2024-05-10T09:00:00+09:00", "timeZone": "Asia/Tokyo"},
+            "end": {"dateTime": "2024-05-10T10:00:00+09:00", "timeZone": "Asia/Tokyo"}
+        }"#;
+
+        let event: GaroonEvent = serde_json::from_str(json).unwrap();
+
+        assert_eq!(event.event_type, GaroonEventType::Unknown);
+        assert_eq!(event.visibility_type, GaroonVisibilityType::Public);
+        assert!(event.facilities.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_garoon_event_必須項目がない() {
+        let json = r#"{
+            "subject": "会議",
+            "start": {"dateTime": "2024-05-10T09:00:00+09:00", "timeZone": "Asia/Tokyo"}
+        }"#;
+
+        let result = serde_json::from_str::<GaroonEvent>(json);
+
+        assert!(result.unwrap_err().to_string().contains("missing field `id`"));
+    }
+
+    #[test]
+    fn test_day_of_month() {
+        assert_eq!(serde_json::from_str::<GaroonDayOfMonth>(r#""15""#).unwrap(), GaroonDayOfMonth::Day(15));
+        assert_eq!(serde_json::from_str::<GaroonDayOfMonth>(r#""END_OF_MONTH""#).unwrap(), GaroonDayOfMonth::EndOfMonth);
+        assert!(serde_json::from_str::<GaroonDayOfMonth>(r#""32""#).is_err());
+        assert_eq!(serde_json::to_string(&GaroonDayOfMonth::Day(1)).unwrap(), r#""1""#);
+    }
+
+    #[test]
+    fn test_order_by() {
+        let order_by: GaroonOrderBy = "updatedAt desc".parse().unwrap();
+        assert_eq!(order_by, GaroonOrderBy { field: GaroonOrderField::UpdatedAt, descending: true });
+        assert_eq!(order_by.to_string(), "updatedAt desc");
+
+        assert_eq!("start".parse::<GaroonOrderBy>().unwrap().to_string(), "start asc");
+        assert!("subject asc".parse::<GaroonOrderBy>().is_err());
+        assert!("start up".parse::<GaroonOrderBy>().is_err());
+    }
 }
@@ -0,0 +1,417 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use base64::prelude::*;
+use chrono::{Duration, Utc};
+use reqwest::header::{COOKIE, SET_COOKIE};
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::models::oauth_token::OAuthToken;
+use crate::repositories::token_repository::TokenRepository;
+
+/// Garoon APIへのリクエストに認証情報を付ける方法
+#[async_trait]
+pub trait GaroonAuthStrategy: Send + Sync {
+    async fn authorize(&self, client: &Client, request: RequestBuilder) -> Result<RequestBuilder, Error>;
+
+    /// 認証エラーが返ったときに認証情報を取り直す。取り直して再試行できる場合はtrueを返す
+    async fn refresh(&self, client: &Client) -> Result<bool, Error>;
+}
+
+/// パスワード認証 (`X-Cybozu-Authorization`ヘッダー)
+pub struct GaroonAuth {
+    pub user_id: String,
+    pub password: String,
+}
+impl GaroonAuth {
+    pub fn cyboze_authorization(&self) -> String {
+        let auth = format!("{}:{}", self.user_id, self.password);
+        BASE64_STANDARD.encode(auth.as_bytes())
+    }
+}
+#[async_trait]
+impl GaroonAuthStrategy for GaroonAuth {
+    async fn authorize(&self, _client: &Client, request: RequestBuilder) -> Result<RequestBuilder, Error> {
+        Ok(request.header("X-Cybozu-Authorization", self.cyboze_authorization()))
+    }
+
+    async fn refresh(&self, _client: &Client) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
+
+/// ログインして得たセッションCookieによる認証
+pub struct GaroonSessionAuth {
+    login_url: String,
+    user_id: String,
+    password: String,
+    cookie: Mutex<Option<String>>,
+}
+impl GaroonSessionAuth {
+    pub fn new(login_url: String, user_id: String, password: String) -> Self {
+        Self { login_url, user_id, password, cookie: Mutex::new(None) }
+    }
+
+    async fn login(&self, client: &Client) -> Result<String, Error> {
+        let response = client
+            .post(&self.login_url)
+            .json(&serde_json::json!({ "username": self.user_id, "password": self.password }))
+            .send()
+            .await?
+            .error_for_status()?;
+        let cookies: Vec<&str> = response
+            .headers()
+            .get_all(SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| value.split(';').next())
+            .collect();
+        if cookies.is_empty() {
+            return Err(Error::auth("login response did not set a session cookie"));
+        }
+
+        let cookie = cookies.join("; ");
+        *self.cookie.lock().unwrap() = Some(cookie.clone());
+
+        Ok(cookie)
+    }
+}
+#[async_trait]
+impl GaroonAuthStrategy for GaroonSessionAuth {
+    async fn authorize(&self, client: &Client, request: RequestBuilder) -> Result<RequestBuilder, Error> {
+        let cookie = self.cookie.lock().unwrap().clone();
+        let cookie = match cookie {
+            Some(cookie) => cookie,
+            None => self.login(client).await?,
+        };
+
+        // セッション認証では、CSRF対策としてXMLHttpRequestであることを示す必要がある
+        Ok(request.header(COOKIE, cookie).header("X-Requested-With", "XMLHttpRequest"))
+    }
+
+    async fn refresh(&self, client: &Client) -> Result<bool, Error> {
+        self.login(client).await?;
+        Ok(true)
+    }
+}
+
+/// GaroonのOAuth 2.0クライアントの設定
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GaroonOAuth2Config {
+    pub authorization_url: String,
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub scope: String,
+}
+
+/// OAuth 2.0 (認可コードフロー) による認証。トークンは期限切れや認証エラーのたびにリフレッシュして保存する
+pub struct GaroonOAuth2Auth {
+    config: GaroonOAuth2Config,
+    token_repository: Box<dyn TokenRepository + Send + Sync>,
+    token: Mutex<Option<OAuthToken>>,
+}
+impl GaroonOAuth2Auth {
+    pub fn new(config: GaroonOAuth2Config, token_repository: impl TokenRepository + Send + Sync + 'static) -> Self {
+        Self { config, token_repository: Box::new(token_repository), token: Mutex::new(None) }
+    }
+
+    /// ブラウザで開いて認可する画面のURL
+    pub fn authorization_url(&self, state: &str) -> Result<Url, Error> {
+        Url::parse_with_params(&self.config.authorization_url, [
+            ("client_id", self.config.client_id.as_str()),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+            ("response_type", "code"),
+            ("scope", self.config.scope.as_str()),
+            ("state", state),
+        ])
+            .map_err(|e| Error::Config(format!("invalid OAuth authorization url: {}", e)))
+    }
+
+    /// 認可コードをトークンに交換して保存する
+    pub async fn exchange_code(&self, client: &Client, code: &str) -> Result<OAuthToken, Error> {
+        self.request_token(client, &[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_uri.as_str()),
+        ], None)
+            .await
+    }
+
+    async fn refresh_token(&self, client: &Client, refresh_token: &str) -> Result<OAuthToken, Error> {
+        self.request_token(client, &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ], Some(refresh_token))
+            .await
+    }
+
+    async fn request_token(&self, client: &Client, params: &[(&str, &str)], refresh_token: Option<&str>) -> Result<OAuthToken, Error> {
+        let mut form = vec![("client_id", self.config.client_id.as_str()), ("client_secret", self.config.client_secret.as_str())];
+        form.extend_from_slice(params);
+        let response = client
+            .post(&self.config.token_url)
+            .form(&form)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::auth(format!("token request failed with {}: {}", status, body)));
+        }
+
+        let response = response.json::<TokenResponse>().await?;
+        let token = OAuthToken::new(
+            response.access_token,
+            // リフレッシュトークンが再発行されない場合は、手元のものを使い続ける
+            response.refresh_token.or(refresh_token.map(str::to_string)),
+            response.expires_in.map(|seconds| Utc::now() + Duration::seconds(seconds)),
+        );
+        self.token_repository
+            .save(&token)
+            .map_err(|e| Error::Auth { message: "failed to save the OAuth token".to_string(), source: Some(e.into()) })?;
+        *self.token.lock().unwrap() = Some(token.clone());
+
+        Ok(token)
+    }
+
+    fn current_token(&self) -> Result<Option<OAuthToken>, Error> {
+        let mut token = self.token.lock().unwrap();
+        if token.is_none() {
+            *token = self
+                .token_repository
+                .load()
+                .map_err(|e| Error::Auth { message: "failed to load the OAuth token".to_string(), source: Some(e.into()) })?;
+        }
+
+        Ok(token.clone())
+    }
+}
+#[async_trait]
+impl GaroonAuthStrategy for GaroonOAuth2Auth {
+    async fn authorize(&self, client: &Client, request: RequestBuilder) -> Result<RequestBuilder, Error> {
+        let token = self
+            .current_token()?
+            .ok_or_else(|| Error::auth("no OAuth token is stored; run `tensha auth` first"))?;
+        let token = match &token.refresh_token {
+            Some(refresh_token) if token.is_expired(Utc::now()) => self.refresh_token(client, refresh_token).await?,
+            _ => token,
+        };
+
+        Ok(request.bearer_auth(token.access_token))
+    }
+
+    async fn refresh(&self, client: &Client) -> Result<bool, Error> {
+        let Some(refresh_token) = self.current_token()?.and_then(|token| token.refresh_token) else {
+            return Ok(false);
+        };
+
+        self.refresh_token(client, &refresh_token).await?;
+        Ok(true)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use reqwest::Client;
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::apis::garoon_auth::*;
+    use crate::models::oauth_token::OAuthToken;
+    use crate::repositories::token_repository::MockTokenRepository;
+
+    fn oauth_config(mock_server: &MockServer) -> GaroonOAuth2Config {
+        GaroonOAuth2Config {
+            authorization_url: format!("{}/oauth2/authorization", mock_server.uri()),
+            token_url: format!("{}/oauth2/token", mock_server.uri()),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_uri: "http://localhost:8080/callback".to_string(),
+            scope: "g:Schedule:read".to_string(),
+        }
+    }
+
+    async fn mount_echo(mock_server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path("/echo"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn authorize_パスワード認証() {
+        let auth = GaroonAuth { user_id: "user".to_string(), password: "password".to_string() };
+        let client = Client::new();
+
+        let request = auth.authorize(&client, client.get("https://example.com")).await.unwrap().build().unwrap();
+
+        assert_eq!(request.headers().get("X-Cybozu-Authorization").unwrap(), "dXNlcjpwYXNzd29yZA==");  // base64("user:password")
+        assert!(!auth.refresh(&client).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn authorize_期限切れのトークンをリフレッシュする() {
+        // Setup: 期限切れのトークンが保存されており、リフレッシュで新しいトークンが返るよう設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/oauth2/token"))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .and(body_string_contains("refresh_token=old-refresh"))
+            .and(body_string_contains("client_id=client"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "new-access",
+                "token_type": "Bearer",
+                "expires_in": 3600,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_load().times(1).returning(|| Ok(Some(OAuthToken::new(
+            "old-access".to_string(),
+            Some("old-refresh".to_string()),
+            Some(Utc::now() - Duration::minutes(5)),
+        ))));
+        // 新しいアクセストークンと、引き継いだリフレッシュトークンが保存されることを設定する
+        token_repository.expect_save()
+            .withf(|token| token.access_token == "new-access" && token.refresh_token.as_deref() == Some("old-refresh"))
+            .times(1)
+            .returning(|_| Ok(()));
+        let auth = GaroonOAuth2Auth::new(oauth_config(&mock_server), token_repository);
+        let client = Client::new();
+
+        // Exercise: リクエストに認証情報を付ける
+        let request = auth.authorize(&client, client.get("https://example.com")).await.unwrap().build().unwrap();
+
+        // Asserts
+        assert_eq!(request.headers().get("Authorization").unwrap(), "Bearer new-access");
+    }
+
+    #[tokio::test]
+    async fn refresh_リフレッシュトークンが無効() {
+        // Setup: リフレッシュトークンが失効している
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/oauth2/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_string(r#"{"error":"invalid_grant"}"#))
+            .mount(&mock_server)
+            .await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_load().returning(|| Ok(Some(OAuthToken::new(
+            "access".to_string(),
+            Some("revoked".to_string()),
+            None,
+        ))));
+        let auth = GaroonOAuth2Auth::new(oauth_config(&mock_server), token_repository);
+
+        // Exercise
+        let result = auth.refresh(&Client::new()).await;
+
+        // Asserts
+        assert!(matches!(result, Err(Error::Auth { message, .. }) if message.contains("invalid_grant")));
+    }
+
+    #[tokio::test]
+    async fn exchange_code_正常系() {
+        // Setup
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/oauth2/token"))
+            .and(body_string_contains("grant_type=authorization_code"))
+            .and(body_string_contains("code=abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "access",
+                "refresh_token": "refresh",
+                "expires_in": 3600,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_save().times(1).returning(|_| Ok(()));
+        let auth = GaroonOAuth2Auth::new(oauth_config(&mock_server), token_repository);
+
+        // Exercise
+        let token = auth.exchange_code(&Client::new(), "abc").await.unwrap();
+
+        // Asserts
+        assert_eq!(token.access_token, "access");
+        assert_eq!(token.refresh_token, Some("refresh".to_string()));
+        assert!(!token.is_expired(Utc::now()));
+    }
+
+    #[test]
+    fn test_authorization_url() {
+        let config = GaroonOAuth2Config {
+            authorization_url: "https://example.cybozu.com/oauth2/authorization".to_string(),
+            token_url: "https://example.cybozu.com/oauth2/token".to_string(),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_uri: "http://localhost:8080/callback".to_string(),
+            scope: "g:Schedule:read".to_string(),
+        };
+        let auth = GaroonOAuth2Auth::new(config, MockTokenRepository::new());
+
+        let url = auth.authorization_url("xyz").unwrap();
+
+        assert_eq!(
+            url.as_str(),
+            "https://example.cybozu.com/oauth2/authorization?client_id=client&redirect_uri=http%3A%2F%2Flocalhost%3A8080%2Fcallback&response_type=code&scope=g%3ASchedule%3Aread&state=xyz"
+        );
+    }
+
+    #[tokio::test]
+    async fn authorize_セッション認証() {
+        // Setup: ログインでセッションCookieが返るよう設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/auth/login.json"))
+            .respond_with(ResponseTemplate::new(200).append_header("Set-Cookie", "JSESSIONID=abc; Path=/; HttpOnly"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/echo"))
+            .and(header("Cookie", "JSESSIONID=abc"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        let auth = GaroonSessionAuth::new(format!("{}/api/auth/login.json", mock_server.uri()), "user".to_string(), "password".to_string());
+        let client = Client::new();
+        let url = format!("{}/echo", mock_server.uri());
+
+        // Exercise: 2回リクエストしても、ログインは1回だけ行う
+        for _ in 0..2 {
+            let response = auth.authorize(&client, client.get(&url)).await.unwrap().send().await.unwrap();
+            assert!(response.status().is_success());
+        }
+    }
+
+    #[tokio::test]
+    async fn authorize_トークン未保存() {
+        let mock_server = MockServer::start().await;
+        mount_echo(&mock_server).await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_load().returning(|| Ok(None));
+        let auth = GaroonOAuth2Auth::new(oauth_config(&mock_server), token_repository);
+        let client = Client::new();
+
+        let result = auth.authorize(&client, client.get(format!("{}/echo", mock_server.uri()))).await;
+
+        assert!(matches!(result, Err(Error::Auth { message, .. }) if message.contains("tensha auth")));
+    }
+}
@@ -1,89 +1,251 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use base64::prelude::*;
-use chrono::SecondsFormat;
-use reqwest::Client;
+use chrono::{SecondsFormat, Utc};
+use futures::{stream, Stream, TryStreamExt};
+use reqwest::{Client, Response, StatusCode};
 use reqwest::header::{ACCEPT, HeaderMap};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
 
-use crate::apis::garoon::{GaroonGetEventsClient, GaroonEvent, GaroonGetEventsRequest};
-
-pub struct GaroonAuth {
-    pub user_id: String,
-    pub password: String,
-}
-impl GaroonAuth {
-    pub fn cyboze_authorization(&self) -> String {
-        let auth = format!("{}:{}", self.user_id, self.password);
-        BASE64_STANDARD.encode(auth.as_bytes())
-    }
-}
+use crate::apis::garoon::{GaroonEntity, GaroonGetEventsClient, GaroonEvent, GaroonGetEventsRequest, GaroonSearchField, GaroonTarget, GaroonTargetType};
+use crate::apis::garoon_auth::GaroonAuthStrategy;
+use crate::apis::retry::RetryPolicy;
+use crate::error::{Error, GaroonApiError};
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub struct GaroonEventResponse {
     events: Vec<GaroonEvent>,
+    #[serde(default)]
+    has_next: bool,
+}
+
+/// ユーザー・組織・施設の一覧。APIごとに配列の名前が異なる
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GaroonEntityResponse {
+    #[serde(alias = "users", alias = "organizations", alias = "facilities")]
+    entities: Vec<GaroonEntity>,
+    has_next: bool,
 }
 
+/// Garoonの予定取得APIが1回で返す件数の既定値
+const DEFAULT_PAGE_SIZE: usize = 100;
+
 pub struct GaroonRestClient {
     client: Client,
     base_url: String,
-    auth: GaroonAuth,
+    auth: Arc<dyn GaroonAuthStrategy>,
+    page_size: usize,
+    retry_policy: RetryPolicy,
 }
 impl GaroonRestClient {
-    pub fn new(base_url: String, auth: GaroonAuth) -> Self {
+    pub fn new(base_url: String, auth: impl GaroonAuthStrategy + 'static) -> Self {
         Self {
             client: Client::new(),
             base_url,
-            auth,
+            auth: Arc::new(auth),
+            page_size: DEFAULT_PAGE_SIZE,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Basic認証やクライアント証明書を設定したクライアントを使う
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// 予定をページ単位で取得しながら1件ずつ返す。期間が長く件数が多い場合に使う
+    pub fn stream(&self, request: GaroonGetEventsRequest) -> impl Stream<Item = Result<GaroonEvent, Error>> + '_ {
+        stream::try_unfold(Some(0), move |offset| {
+            let request = request.clone();
+            async move {
+                let Some(offset) = offset else {
+                    return Ok::<_, Error>(None);
+                };
+
+                let mut page = self.get_page(&request, offset).await?;
+                let remaining = request.options.limit.map(|limit| limit.saturating_sub(offset));
+                if let Some(remaining) = remaining {
+                    page.events.truncate(remaining);
+                }
+                // hasNextがtrueのまま空のページが返っても無限に取得し続けないようにする
+                let next_offset = offset + page.events.len();
+                let has_next = page.has_next
+                    && !page.events.is_empty()
+                    && remaining.is_none_or(|remaining| page.events.len() < remaining);
+
+                Ok(Some((stream::iter(page.events.into_iter().map(Ok)), has_next.then_some(next_offset))))
+            }
+        })
+        .try_flatten()
+    }
+
+    async fn get_page(&self, request: &GaroonGetEventsRequest, offset: usize) -> Result<GaroonEventResponse, Error> {
+        // 最大件数の指定があれば、残りの件数より多くは取得しない
+        let limit = match request.options.limit {
+            Some(limit) => self.page_size.min(limit.saturating_sub(offset)),
+            None => self.page_size,
+        };
+        let mut query = self.events_query(request);
+        query.extend(self.page_query(offset, limit));
+
+        self.get_json(self.get_events_path(), &query).await
+    }
+
+    /// ユーザーのログイン名や組織・施設のコードから、予定の取得対象を求める
+    pub async fn find_target(&self, target_type: GaroonTargetType, code: &str) -> Result<GaroonTarget, Error> {
+        let mut offset = 0;
+        loop {
+            let mut query = vec![("name", code.to_string())];
+            query.extend(self.page_query(offset, self.page_size));
+            let page: GaroonEntityResponse = self.get_json(self.lookup_path(target_type), &query).await?;
+
+            // nameは表示名などとの部分一致なので、コードが完全に一致するものを探す
+            if let Some(entity) = page.entities.iter().find(|entity| entity.code == code) {
+                return Ok(GaroonTarget::new(target_type, entity.id.clone()));
+            }
+            if !page.has_next || page.entities.is_empty() {
+                return Err(Error::Config(format!("{} `{}` was not found in Garoon", target_type.as_str(), code)));
+            }
+            offset += page.entities.len();
         }
     }
 
+    /// 一時的なエラーは再試行の方針に従って再試行する
+    async fn get_json<T: DeserializeOwned>(&self, path: &str, query: &[(&str, String)]) -> Result<T, Error> {
+        let mut attempt = 1;
+        loop {
+            let retry_after = match self.send_authorized(path, query).await {
+                Ok(response) if response.status().is_success() => return Ok(response.json::<T>().await?),
+                Ok(response) if RetryPolicy::is_retryable_status(response.status()) && self.retry_policy.can_retry(attempt) => {
+                    RetryPolicy::retry_after(response.headers(), Utc::now())
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    return Err(GaroonApiError::from_body(status, &body).into());
+                }
+                Err(Error::Transport(e)) if RetryPolicy::is_retryable_error(&e) && self.retry_policy.can_retry(attempt) => None,
+                Err(e) => return Err(e),
+            };
+
+            sleep(self.retry_policy.delay(attempt, retry_after)).await;
+            attempt += 1;
+        }
+    }
+
+    /// セッション切れやトークンの失効なら、認証情報を取り直して1回だけ再試行する
+    async fn send_authorized(&self, path: &str, query: &[(&str, String)]) -> Result<Response, Error> {
+        let response = self.send(path, query).await?;
+        if response.status() == StatusCode::UNAUTHORIZED && self.auth.refresh(&self.client).await? {
+            return self.send(path, query).await;
+        }
+
+        Ok(response)
+    }
+
+    async fn send(&self, path: &str, query: &[(&str, String)]) -> Result<Response, Error> {
+        let url = format!("{}{}", self.base_url, path);
+        let builder = self.client
+            .get(&url)
+            .headers(self.headers())
+            .query(query);
+
+        Ok(self.auth.authorize(&self.client, builder).await?.send().await?)
+    }
+
     fn get_events_path(&self) -> &str {
         "/api/v1/schedule/events"
     }
 
+    fn lookup_path(&self, target_type: GaroonTargetType) -> &str {
+        match target_type {
+            GaroonTargetType::User => "/api/v1/base/users",
+            GaroonTargetType::Organization => "/api/v1/base/organizations",
+            GaroonTargetType::Facility => "/api/v1/schedule/facilities",
+        }
+    }
+
     fn headers(&self) -> HeaderMap {
         let mut headers = HeaderMap::new();
         headers.insert(ACCEPT, "application/json; charset=UTF-8".parse().unwrap());
-        headers.insert("X-Cybozu-Authorization", self.auth.cyboze_authorization().parse().unwrap());
 
         headers
     }
 
-    fn range_query(&self, request: GaroonGetEventsRequest) -> Vec<(&str, String)> {
-        vec![
+    fn events_query(&self, request: &GaroonGetEventsRequest) -> Vec<(&'static str, String)> {
+        let mut query = vec![
             ("rangeStart", request.period.start.to_rfc3339_opts(SecondsFormat::Secs, true)),
             ("rangeEnd", request.period.end.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ];
+        if let Some(target) = &request.target {
+            query.push(("target", target.id.clone()));
+            query.push(("targetType", target.target_type.as_str().to_string()));
+        }
+
+        let options = &request.options;
+        if let Some(keyword) = &options.keyword {
+            query.push(("keyword", keyword.clone()));
+        }
+        if !options.exclude_from_search.is_empty() {
+            let fields: Vec<&str> = options.exclude_from_search.iter().map(GaroonSearchField::as_str).collect();
+            query.push(("excludeFromSearch", fields.join(",")));
+        }
+        if !options.fields.is_empty() {
+            query.push(("fields", options.fields.join(",")));
+        }
+        if let Some(order_by) = &options.order_by {
+            query.push(("orderBy", order_by.to_string()));
+        }
+        if let Some(show_private) = options.show_private {
+            query.push(("showPrivate", show_private.to_string()));
+        }
+
+        query
+    }
+
+    fn page_query(&self, offset: usize, limit: usize) -> Vec<(&'static str, String)> {
+        vec![
+            ("limit", limit.to_string()),
+            ("offset", offset.to_string()),
         ]
     }
 }
 #[async_trait]
 impl GaroonGetEventsClient for GaroonRestClient {
-    async fn get(&self, request: GaroonGetEventsRequest) -> Result<Vec<GaroonEvent>, reqwest::Error> {
-        let url = format!("{}{}", self.base_url, self.get_events_path());
-        
-        let response = self.client
-            .get(&url)
-            .headers(self.headers())
-            .query(&self.range_query(request))
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<GaroonEventResponse>()
-            .await?;
-
-        Ok(response.events)
+    async fn get(&self, request: GaroonGetEventsRequest) -> Result<Vec<GaroonEvent>, Error> {
+        self.stream(request).try_collect().await
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::TimeZone;
-    use reqwest::StatusCode;
+    use std::time;
+
+    use chrono::{Duration, TimeZone, Utc};
     use wiremock::{Mock, MockServer, ResponseTemplate};
     use wiremock::matchers::{header, method, path, query_param};
 
     use crate::apis::garoon::*;
+    use crate::apis::garoon_auth::{GaroonAuth, GaroonOAuth2Auth, GaroonOAuth2Config};
     use crate::apis::garoon_rest::*;
+    use crate::apis::retry::RetryPolicy;
+    use crate::models::oauth_token::OAuthToken;
+    use crate::repositories::token_repository::MockTokenRepository;
     use crate::utils::date_time_range::DateTimeRange;
 
     #[tokio::test]
@@ -93,19 +255,23 @@ mod tests {
         let expected_auth = "dXNlcjpwYXNzd29yZA==";  // base64("user:password")
         let garoon_response = GaroonEventResponse {
             events: vec![GaroonEvent {
+                id: "1".to_string(),
                 subject: "会議".to_string(),
                 attendees: vec![GaroonAttendee {
                     name: "山田太郎".to_string(),
+                    ..Default::default()
                 }],
                 start: GaroonDateTime {
-                    dateTime: "2024-05-10T09:00:00+09:00".to_string(),
-                    timeZone: "Asia/Tokyo".to_string(),
-                },
-                end: GaroonDateTime {
-                    dateTime: "2024-05-10T17:00:00+09:00".to_string(),
-                    timeZone: "Asia/Tokyo".to_string(),
+                    date_time: "2024-05-10T09:00:00+09:00".to_string(),
+                    time_zone: "Asia/Tokyo".to_string(),
                 },
+                end: Some(GaroonDateTime {
+                    date_time: "2024-05-10T17:00:00+09:00".to_string(),
+                    time_zone: "Asia/Tokyo".to_string(),
+                }),
+                ..Default::default()
             }],
+            has_next: false,
         };
         let start_date = "2024-05-10T00:00:00Z";
         let end_date = "2024-05-11T00:00:00Z";
@@ -128,10 +294,10 @@ mod tests {
         let client = GaroonRestClient::new(mock_server.uri(), auth);
 
         // Exercise: GaroonClient#get_eventsを実行する
-        let result = client.get(GaroonGetEventsRequest { period: DateTimeRange::new(
+        let result = client.get(GaroonGetEventsRequest::new(DateTimeRange::new(
             chrono::Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap(),
             chrono::Utc.with_ymd_and_hms(2024, 5, 11, 0, 0, 0).unwrap(),
-        ) }).await;
+        ))).await;
 
         // Asserts
         assert!(result.is_ok(), "Failed to get events: {:?}", result.err().unwrap());
@@ -153,17 +319,96 @@ mod tests {
         let client = GaroonRestClient::new(mock_server.uri(), auth);
 
         // Exercise: GaroonClient#get_eventsを実行する
-        let result = client.get(GaroonGetEventsRequest { period: DateTimeRange::new(
+        let result = client.get(GaroonGetEventsRequest::new(DateTimeRange::new(
             chrono::Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap(),
             chrono::Utc.with_ymd_and_hms(2024, 5, 11, 0, 0, 0).unwrap(),
-        ) }).await;
+        ))).await;
 
         // Asserts
-        assert!(result.is_err());
-        let actual = result.unwrap_err();
-        assert_eq!(actual.status(), Some(StatusCode::UNAUTHORIZED));
+        assert!(matches!(result, Err(Error::Auth { .. })));
     }
     
+    fn paged_event(id: usize) -> GaroonEvent {
+        GaroonEvent {
+            id: id.to_string(),
+            subject: format!("会議{}", id),
+            attendees: vec![],
+            start: GaroonDateTime {
+                date_time: "2024-05-10T09:00:00+09:00".to_string(),
+                time_zone: "Asia/Tokyo".to_string(),
+            },
+            end: Some(GaroonDateTime {
+                date_time: "2024-05-10T10:00:00+09:00".to_string(),
+                time_zone: "Asia/Tokyo".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    async fn mount_pages(mock_server: &MockServer) {
+        let pages = [
+            ("0", GaroonEventResponse { events: vec![paged_event(1), paged_event(2)], has_next: true }),
+            ("2", GaroonEventResponse { events: vec![paged_event(3), paged_event(4)], has_next: true }),
+            ("4", GaroonEventResponse { events: vec![paged_event(5)], has_next: false }),
+        ];
+        for (offset, page) in pages {
+            Mock::given(method("GET"))
+                .and(path("/api/v1/schedule/events"))
+                .and(query_param("limit", "2"))
+                .and(query_param("offset", offset))
+                .respond_with(ResponseTemplate::new(200).set_body_json(&page))
+                .expect(1)
+                .mount(mock_server)
+                .await;
+        }
+    }
+
+    fn one_day_request() -> GaroonGetEventsRequest {
+        GaroonGetEventsRequest::new(DateTimeRange::new(
+            chrono::Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap(),
+            chrono::Utc.with_ymd_and_hms(2024, 5, 11, 0, 0, 0).unwrap(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn get_events_ページング() {
+        // Setup: 2件ずつ3ページに分けて返すよう設定する
+        let mock_server = MockServer::start().await;
+        mount_pages(&mock_server).await;
+        let auth = GaroonAuth {
+            user_id: "user".to_string(),
+            password: "password".to_string(),
+        };
+        let client = GaroonRestClient::new(mock_server.uri(), auth).with_page_size(2);
+
+        // Exercise: GaroonClient#get_eventsを実行する
+        let result = client.get(one_day_request()).await;
+
+        // Asserts: hasNextがfalseになるまで全ページを取得している
+        let actual = result.unwrap();
+        let ids: Vec<String> = actual.iter().map(|event| event.id.clone()).collect();
+        assert_eq!(ids, vec!["1", "2", "3", "4", "5"]);
+    }
+
+    #[tokio::test]
+    async fn stream_events_ページング() {
+        // Setup: 2件ずつ3ページに分けて返すよう設定する
+        let mock_server = MockServer::start().await;
+        mount_pages(&mock_server).await;
+        let auth = GaroonAuth {
+            user_id: "user".to_string(),
+            password: "password".to_string(),
+        };
+        let client = GaroonRestClient::new(mock_server.uri(), auth).with_page_size(2);
+
+        // Exercise: GaroonRestClient#streamで1件ずつ取得する
+        let actual: Vec<GaroonEvent> = client.stream(one_day_request()).try_collect().await.unwrap();
+
+        // Asserts
+        assert_eq!(actual.len(), 5);
+        assert_eq!(actual[4], paged_event(5));
+    }
+
     #[test]
     fn test_headers() {
         let auth = GaroonAuth {
@@ -174,6 +419,281 @@ mod tests {
 
         let headers = client.headers();
         assert_eq!(headers.get("Accept").unwrap(), "application/json; charset=UTF-8");
-        assert_eq!(headers.get("X-Cybozu-Authorization").unwrap(), "dXNlcjpwYXNzd29yZA==");  // base64("user:password")
+    }
+
+    fn example_client() -> GaroonRestClient {
+        let auth = GaroonAuth {
+            user_id: "user".to_string(),
+            password: "password".to_string(),
+        };
+        GaroonRestClient::new("https://example.com".to_string(), auth)
+    }
+
+    #[test]
+    fn test_events_query_期間のみ() {
+        let query = example_client().events_query(&one_day_request());
+
+        assert_eq!(query, vec![
+            ("rangeStart", "2024-05-10T00:00:00Z".to_string()),
+            ("rangeEnd", "2024-05-11T00:00:00Z".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_events_query_対象の施設() {
+        let request = one_day_request().with_target(Some(GaroonTarget::new(GaroonTargetType::Facility, "42".to_string())));
+
+        let query = example_client().events_query(&request);
+
+        assert_eq!(query, vec![
+            ("rangeStart", "2024-05-10T00:00:00Z".to_string()),
+            ("rangeEnd", "2024-05-11T00:00:00Z".to_string()),
+            ("target", "42".to_string()),
+            ("targetType", "facility".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_events_query_全ての条件() {
+        let request = one_day_request().with_options(GaroonGetEventsOptions {
+            keyword: Some("定例".to_string()),
+            exclude_from_search: vec![GaroonSearchField::Notes, GaroonSearchField::Comments],
+            fields: vec!["id".to_string(), "subject".to_string(), "start".to_string(), "end".to_string()],
+            order_by: Some("updatedAt desc".parse().unwrap()),
+            show_private: Some(false),
+            limit: Some(10),
+        });
+
+        let query = example_client().events_query(&request);
+
+        // limitはページングと合わせてpage_queryで指定する
+        assert_eq!(query, vec![
+            ("rangeStart", "2024-05-10T00:00:00Z".to_string()),
+            ("rangeEnd", "2024-05-11T00:00:00Z".to_string()),
+            ("keyword", "定例".to_string()),
+            ("excludeFromSearch", "notes,comments".to_string()),
+            ("fields", "id,subject,start,end".to_string()),
+            ("orderBy", "updatedAt desc".to_string()),
+            ("showPrivate", "false".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_page_query() {
+        let query = example_client().page_query(200, 50);
+
+        assert_eq!(query, vec![("limit", "50".to_string()), ("offset", "200".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn get_events_最大件数() {
+        // Setup: 2件ずつ3ページに分けて返すよう設定する。3件目までしか取得しないので、3ページ目は問い合わせない
+        let mock_server = MockServer::start().await;
+        let pages = [
+            ("0", "2", GaroonEventResponse { events: vec![paged_event(1), paged_event(2)], has_next: true }),
+            ("2", "1", GaroonEventResponse { events: vec![paged_event(3)], has_next: true }),
+        ];
+        for (offset, limit, page) in pages {
+            Mock::given(method("GET"))
+                .and(path("/api/v1/schedule/events"))
+                .and(query_param("limit", limit))
+                .and(query_param("offset", offset))
+                .respond_with(ResponseTemplate::new(200).set_body_json(&page))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+        let client = password_client(&mock_server).with_page_size(2);
+
+        // Exercise
+        let request = one_day_request().with_options(GaroonGetEventsOptions { limit: Some(3), ..Default::default() });
+        let result = client.get(request).await;
+
+        // Asserts
+        let ids: Vec<String> = result.unwrap().iter().map(|event| event.id.clone()).collect();
+        assert_eq!(ids, vec!["1", "2", "3"]);
+    }
+
+    #[tokio::test]
+    async fn get_events_認証エラーならトークンをリフレッシュして再試行() {
+        // Setup: 失効したトークンでは401を返し、リフレッシュ後のトークンでは予定を返すよう設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/schedule/events"))
+            .and(header("Authorization", "Bearer revoked-access"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/schedule/events"))
+            .and(header("Authorization", "Bearer new-access"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(GaroonEventResponse { events: vec![paged_event(1)], has_next: false }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/oauth2/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "new-access",
+                "refresh_token": "new-refresh",
+                "expires_in": 3600,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_load().returning(|| Ok(Some(OAuthToken::new(
+            "revoked-access".to_string(),
+            Some("refresh".to_string()),
+            Some(Utc::now() + Duration::hours(1)),
+        ))));
+        token_repository.expect_save()
+            .withf(|token| token.refresh_token.as_deref() == Some("new-refresh"))
+            .times(1)
+            .returning(|_| Ok(()));
+        let auth = GaroonOAuth2Auth::new(GaroonOAuth2Config {
+            authorization_url: format!("{}/oauth2/authorization", mock_server.uri()),
+            token_url: format!("{}/oauth2/token", mock_server.uri()),
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            redirect_uri: "http://localhost:8080/callback".to_string(),
+            scope: "g:Schedule:read".to_string(),
+        }, token_repository);
+        let client = GaroonRestClient::new(mock_server.uri(), auth);
+
+        // Exercise
+        let result = client.get(one_day_request()).await;
+
+        // Asserts
+        assert_eq!(result.unwrap(), vec![paged_event(1)]);
+    }
+
+    fn password_client(mock_server: &MockServer) -> GaroonRestClient {
+        let auth = GaroonAuth {
+            user_id: "user".to_string(),
+            password: "password".to_string(),
+        };
+        GaroonRestClient::new(mock_server.uri(), auth).with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: time::Duration::from_millis(1),
+            max_delay: time::Duration::from_millis(10),
+        })
+    }
+
+    #[tokio::test]
+    async fn get_events_一時的なエラーは再試行する() {
+        // Setup: 2回503を返した後に予定を返すよう設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/schedule/events"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/schedule/events"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(GaroonEventResponse { events: vec![paged_event(1)], has_next: false }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Exercise
+        let result = password_client(&mock_server).get(one_day_request()).await;
+
+        // Asserts
+        assert_eq!(result.unwrap(), vec![paged_event(1)]);
+    }
+
+    #[tokio::test]
+    async fn get_events_再試行の上限() {
+        // Setup: Retry-Afterを付けて429を返し続ける
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/schedule/events"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        // Exercise
+        let result = password_client(&mock_server).get(one_day_request()).await;
+
+        // Asserts: 最大試行回数で諦め、最後のエラーを返す
+        assert!(matches!(result, Err(Error::Garoon(error)) if error.status == StatusCode::TOO_MANY_REQUESTS));
+    }
+
+    #[tokio::test]
+    async fn get_events_エラーの本文を返す() {
+        // Setup: 再試行しても成功しないエラーを返す
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/schedule/events"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+                "errorCode": "GRN_CMMN_00105",
+                "message": "パラメータが不正です。",
+                "cause": "rangeStartの形式が不正です。",
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Exercise
+        let result = password_client(&mock_server).get(one_day_request()).await;
+
+        // Asserts: 再試行せず、Garoonのエラーコードと原因を返す
+        let Err(Error::Garoon(error)) = result else {
+            panic!("unexpected result: {:?}", result);
+        };
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(error.error_code, Some("GRN_CMMN_00105".to_string()));
+        assert_eq!(error.cause, Some("rangeStartの形式が不正です。".to_string()));
+    }
+
+    #[tokio::test]
+    async fn find_target_コードで検索する() {
+        // Setup: 1ページ目には部分一致した別の組織だけを返し、2ページ目に該当する組織を返すよう設定する
+        let mock_server = MockServer::start().await;
+        let pages = [
+            ("0", serde_json::json!({
+                "organizations": [{ "id": "7", "code": "sales-east", "name": "東日本営業部" }],
+                "hasNext": true,
+            })),
+            ("1", serde_json::json!({
+                "organizations": [{ "id": "8", "code": "sales", "name": "営業部" }],
+                "hasNext": false,
+            })),
+        ];
+        for (offset, page) in pages {
+            Mock::given(method("GET"))
+                .and(path("/api/v1/base/organizations"))
+                .and(query_param("name", "sales"))
+                .and(query_param("offset", offset))
+                .respond_with(ResponseTemplate::new(200).set_body_json(page))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+        }
+
+        // Exercise
+        let target = password_client(&mock_server).find_target(GaroonTargetType::Organization, "sales").await.unwrap();
+
+        // Asserts
+        assert_eq!(target, GaroonTarget::new(GaroonTargetType::Organization, "8".to_string()));
+    }
+
+    #[tokio::test]
+    async fn find_target_見つからない() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v1/schedule/facilities"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "facilities": [], "hasNext": false })))
+            .mount(&mock_server)
+            .await;
+
+        let result = password_client(&mock_server).find_target(GaroonTargetType::Facility, "room-z").await;
+
+        assert!(matches!(result, Err(Error::Config(message)) if message == "facility `room-z` was not found in Garoon"));
     }
 }
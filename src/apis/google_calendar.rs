@@ -0,0 +1,229 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use mockall::automock;
+use crate::models::event::{Event, EventTime};
+
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait GoogleCalendarClient {
+    async fn insert(&self, event: GoogleCalendarEvent) -> Result<GoogleCalendarEvent, reqwest::Error>;
+    async fn update(&self, event_id: String, event: GoogleCalendarEvent) -> Result<GoogleCalendarEvent, reqwest::Error>;
+    async fn delete(&self, event_id: String) -> Result<(), reqwest::Error>;
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GoogleCalendarEvent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) id: Option<String>,
+    pub(crate) summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) visibility: Option<String>,
+    pub(crate) start: GoogleCalendarDateTime,
+    pub(crate) end: GoogleCalendarDateTime,
+    /// 繰り返し予定のRRULE行とEXDATE行
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) recurrence: Option<Vec<String>>,
+}
+impl From<&Event> for GoogleCalendarEvent {
+    fn from(value: &Event) -> Self {
+        // Googleカレンダーの参加者はメールアドレスが必須なので、表示名は説明欄に残す
+        let mut description_lines = vec![];
+        if let Some(notes) = &value.description {
+            description_lines.push(notes.clone());
+        }
+        if !value.attendees.is_empty() {
+            let names: Vec<&str> = value.attendees.iter().map(|attendee| attendee.display_name.as_str()).collect();
+            description_lines.push(format!("参加者: {}", names.join(", ")));
+        }
+        let description = Some(description_lines.join("\n\n")).filter(|description| !description.is_empty());
+        let (start, end) = match &value.time {
+            EventTime::Timed(range) => (
+                GoogleCalendarDateTime::date_time(&range.start, &value.time_zones.start),
+                GoogleCalendarDateTime::date_time(&range.end, &value.time_zones.end),
+            ),
+            // Google Calendar APIの終日予定の終了日は翌日を指定する
+            EventTime::AllDay(dates) => (GoogleCalendarDateTime::date(&dates.start), GoogleCalendarDateTime::date(&dates.exclusive_end())),
+            // 終了日時は必須なので、開始と同じ日時にする
+            EventTime::StartOnly { start } => (
+                GoogleCalendarDateTime::date_time(start, &value.time_zones.start),
+                GoogleCalendarDateTime::date_time(start, &value.time_zones.start),
+            ),
+        };
+
+        Self {
+            id: None,
+            summary: value.title.as_str(),
+            description,
+            location: value.location.clone(),
+            visibility: value.private.then(|| "private".to_string()),
+            start,
+            end,
+            recurrence: Some(value.recurrence_lines()).filter(|lines| !lines.is_empty()),
+        }
+    }
+}
+
+/// 時刻のある予定は`dateTime`、終日予定は`date`のどちらか一方を持つ
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GoogleCalendarDateTime {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) date_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) time_zone: Option<String>,
+}
+impl GoogleCalendarDateTime {
+    /// 繰り返しや表示に使われるため、Garoonのタイムゾーンも渡す
+    fn date_time(date_time: &DateTime<Utc>, time_zone: &Tz) -> Self {
+        Self {
+            date_time: Some(date_time.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            date: None,
+            time_zone: Some(time_zone.name().to_string()),
+        }
+    }
+
+    fn date(date: &NaiveDate) -> Self {
+        Self { date_time: None, date: Some(date.to_string()), time_zone: None }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use chrono::{NaiveDate, Weekday};
+
+    use crate::apis::google_calendar::{GoogleCalendarDateTime, GoogleCalendarEvent};
+    use crate::models::event::{Attendee, Event, EventId, EventTime, EventTimeZones, Title};
+    use crate::models::recurrence::{Frequency, Recurrence};
+    use crate::utils::date_range::DateRange;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    #[test]
+    fn test_from_event() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("会議".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![Attendee::new("user1".to_string()), Attendee::new("user2".to_string())],
+        );
+
+        let google_event = GoogleCalendarEvent::from(&event);
+
+        assert_eq!(google_event.id, None);
+        assert_eq!(google_event.summary, "会議".to_string());
+        assert_eq!(google_event.description, Some("参加者: user1, user2".to_string()));
+        assert_eq!(google_event.location, None);
+        assert_eq!(google_event.visibility, None);
+        assert_eq!(google_event.start.date_time, Some("2021-01-01T00:00:00Z".to_string()));
+        assert_eq!(google_event.end.date_time, Some("2021-01-01T01:00:00Z".to_string()));
+        assert_eq!(google_event.start.date, None);
+        assert_eq!(google_event.start.time_zone, Some("UTC".to_string()));
+    }
+
+    #[test]
+    fn test_from_event_詳細() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("会議".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![Attendee::new("user1".to_string())],
+        )
+            .with_description(Some("資料を持参".to_string()))
+            .with_location(Some("会議室A".to_string()))
+            .with_private(true);
+
+        let google_event = GoogleCalendarEvent::from(&event);
+
+        assert_eq!(google_event.description, Some("資料を持参\n\n参加者: user1".to_string()));
+        assert_eq!(google_event.location, Some("会議室A".to_string()));
+        assert_eq!(google_event.visibility, Some("private".to_string()));
+    }
+
+    #[test]
+    fn test_from_event_終日予定() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("休暇".to_string()),
+            EventTime::AllDay(DateRange::new(
+                NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2021, 1, 2).unwrap(),
+            )),
+            vec![],
+        );
+
+        let google_event = GoogleCalendarEvent::from(&event);
+
+        assert_eq!(google_event.start, GoogleCalendarDateTime { date_time: None, date: Some("2021-01-01".to_string()), time_zone: None });
+        assert_eq!(google_event.end, GoogleCalendarDateTime { date_time: None, date: Some("2021-01-03".to_string()), time_zone: None });
+    }
+
+    #[test]
+    fn test_from_event_開始時刻のみ() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("締め切り".to_string()),
+            EventTime::StartOnly { start: Utc.with_ymd_and_hms(2021, 1, 1, 9, 0, 0).unwrap() },
+            vec![],
+        );
+
+        let google_event = GoogleCalendarEvent::from(&event);
+
+        assert_eq!(google_event.start.date_time, Some("2021-01-01T09:00:00Z".to_string()));
+        assert_eq!(google_event.end, google_event.start);
+    }
+
+    #[test]
+    fn test_from_event_タイムゾーン() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("移動".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 14, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+            .with_time_zones(EventTimeZones::new(chrono_tz::Asia::Tokyo, chrono_tz::America::New_York));
+
+        let google_event = GoogleCalendarEvent::from(&event);
+
+        assert_eq!(google_event.start.time_zone, Some("Asia/Tokyo".to_string()));
+        assert_eq!(google_event.end.time_zone, Some("America/New_York".to_string()));
+    }
+
+    #[test]
+    fn test_from_event_繰り返し予定() {
+        let period = DateRange::new(NaiveDate::from_ymd_opt(2024, 4, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 9, 30).unwrap());
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("定例".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2024, 4, 5, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 4, 5, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+            .with_recurrence(Some(Recurrence::new(Frequency::Weekly(Weekday::Fri), period, chrono_tz::Asia::Tokyo)));
+
+        let google_event = GoogleCalendarEvent::from(&event);
+
+        assert_eq!(google_event.recurrence, Some(vec!["RRULE:FREQ=WEEKLY;BYDAY=FR;UNTIL=20240930T145959Z".to_string()]));
+        assert_eq!(GoogleCalendarEvent::from(&event.with_recurrence(None)).recurrence, None);
+    }
+}
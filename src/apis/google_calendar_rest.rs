@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use reqwest::{Client, Url};
+use reqwest::header::{ACCEPT, AUTHORIZATION, HeaderMap};
+
+use crate::apis::google_calendar::{GoogleCalendarClient, GoogleCalendarEvent};
+
+pub struct GoogleCalendarRestClient {
+    client: Client,
+    base_url: String,
+    calendar_id: String,
+    access_token: String,
+}
+impl GoogleCalendarRestClient {
+    pub fn new(base_url: String, calendar_id: String, access_token: String) -> Self {
+        Self {
+            client: Client::new(),
+            base_url,
+            calendar_id,
+            access_token,
+        }
+    }
+
+    fn events_url(&self, event_id: Option<&str>) -> Url {
+        // カレンダーIDには`@`や`#`が含まれるため、パスセグメントとしてエンコードする
+        let mut url = Url::parse(&self.base_url).expect("invalid Google Calendar base url");
+        {
+            let mut segments = url.path_segments_mut().expect("invalid Google Calendar base url");
+            segments
+                .pop_if_empty()
+                .extend(["calendar", "v3", "calendars", &self.calendar_id, "events"]);
+            if let Some(event_id) = event_id {
+                segments.push(event_id);
+            }
+        }
+
+        url
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, "application/json; charset=UTF-8".parse().unwrap());
+        headers.insert(AUTHORIZATION, format!("Bearer {}", self.access_token).parse().unwrap());
+
+        headers
+    }
+}
+#[async_trait]
+impl GoogleCalendarClient for GoogleCalendarRestClient {
+    async fn insert(&self, event: GoogleCalendarEvent) -> Result<GoogleCalendarEvent, reqwest::Error> {
+        self.client
+            .post(self.events_url(None))
+            .headers(self.headers())
+            .json(&event)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<GoogleCalendarEvent>()
+            .await
+    }
+
+    async fn update(&self, event_id: String, event: GoogleCalendarEvent) -> Result<GoogleCalendarEvent, reqwest::Error> {
+        self.client
+            .put(self.events_url(Some(&event_id)))
+            .headers(self.headers())
+            .json(&event)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<GoogleCalendarEvent>()
+            .await
+    }
+
+    async fn delete(&self, event_id: String) -> Result<(), reqwest::Error> {
+        self.client
+            .delete(self.events_url(Some(&event_id)))
+            .headers(self.headers())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    use wiremock::matchers::{body_json, header, method, path};
+
+    use crate::apis::google_calendar::*;
+    use crate::apis::google_calendar_rest::*;
+
+    fn google_event(id: Option<&str>) -> GoogleCalendarEvent {
+        GoogleCalendarEvent {
+            id: id.map(|id| id.to_string()),
+            summary: "会議".to_string(),
+            description: None,
+            location: None,
+            visibility: None,
+            start: GoogleCalendarDateTime {
+                date_time: Some("2024-05-10T00:00:00Z".to_string()),
+                date: None,
+                time_zone: Some("Asia/Tokyo".to_string()),
+            },
+            end: GoogleCalendarDateTime {
+                date_time: Some("2024-05-10T08:00:00Z".to_string()),
+                date: None,
+                time_zone: Some("Asia/Tokyo".to_string()),
+            },
+            recurrence: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn insert_正常系() {
+        // Setup: モックサーバーを起動し、Google Calendar APIのレスポンスを設定する
+        let mock_server = MockServer::start().await;
+        let response = ResponseTemplate::new(200).set_body_json(google_event(Some("google-1")));
+
+        Mock::given(method("POST"))
+            .and(path("/calendar/v3/calendars/team@group.calendar.google.com/events"))
+            .and(header("Authorization", "Bearer token"))
+            .and(body_json(google_event(None)))
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = GoogleCalendarRestClient::new(
+            mock_server.uri(),
+            "team@group.calendar.google.com".to_string(),
+            "token".to_string(),
+        );
+
+        // Exercise: GoogleCalendarClient#insertを実行する
+        let result = client.insert(google_event(None)).await;
+
+        // Asserts
+        assert!(result.is_ok(), "Failed to insert event: {:?}", result.err().unwrap());
+        assert_eq!(result.unwrap().id, Some("google-1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn update_正常系() {
+        // Setup: モックサーバーを起動し、Google Calendar APIのレスポンスを設定する
+        let mock_server = MockServer::start().await;
+        let response = ResponseTemplate::new(200).set_body_json(google_event(Some("google-1")));
+
+        Mock::given(method("PUT"))
+            .and(path("/calendar/v3/calendars/primary/events/google-1"))
+            .and(header("Authorization", "Bearer token"))
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = GoogleCalendarRestClient::new(mock_server.uri(), "primary".to_string(), "token".to_string());
+
+        // Exercise: GoogleCalendarClient#updateを実行する
+        let result = client.update("google-1".to_string(), google_event(None)).await;
+
+        // Asserts
+        assert!(result.is_ok(), "Failed to update event: {:?}", result.err().unwrap());
+        assert_eq!(result.unwrap(), google_event(Some("google-1")));
+    }
+
+    #[tokio::test]
+    async fn delete_正常系() {
+        // Setup: モックサーバーを起動し、Google Calendar APIのレスポンスを設定する
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("DELETE"))
+            .and(path("/calendar/v3/calendars/primary/events/google-1"))
+            .and(header("Authorization", "Bearer token"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let client = GoogleCalendarRestClient::new(mock_server.uri(), "primary".to_string(), "token".to_string());
+
+        // Exercise: GoogleCalendarClient#deleteを実行する
+        let result = client.delete("google-1".to_string()).await;
+
+        // Asserts
+        assert!(result.is_ok(), "Failed to delete event: {:?}", result.err().unwrap());
+    }
+
+    #[tokio::test]
+    async fn insert_認証失敗() {
+        // Setup: モックサーバーを起動し、Google Calendar APIのレスポンスを設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&mock_server)
+            .await;
+
+        let client = GoogleCalendarRestClient::new(mock_server.uri(), "primary".to_string(), "wrong_token".to_string());
+
+        // Exercise: GoogleCalendarClient#insertを実行する
+        let result = client.insert(google_event(None)).await;
+
+        // Asserts
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().status(), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn test_headers() {
+        let client = GoogleCalendarRestClient::new(
+            "https://www.googleapis.com".to_string(),
+            "primary".to_string(),
+            "token".to_string(),
+        );
+
+        let headers = client.headers();
+        assert_eq!(headers.get("Accept").unwrap(), "application/json; charset=UTF-8");
+        assert_eq!(headers.get("Authorization").unwrap(), "Bearer token");
+    }
+}
@@ -0,0 +1,331 @@
+use std::sync::Mutex;
+
+use chrono::{Duration, Utc};
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::time::sleep;
+
+use crate::error::Error;
+use crate::models::oauth_token::OAuthToken;
+use crate::repositories::token_repository::TokenRepository;
+
+/// デバイスコードの確認を続ける間隔を指定されなかった場合の秒数 (RFC 8628 3.2)
+const DEFAULT_POLL_INTERVAL_SECONDS: u64 = 5;
+
+/// Microsoft ID プラットフォームのOAuth 2.0クライアントの設定。シークレットを持たないパブリッククライアントとして登録する
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MicrosoftOAuthConfig {
+    /// `https://login.microsoftonline.com/{tenant}/oauth2/v2.0`
+    pub authority_url: String,
+    pub client_id: String,
+    pub scope: String,
+}
+
+/// ブラウザで開いて入力してもらうコード
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct DeviceCode {
+    pub(crate) device_code: String,
+    pub(crate) user_code: String,
+    pub(crate) verification_uri: String,
+    pub(crate) expires_in: i64,
+    #[serde(default)]
+    pub(crate) interval: Option<u64>,
+    /// 利用者向けの案内文
+    #[serde(default)]
+    pub(crate) message: Option<String>,
+}
+impl DeviceCode {
+    pub fn message(&self) -> String {
+        self.message
+            .clone()
+            .unwrap_or_else(|| format!("Open {} and enter the code {}", self.verification_uri, self.user_code))
+    }
+}
+
+/// デバイスコードフロー (RFC 8628) による認証。トークンは期限切れや認証エラーのたびにリフレッシュして保存する
+pub struct MicrosoftOAuth {
+    config: MicrosoftOAuthConfig,
+    token_repository: Box<dyn TokenRepository + Send + Sync>,
+    token: Mutex<Option<OAuthToken>>,
+}
+impl MicrosoftOAuth {
+    pub fn new(config: MicrosoftOAuthConfig, token_repository: impl TokenRepository + Send + Sync + 'static) -> Self {
+        Self { config, token_repository: Box::new(token_repository), token: Mutex::new(None) }
+    }
+
+    pub async fn request_device_code(&self, client: &Client) -> Result<DeviceCode, Error> {
+        let response = client
+            .post(format!("{}/devicecode", self.config.authority_url))
+            .form(&[("client_id", self.config.client_id.as_str()), ("scope", self.config.scope.as_str())])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(Error::auth(format!("device code request failed with {}: {}", status, body)));
+        }
+
+        Ok(response.json::<DeviceCode>().await?)
+    }
+
+    /// 利用者がコードを入力し終えるまで待ち、トークンを保存する
+    pub async fn poll_token(&self, client: &Client, device_code: &DeviceCode) -> Result<OAuthToken, Error> {
+        let deadline = Utc::now() + Duration::seconds(device_code.expires_in);
+        let mut interval = device_code.interval.unwrap_or(DEFAULT_POLL_INTERVAL_SECONDS);
+        loop {
+            sleep(std::time::Duration::from_secs(interval)).await;
+            let result = self
+                .request_token(client, &[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                    ("device_code", device_code.device_code.as_str()),
+                ], None)
+                .await;
+            match result {
+                Err(TokenError::Pending) if Utc::now() < deadline => {}
+                // 問い合わせが多すぎる場合は間隔を5秒延ばす (RFC 8628 3.5)
+                Err(TokenError::SlowDown) if Utc::now() < deadline => interval += 5,
+                Err(TokenError::Pending | TokenError::SlowDown) => return Err(Error::auth("the device code expired before it was entered")),
+                Err(TokenError::Failed(e)) => return Err(e),
+                Ok(token) => return Ok(token),
+            }
+        }
+    }
+
+    /// 保存されたアクセストークン。期限切れであればリフレッシュする
+    pub async fn access_token(&self, client: &Client) -> Result<String, Error> {
+        let token = self
+            .current_token()?
+            .ok_or_else(|| Error::auth("no Microsoft OAuth token is stored; run `tensha auth --outlook` first"))?;
+        let token = match &token.refresh_token {
+            Some(refresh_token) if token.is_expired(Utc::now()) => self.refresh_token(client, refresh_token).await?,
+            _ => token,
+        };
+
+        Ok(token.access_token)
+    }
+
+    /// 認証エラーが返ったときにトークンを取り直す。取り直して再試行できる場合はtrueを返す
+    pub async fn refresh(&self, client: &Client) -> Result<bool, Error> {
+        let Some(refresh_token) = self.current_token()?.and_then(|token| token.refresh_token) else {
+            return Ok(false);
+        };
+
+        self.refresh_token(client, &refresh_token).await?;
+        Ok(true)
+    }
+
+    async fn refresh_token(&self, client: &Client, refresh_token: &str) -> Result<OAuthToken, Error> {
+        self.request_token(client, &[("grant_type", "refresh_token"), ("refresh_token", refresh_token)], Some(refresh_token))
+            .await
+            .map_err(|e| match e {
+                TokenError::Failed(e) => e,
+                TokenError::Pending | TokenError::SlowDown => Error::auth("unexpected response to the token refresh"),
+            })
+    }
+
+    async fn request_token(&self, client: &Client, params: &[(&str, &str)], refresh_token: Option<&str>) -> Result<OAuthToken, TokenError> {
+        let mut form = vec![("client_id", self.config.client_id.as_str()), ("scope", self.config.scope.as_str())];
+        form.extend_from_slice(params);
+        let response = client
+            .post(format!("{}/token", self.config.authority_url))
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| TokenError::Failed(e.into()))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let error = serde_json::from_str::<TokenErrorResponse>(&body).map(|response| response.error).unwrap_or_default();
+            return Err(match error.as_str() {
+                "authorization_pending" => TokenError::Pending,
+                "slow_down" => TokenError::SlowDown,
+                _ => TokenError::Failed(Error::auth(format!("token request failed with {}: {}", status, body))),
+            });
+        }
+
+        let response = response.json::<TokenResponse>().await.map_err(|e| TokenError::Failed(e.into()))?;
+        let token = OAuthToken::new(
+            response.access_token,
+            // リフレッシュトークンが再発行されない場合は、手元のものを使い続ける
+            response.refresh_token.or(refresh_token.map(str::to_string)),
+            response.expires_in.map(|seconds| Utc::now() + Duration::seconds(seconds)),
+        );
+        self.token_repository
+            .save(&token)
+            .map_err(|e| TokenError::Failed(Error::Auth { message: "failed to save the OAuth token".to_string(), source: Some(e.into()) }))?;
+        *self.token.lock().unwrap() = Some(token.clone());
+
+        Ok(token)
+    }
+
+    fn current_token(&self) -> Result<Option<OAuthToken>, Error> {
+        let mut token = self.token.lock().unwrap();
+        if token.is_none() {
+            *token = self
+                .token_repository
+                .load()
+                .map_err(|e| Error::Auth { message: "failed to load the OAuth token".to_string(), source: Some(e.into()) })?;
+        }
+
+        Ok(token.clone())
+    }
+}
+
+/// トークンを得られなかった理由。デバイスコードの入力待ちは失敗ではない
+enum TokenError {
+    Pending,
+    SlowDown,
+    Failed(Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use reqwest::Client;
+    use wiremock::matchers::{body_string_contains, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::apis::microsoft_auth::*;
+    use crate::models::oauth_token::OAuthToken;
+    use crate::repositories::token_repository::MockTokenRepository;
+
+    fn oauth_config(mock_server: &MockServer) -> MicrosoftOAuthConfig {
+        MicrosoftOAuthConfig {
+            authority_url: format!("{}/organizations/oauth2/v2.0", mock_server.uri()),
+            client_id: "client".to_string(),
+            scope: "offline_access Calendars.ReadWrite".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn poll_token_入力されるまで待つ() {
+        // Setup: デバイスコードを発行し、1回目の確認は入力待ち、2回目でトークンが返るよう設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/organizations/oauth2/v2.0/devicecode"))
+            .and(body_string_contains("scope=offline_access+Calendars.ReadWrite"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "device_code": "device",
+                "user_code": "ABCD-EFGH",
+                "verification_uri": "https://microsoft.com/devicelogin",
+                "expires_in": 900,
+                "interval": 0,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/organizations/oauth2/v2.0/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({ "error": "authorization_pending" })))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/organizations/oauth2/v2.0/token"))
+            .and(body_string_contains("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code"))
+            .and(body_string_contains("device_code=device"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "access",
+                "refresh_token": "refresh",
+                "expires_in": 3600,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_save().withf(|token| token.access_token == "access").times(1).returning(|_| Ok(()));
+        let auth = MicrosoftOAuth::new(oauth_config(&mock_server), token_repository);
+        let client = Client::new();
+
+        // Exercise
+        let device_code = auth.request_device_code(&client).await.unwrap();
+        let token = auth.poll_token(&client, &device_code).await;
+
+        // Asserts
+        assert_eq!(device_code.message(), "Open https://microsoft.com/devicelogin and enter the code ABCD-EFGH");
+        assert_eq!(token.unwrap().refresh_token, Some("refresh".to_string()));
+    }
+
+    #[tokio::test]
+    async fn poll_token_拒否された() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/organizations/oauth2/v2.0/token"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({ "error": "authorization_declined" })))
+            .mount(&mock_server)
+            .await;
+        let auth = MicrosoftOAuth::new(oauth_config(&mock_server), MockTokenRepository::new());
+        let device_code = DeviceCode {
+            device_code: "device".to_string(),
+            user_code: "ABCD-EFGH".to_string(),
+            verification_uri: "https://microsoft.com/devicelogin".to_string(),
+            expires_in: 900,
+            interval: Some(0),
+            message: None,
+        };
+
+        let result = auth.poll_token(&Client::new(), &device_code).await;
+
+        assert!(matches!(result, Err(Error::Auth { message, .. }) if message.contains("authorization_declined")));
+    }
+
+    #[tokio::test]
+    async fn access_token_期限切れのトークンをリフレッシュする() {
+        // Setup: 期限切れのトークンが保存されており、リフレッシュで新しいトークンが返るよう設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/organizations/oauth2/v2.0/token"))
+            .and(body_string_contains("grant_type=refresh_token"))
+            .and(body_string_contains("refresh_token=old-refresh"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "access_token": "new-access",
+                "refresh_token": "new-refresh",
+                "expires_in": 3600,
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_load().times(1).returning(|| Ok(Some(OAuthToken::new(
+            "old-access".to_string(),
+            Some("old-refresh".to_string()),
+            Some(Utc::now() - Duration::minutes(5)),
+        ))));
+        token_repository.expect_save()
+            .withf(|token| token.refresh_token.as_deref() == Some("new-refresh"))
+            .times(1)
+            .returning(|_| Ok(()));
+        let auth = MicrosoftOAuth::new(oauth_config(&mock_server), token_repository);
+
+        // Exercise
+        let result = auth.access_token(&Client::new()).await;
+
+        // Asserts
+        assert_eq!(result.unwrap(), "new-access");
+    }
+
+    #[tokio::test]
+    async fn access_token_トークン未保存() {
+        let mock_server = MockServer::start().await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_load().returning(|| Ok(None));
+        let auth = MicrosoftOAuth::new(oauth_config(&mock_server), token_repository);
+
+        let result = auth.access_token(&Client::new()).await;
+
+        assert!(matches!(result, Err(Error::Auth { message, .. }) if message.contains("tensha auth --outlook")));
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+#[cfg(test)]
+use mockall::automock;
+
+use crate::error::Error;
+use crate::models::event::{Event, EventTime};
+
+/// Garoonの予定IDを記録する拡張プロパティ。同期状態を失っても、転写済みの予定を探せるようにする
+pub const GAROON_ID_PROPERTY: &str = "String {2f7a8c3e-5b14-4d9a-a6e1-0c9b7d3f5e28} Name GaroonId";
+
+/// Microsoft Graphの予定API (Outlook / Exchange Online)
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait MicrosoftGraphClient {
+    /// Garoonの予定IDを記録した予定の、Garoonの予定IDからGraphの予定IDへの対応
+    async fn list_synced(&self) -> Result<HashMap<String, String>, Error>;
+
+    /// JSONバッチでまとめて送り、`requests`と同じ順に結果を返す。個々のリクエストの失敗は結果の`status`で表す
+    async fn batch(&self, requests: Vec<GraphRequest>) -> Result<Vec<GraphResponse>, Error>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GraphRequest {
+    Create(GraphEvent),
+    Update { event_id: String, event: GraphEvent },
+    Delete { event_id: String },
+}
+
+/// バッチ内の1件分の結果
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphResponse {
+    pub(crate) status: StatusCode,
+    pub(crate) body: serde_json::Value,
+}
+impl GraphResponse {
+    pub fn new(status: StatusCode, body: serde_json::Value) -> Self {
+        Self { status, body }
+    }
+
+    /// 作成・更新した予定のID
+    pub fn event_id(&self) -> Option<String> {
+        self.body.get("id").and_then(|id| id.as_str()).map(str::to_string)
+    }
+
+    /// エラーの場合の説明。Graphのエラー本文になければステータスだけを返す
+    pub fn error_message(&self) -> String {
+        match self.body.pointer("/error/message").and_then(|message| message.as_str()) {
+            Some(message) => format!("Microsoft Graph returned {}: {}", self.status, message),
+            None => format!("Microsoft Graph returned {}", self.status),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphEvent {
+    pub(crate) subject: String,
+    pub(crate) body: GraphItemBody,
+    pub(crate) start: GraphDateTime,
+    pub(crate) end: GraphDateTime,
+    pub(crate) is_all_day: bool,
+    /// 更新時に前回の値を消すため、場所がなくても空の表示名を送る
+    pub(crate) location: GraphLocation,
+    pub(crate) sensitivity: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) single_value_extended_properties: Vec<GraphExtendedProperty>,
+}
+impl From<&Event> for GraphEvent {
+    fn from(value: &Event) -> Self {
+        // Graphの参加者はメールアドレスが必須で、招待も送られてしまうので、表示名は本文に残す
+        let mut body_lines = vec![];
+        if let Some(notes) = &value.description {
+            body_lines.push(notes.clone());
+        }
+        if !value.attendees.is_empty() {
+            let names: Vec<&str> = value.attendees.iter().map(|attendee| attendee.display_name.as_str()).collect();
+            body_lines.push(format!("参加者: {}", names.join(", ")));
+        }
+        let (start, end, is_all_day) = match &value.time {
+            EventTime::Timed(range) => (GraphDateTime::date_time(&range.start), GraphDateTime::date_time(&range.end), false),
+            // 終日予定の終了日は最終日の翌日を指定する
+            EventTime::AllDay(dates) => (GraphDateTime::date(&dates.start), GraphDateTime::date(&dates.exclusive_end()), true),
+            // 終了日時は必須なので、開始と同じ日時にする
+            EventTime::StartOnly { start } => (GraphDateTime::date_time(start), GraphDateTime::date_time(start), false),
+        };
+
+        Self {
+            subject: value.title.as_str(),
+            body: GraphItemBody { content_type: "text".to_string(), content: body_lines.join("\n\n") },
+            start,
+            end,
+            is_all_day,
+            location: GraphLocation { display_name: value.location.clone().unwrap_or_default() },
+            sensitivity: if value.private { "private" } else { "normal" }.to_string(),
+            single_value_extended_properties: vec![GraphExtendedProperty {
+                id: GAROON_ID_PROPERTY.to_string(),
+                value: value.id.as_str().to_string(),
+            }],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphItemBody {
+    pub(crate) content_type: String,
+    pub(crate) content: String,
+}
+
+/// Graphのタイムゾーン名はWindowsの名前が基本なので、日時はUTCで渡す
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphDateTime {
+    pub(crate) date_time: String,
+    pub(crate) time_zone: String,
+}
+impl GraphDateTime {
+    fn date_time(date_time: &DateTime<Utc>) -> Self {
+        Self { date_time: date_time.format("%Y-%m-%dT%H:%M:%S").to_string(), time_zone: "UTC".to_string() }
+    }
+
+    /// 終日予定は、開始と終了をその日の0時にする
+    fn date(date: &NaiveDate) -> Self {
+        Self { date_time: format!("{}T00:00:00", date), time_zone: "UTC".to_string() }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphLocation {
+    pub(crate) display_name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GraphExtendedProperty {
+    pub(crate) id: String,
+    pub(crate) value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use reqwest::StatusCode;
+
+    use crate::apis::microsoft_graph::{GraphDateTime, GraphEvent, GraphResponse, GAROON_ID_PROPERTY};
+    use crate::models::event::{Attendee, Event, EventId, EventTime, Title};
+    use crate::utils::date_range::DateRange;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    #[test]
+    fn test_from_event() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("会議".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![Attendee::new("user1".to_string()), Attendee::new("user2".to_string())],
+        )
+            .with_location(Some("会議室A".to_string()))
+            .with_private(true);
+
+        let graph_event = GraphEvent::from(&event);
+
+        assert_eq!(graph_event.subject, "会議");
+        assert_eq!(graph_event.body.content, "参加者: user1, user2");
+        assert_eq!(graph_event.start, GraphDateTime { date_time: "2021-01-01T00:00:00".to_string(), time_zone: "UTC".to_string() });
+        assert_eq!(graph_event.end.date_time, "2021-01-01T01:00:00");
+        assert!(!graph_event.is_all_day);
+        assert_eq!(graph_event.location.display_name, "会議室A");
+        assert_eq!(graph_event.sensitivity, "private");
+        assert_eq!(graph_event.single_value_extended_properties[0].id, GAROON_ID_PROPERTY);
+        assert_eq!(graph_event.single_value_extended_properties[0].value, "1");
+    }
+
+    #[test]
+    fn test_from_event_終日予定() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("休暇".to_string()),
+            EventTime::AllDay(DateRange::new(
+                NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2021, 1, 2).unwrap(),
+            )),
+            vec![],
+        );
+
+        let graph_event = GraphEvent::from(&event);
+
+        assert!(graph_event.is_all_day);
+        assert_eq!(graph_event.start.date_time, "2021-01-01T00:00:00");
+        assert_eq!(graph_event.end.date_time, "2021-01-03T00:00:00");
+        // JSONの項目名はcamelCase
+        let json = serde_json::to_value(&graph_event).unwrap();
+        assert_eq!(json["isAllDay"], true);
+        assert_eq!(json["singleValueExtendedProperties"][0]["value"], "1");
+    }
+
+    #[test]
+    fn test_graph_response() {
+        let created = GraphResponse::new(StatusCode::CREATED, serde_json::json!({ "id": "AAMk" }));
+        let failed = GraphResponse::new(StatusCode::BAD_REQUEST, serde_json::json!({ "error": { "code": "ErrorInvalidRequest", "message": "bad time zone" } }));
+
+        assert_eq!(created.event_id(), Some("AAMk".to_string()));
+        assert_eq!(failed.error_message(), "Microsoft Graph returned 400 Bad Request: bad time zone");
+        assert_eq!(GraphResponse::new(StatusCode::NOT_FOUND, serde_json::Value::Null).error_message(), "Microsoft Graph returned 404 Not Found");
+    }
+}
@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::time::sleep;
+
+use crate::apis::microsoft_auth::MicrosoftOAuth;
+use crate::apis::microsoft_graph::{GraphRequest, GraphResponse, MicrosoftGraphClient, GAROON_ID_PROPERTY};
+use crate::apis::retry::RetryPolicy;
+use crate::error::Error;
+
+/// 1回のJSONバッチに含められるリクエストの上限
+const MAX_BATCH_SIZE: usize = 20;
+
+pub struct MicrosoftGraphRestClient {
+    client: Client,
+    base_url: String,
+    calendar_id: Option<String>,
+    auth: MicrosoftOAuth,
+    retry_policy: RetryPolicy,
+}
+impl MicrosoftGraphRestClient {
+    /// `base_url`は`https://graph.microsoft.com/v1.0`のようにバージョンまで含める
+    pub fn new(base_url: String, auth: MicrosoftOAuth) -> Self {
+        Self { client: Client::new(), base_url, calendar_id: None, auth, retry_policy: RetryPolicy::default() }
+    }
+
+    /// 省略時は既定のカレンダー
+    pub fn with_calendar_id(mut self, calendar_id: Option<String>) -> Self {
+        self.calendar_id = calendar_id;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// バッチの中のURLはバージョンからの相対パスで指定する
+    fn events_path(&self, event_id: Option<&str>) -> String {
+        // カレンダーIDや予定IDには`/`や`=`が含まれうるため、パスセグメントとしてエンコードする
+        let mut url = Url::parse("https://graph.invalid/").unwrap();
+        {
+            let mut segments = url.path_segments_mut().unwrap();
+            segments.pop_if_empty().push("me");
+            match &self.calendar_id {
+                Some(calendar_id) => segments.extend(["calendars", calendar_id]),
+                None => segments.push("calendar"),
+            };
+            segments.push("events");
+            if let Some(event_id) = event_id {
+                segments.push(event_id);
+            }
+        }
+
+        url.path().to_string()
+    }
+
+    /// 認証エラーはトークンをリフレッシュして1回だけ、スロットリング(429)などの一時的なエラーは`Retry-After`に従って再試行する
+    async fn send(&self, request: impl Fn(&Client) -> RequestBuilder) -> Result<Response, Error> {
+        let mut attempt = 1;
+        let mut refreshed = false;
+        loop {
+            let access_token = self.auth.access_token(&self.client).await?;
+            let retry_after = match request(&self.client).bearer_auth(access_token).send().await {
+                Ok(response) if response.status() == StatusCode::UNAUTHORIZED && !refreshed => {
+                    refreshed = true;
+                    if self.auth.refresh(&self.client).await? {
+                        continue;
+                    }
+                    return Ok(response.error_for_status()?);
+                }
+                Ok(response) if RetryPolicy::is_retryable_status(response.status()) && self.retry_policy.can_retry(attempt) => {
+                    RetryPolicy::retry_after(response.headers(), Utc::now())
+                }
+                Ok(response) => return Ok(response.error_for_status()?),
+                Err(e) if RetryPolicy::is_retryable_error(&e) && self.retry_policy.can_retry(attempt) => None,
+                Err(e) => return Err(e.into()),
+            };
+            sleep(self.retry_policy.delay(attempt, retry_after)).await;
+            attempt += 1;
+        }
+    }
+
+    /// `requests`の番号をバッチ内のIDにして送り、番号と結果の組を返す
+    async fn send_batch(&self, requests: &[(usize, &GraphRequest)]) -> Result<Vec<(usize, BatchResponse)>, Error> {
+        let body = json!({
+            "requests": requests.iter().map(|(index, request)| self.batch_request(*index, request)).collect::<Vec<_>>(),
+        });
+        let response = self
+            .send(|client| client.post(format!("{}/$batch", self.base_url)).json(&body))
+            .await?
+            .json::<BatchResponses>()
+            .await?;
+
+        response
+            .responses
+            .into_iter()
+            .map(|response| {
+                let index = response.id.parse::<usize>().map_err(|_| Error::Graph(format!("unknown batch response id {}", response.id)))?;
+                Ok((index, response))
+            })
+            .collect()
+    }
+
+    fn batch_request(&self, index: usize, request: &GraphRequest) -> serde_json::Value {
+        let (method, url, event) = match request {
+            GraphRequest::Create(event) => ("POST", self.events_path(None), Some(event)),
+            GraphRequest::Update { event_id, event } => ("PATCH", self.events_path(Some(event_id)), Some(event)),
+            GraphRequest::Delete { event_id } => ("DELETE", self.events_path(Some(event_id)), None),
+        };
+        match event {
+            Some(event) => json!({
+                "id": index.to_string(),
+                "method": method,
+                "url": url,
+                "headers": { "Content-Type": "application/json" },
+                "body": event,
+            }),
+            None => json!({ "id": index.to_string(), "method": method, "url": url }),
+        }
+    }
+}
+#[async_trait]
+impl MicrosoftGraphClient for MicrosoftGraphRestClient {
+    async fn list_synced(&self) -> Result<HashMap<String, String>, Error> {
+        let mut url = Url::parse(&format!("{}{}", self.base_url, self.events_path(None)))
+            .map_err(|e| Error::Config(format!("invalid Microsoft Graph base url: {}", e)))?;
+        url.query_pairs_mut()
+            .append_pair("$filter", &format!("singleValueExtendedProperties/Any(ep: ep/id eq '{}' and ep/value ne null)", GAROON_ID_PROPERTY))
+            .append_pair("$expand", &format!("singleValueExtendedProperties($filter=id eq '{}')", GAROON_ID_PROPERTY))
+            .append_pair("$select", "id")
+            .append_pair("$top", "100");
+
+        let mut synced = HashMap::new();
+        let mut next = Some(url.to_string());
+        // 結果が多い場合は`@odata.nextLink`でページを分けて返る
+        while let Some(url) = next {
+            let page = self.send(|client| client.get(&url)).await?.json::<EventsPage>().await?;
+            for event in page.value {
+                if let Some(property) = event.single_value_extended_properties.into_iter().find(|property| property.id == GAROON_ID_PROPERTY) {
+                    synced.insert(property.value, event.id);
+                }
+            }
+            next = page.next_link;
+        }
+
+        Ok(synced)
+    }
+
+    async fn batch(&self, requests: Vec<GraphRequest>) -> Result<Vec<GraphResponse>, Error> {
+        let mut responses: Vec<Option<GraphResponse>> = vec![None; requests.len()];
+        for start in (0..requests.len()).step_by(MAX_BATCH_SIZE) {
+            let mut pending: Vec<(usize, &GraphRequest)> = requests.iter().enumerate().skip(start).take(MAX_BATCH_SIZE).collect();
+            let mut attempt = 1;
+            // バッチ全体が成功しても、個々のリクエストがスロットリングされることがあるので、それだけを送り直す
+            while !pending.is_empty() {
+                let mut throttled = vec![];
+                let mut retry_after = None;
+                for (index, response) in self.send_batch(&pending).await? {
+                    let request = requests.get(index).ok_or_else(|| Error::Graph(format!("unknown batch response id {}", index)))?;
+                    let status = StatusCode::from_u16(response.status).map_err(|e| Error::Graph(e.to_string()))?;
+                    if RetryPolicy::is_retryable_status(status) && self.retry_policy.can_retry(attempt) {
+                        retry_after = retry_after.max(RetryPolicy::retry_after(&response.header_map(), Utc::now()));
+                        throttled.push((index, request));
+                    } else {
+                        responses[index] = Some(GraphResponse::new(status, response.body));
+                    }
+                }
+                if !throttled.is_empty() {
+                    sleep(self.retry_policy.delay(attempt, retry_after)).await;
+                    attempt += 1;
+                }
+                pending = throttled;
+            }
+        }
+
+        responses
+            .into_iter()
+            .enumerate()
+            .map(|(index, response)| response.ok_or_else(|| Error::Graph(format!("no batch response for request {}", index))))
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchResponses {
+    responses: Vec<BatchResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchResponse {
+    id: String,
+    status: u16,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: serde_json::Value,
+}
+impl BatchResponse {
+    fn header_map(&self) -> HeaderMap {
+        self.headers
+            .iter()
+            .filter_map(|(name, value)| Some((HeaderName::try_from(name.as_str()).ok()?, HeaderValue::try_from(value.as_str()).ok()?)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsPage {
+    value: Vec<SyncedEvent>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SyncedEvent {
+    id: String,
+    #[serde(default)]
+    single_value_extended_properties: Vec<SyncedProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncedProperty {
+    id: String,
+    value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+    use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::apis::microsoft_auth::{MicrosoftOAuth, MicrosoftOAuthConfig};
+    use crate::apis::microsoft_graph::{GraphEvent, GraphRequest, MicrosoftGraphClient, GAROON_ID_PROPERTY};
+    use crate::apis::microsoft_graph_rest::MicrosoftGraphRestClient;
+    use crate::apis::retry::RetryPolicy;
+    use crate::models::event::{Event, EventId, Title};
+    use crate::models::oauth_token::OAuthToken;
+    use crate::repositories::token_repository::MockTokenRepository;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn graph_event(subject: &str) -> GraphEvent {
+        let now = chrono::Utc::now();
+        GraphEvent::from(&Event::new(EventId::new("1".to_string()), Title::new(subject.to_string()), DateTimeRange::new(now, now), vec![]))
+    }
+
+    fn client(mock_server: &MockServer) -> MicrosoftGraphRestClient {
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_load().returning(|| Ok(Some(OAuthToken::new("access".to_string(), Some("refresh".to_string()), None))));
+        let config = MicrosoftOAuthConfig {
+            authority_url: format!("{}/organizations/oauth2/v2.0", mock_server.uri()),
+            client_id: "client".to_string(),
+            scope: "offline_access Calendars.ReadWrite".to_string(),
+        };
+
+        MicrosoftGraphRestClient::new(format!("{}/v1.0", mock_server.uri()), MicrosoftOAuth::new(config, token_repository))
+            .with_calendar_id(Some("AAMk/work=".to_string()))
+            .with_retry_policy(RetryPolicy { max_attempts: 3, base_delay: Duration::ZERO, max_delay: Duration::ZERO })
+    }
+
+    #[tokio::test]
+    async fn batch_正常系() {
+        // Setup: 順不同で返る結果を、リクエストの順に並べ直すことを設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1.0/$batch"))
+            .and(header("Authorization", "Bearer access"))
+            .and(body_string_contains(r#""url":"/me/calendars/AAMk%2Fwork=/events""#))
+            .and(body_string_contains(r#""method":"DELETE""#))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "responses": [
+                    { "id": "1", "status": 204 },
+                    { "id": "0", "status": 201, "body": { "id": "AAMk-1" } },
+                ]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Exercise
+        let requests = vec![GraphRequest::Create(graph_event("会議")), GraphRequest::Delete { event_id: "AAMk-2".to_string() }];
+        let result = client(&mock_server).batch(requests).await;
+
+        // Asserts
+        let responses = result.unwrap();
+        assert_eq!(responses[0].event_id(), Some("AAMk-1".to_string()));
+        assert_eq!(responses[1].status, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn batch_スロットリングされたリクエストだけ送り直す() {
+        // Setup: 1回目は2件目だけが429になり、2回目に2件目だけを送ると成功するよう設定する
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1.0/$batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "responses": [
+                    { "id": "0", "status": 201, "body": { "id": "AAMk-1" } },
+                    { "id": "1", "status": 429, "headers": { "Retry-After": "0" }, "body": { "error": { "code": "ApplicationThrottled" } } },
+                ]
+            })))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1.0/$batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "responses": [{ "id": "1", "status": 201, "body": { "id": "AAMk-2" } }]
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Exercise
+        let requests = vec![GraphRequest::Create(graph_event("会議")), GraphRequest::Create(graph_event("面談"))];
+        let result = client(&mock_server).batch(requests).await;
+
+        // Asserts
+        let ids: Vec<Option<String>> = result.unwrap().iter().map(|response| response.event_id()).collect();
+        assert_eq!(ids, vec![Some("AAMk-1".to_string()), Some("AAMk-2".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn batch_20件ずつ送る() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1.0/$batch"))
+            .respond_with(|request: &wiremock::Request| {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                let responses: Vec<serde_json::Value> = body["requests"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|request| serde_json::json!({ "id": request["id"], "status": 204 }))
+                    .collect();
+                assert!(responses.len() <= 20);
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "responses": responses }))
+            })
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        let requests = (0..25).map(|i| GraphRequest::Delete { event_id: format!("AAMk-{}", i) }).collect();
+        let result = client(&mock_server).batch(requests).await;
+
+        assert_eq!(result.unwrap().len(), 25);
+    }
+
+    #[tokio::test]
+    async fn batch_バッチ全体の429は待って再試行する() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1.0/$batch"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1.0/$batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "responses": [{ "id": "0", "status": 204 }] })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let result = client(&mock_server).batch(vec![GraphRequest::Delete { event_id: "AAMk-1".to_string() }]).await;
+
+        assert_eq!(result.unwrap()[0].status, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn list_synced_ページをたどる() {
+        // Setup: 2ページに分けて、Garoonの予定IDを記録した予定を返すよう設定する
+        let mock_server = MockServer::start().await;
+        let next_link = format!("{}/v1.0/me/calendars/AAMk%2Fwork=/events?$skip=1", mock_server.uri());
+        Mock::given(method("GET"))
+            .and(path("/v1.0/me/calendars/AAMk%2Fwork=/events"))
+            .and(query_param("$select", "id"))
+            .and(query_param("$expand", format!("singleValueExtendedProperties($filter=id eq '{}')", GAROON_ID_PROPERTY)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "value": [{ "id": "AAMk-1", "singleValueExtendedProperties": [{ "id": GAROON_ID_PROPERTY, "value": "1" }] }],
+                "@odata.nextLink": next_link,
+            })))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v1.0/me/calendars/AAMk%2Fwork=/events"))
+            .and(query_param("$skip", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "value": [{ "id": "AAMk-2", "singleValueExtendedProperties": [{ "id": GAROON_ID_PROPERTY, "value": "2" }] }],
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Exercise
+        let result = client(&mock_server).list_synced().await;
+
+        // Asserts
+        let synced = result.unwrap();
+        assert_eq!(synced.get("1"), Some(&"AAMk-1".to_string()));
+        assert_eq!(synced.get("2"), Some(&"AAMk-2".to_string()));
+    }
+
+    #[tokio::test]
+    async fn list_synced_認証エラーはリフレッシュして再試行する() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("Authorization", "Bearer access"))
+            .respond_with(ResponseTemplate::new(401))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/organizations/oauth2/v2.0/token"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "access_token": "new-access", "expires_in": 3600 })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(header("Authorization", "Bearer new-access"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "value": [] })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let mut token_repository = MockTokenRepository::new();
+        token_repository.expect_load().returning(|| Ok(Some(OAuthToken::new("access".to_string(), Some("refresh".to_string()), None))));
+        token_repository.expect_save().returning(|_| Ok(()));
+        let config = MicrosoftOAuthConfig {
+            authority_url: format!("{}/organizations/oauth2/v2.0", mock_server.uri()),
+            client_id: "client".to_string(),
+            scope: "offline_access Calendars.ReadWrite".to_string(),
+        };
+        let client = MicrosoftGraphRestClient::new(format!("{}/v1.0", mock_server.uri()), MicrosoftOAuth::new(config, token_repository));
+
+        let result = client.list_synced().await;
+
+        assert!(result.unwrap().is_empty());
+    }
+}
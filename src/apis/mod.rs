@@ -1,2 +1,11 @@
+pub mod caldav;
+pub mod garoon_auth;
 pub mod garoon_rest;
-pub mod garoon;
\ No newline at end of file
+pub mod garoon;
+pub mod google_calendar_rest;
+pub mod google_calendar;
+pub mod http_client;
+pub mod microsoft_auth;
+pub mod microsoft_graph_rest;
+pub mod microsoft_graph;
+pub mod retry;
\ No newline at end of file
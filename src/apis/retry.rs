@@ -0,0 +1,133 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+
+/// 一時的なエラーで失敗した冪等なリクエストの再試行の方針
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// 最初のリクエストを含む試行回数。1なら再試行しない
+    pub max_attempts: u32,
+    /// 1回目の再試行までの待ち時間の上限。以降は2倍ずつ増やす
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+impl RetryPolicy {
+    pub fn no_retry() -> Self {
+        Self { max_attempts: 1, ..Default::default() }
+    }
+
+    /// `attempt`回目の試行が失敗した後に、もう一度試行するか
+    pub fn can_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    pub fn is_retryable_status(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+        error.is_timeout() || error.is_connect()
+    }
+
+    /// `attempt`回目の試行が失敗した後の待ち時間。サーバーが`Retry-After`を返した場合はそれに従う
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after.min(self.max_delay);
+        }
+
+        // 複数のクライアントが同時に再試行しないよう、上限までの間でランダムに待つ (full jitter)
+        let exponent = attempt.saturating_sub(1).min(16);
+        let ceiling = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+        ceiling.mul_f64(jitter())
+    }
+
+    /// `Retry-After`ヘッダーの秒数またはHTTP日付
+    pub fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+        let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+
+        let date = DateTime::parse_from_rfc2822(value).ok()?;
+        Some((date.with_timezone(&Utc) - now).to_std().unwrap_or(Duration::ZERO))
+    }
+}
+
+/// 0以上1未満の乱数
+fn jitter() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+    use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+    use crate::apis::retry::RetryPolicy;
+
+    #[test]
+    fn test_delay_指数的に増やして上限で止める() {
+        let policy = RetryPolicy { max_attempts: 10, base_delay: Duration::from_secs(1), max_delay: Duration::from_secs(5) };
+
+        for _ in 0..100 {
+            assert!(policy.delay(1, None) < Duration::from_secs(1));
+            assert!(policy.delay(3, None) < Duration::from_secs(4));
+            assert!(policy.delay(10, None) < Duration::from_secs(5));
+        }
+    }
+
+    #[test]
+    fn test_delay_retry_afterを優先する() {
+        let policy = RetryPolicy { max_attempts: 10, base_delay: Duration::from_secs(1), max_delay: Duration::from_secs(60) };
+
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(10))), Duration::from_secs(10));
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(600))), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap();
+        let mut headers = HeaderMap::new();
+
+        headers.insert(RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(RetryPolicy::retry_after(&headers, now), Some(Duration::from_secs(120)));
+
+        headers.insert(RETRY_AFTER, "Fri, 10 May 2024 00:00:30 GMT".parse().unwrap());
+        assert_eq!(RetryPolicy::retry_after(&headers, now), Some(Duration::from_secs(30)));
+
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(RetryPolicy::retry_after(&headers, now), None);
+    }
+
+    #[test]
+    fn test_can_retry() {
+        let policy = RetryPolicy { max_attempts: 3, ..Default::default() };
+
+        assert!(policy.can_retry(2));
+        assert!(!policy.can_retry(3));
+        assert!(!RetryPolicy::no_retry().can_retry(1));
+    }
+}
@@ -0,0 +1,250 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+
+use crate::apis::garoon::GaroonTargetType;
+use crate::error::Error;
+use crate::utils::date_time_range::{start_of_day, DateTimeRange};
+
+/// 取得期間を指定しない場合の日数
+const DEFAULT_DAYS: u32 = 7;
+
+/// GaroonのスケジュールをGoogleカレンダーに転写する
+#[derive(Debug, Parser)]
+#[command(name = "tensha", version)]
+pub struct Cli {
+    /// 設定ファイル。省略時はカレントディレクトリの`tensha.toml`があれば読み込む
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    /// 設定ファイルのプロファイル名
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Garoonの予定を取得して表示する
+    Fetch {
+        #[command(flatten)]
+        range: RangeArgs,
+    },
+    /// Garoonの予定をファイルに出力する
+    Export {
+        #[command(flatten)]
+        range: RangeArgs,
+        /// 出力先のファイル。省略時は設定ファイルの値か、形式に応じて`./events.csv`または`./events.ics`
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 出力形式。省略時は設定ファイルの値か`csv`
+        #[arg(long, value_enum)]
+        format: Option<ExportFormat>,
+    },
+    /// Garoonの予定をGoogleカレンダーに同期する
+    Sync {
+        #[command(flatten)]
+        range: RangeArgs,
+        /// 反映せずに、`plan`と同じく反映内容だけを表示する
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 転写先には反映せず、同期状態を持つ転写先ごとに、同期した場合の反映内容を表示する
+    Plan {
+        #[command(flatten)]
+        range: RangeArgs,
+    },
+    /// 設定とGaroonへの接続を確認する
+    Doctor,
+    /// ユーザーのログイン名や組織・施設のコードから、Garoon上のIDを調べる
+    Lookup {
+        #[arg(value_enum)]
+        target_type: GaroonTargetType,
+        /// ユーザーならログイン名、組織・施設ならコード
+        code: String,
+    },
+    /// プロファイルごとの予定をiCalendarのフィードとして配信する。`--profile`を省略した場合は全てのプロファイルを配信する
+    Serve {
+        /// 待ち受けるアドレス
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: SocketAddr,
+        /// URLの`token`に指定させる秘密の文字列。省略時は`TENSHA_SERVE_TOKEN`
+        #[arg(long)]
+        token: Option<String>,
+        /// Garoonから取得し直す間隔(分)
+        #[arg(long, default_value_t = 15)]
+        refresh_minutes: u64,
+        #[command(flatten)]
+        range: RangeArgs,
+    },
+    /// GaroonのOAuth 2.0を認可する。認可画面のURLを表示し、リダイレクト先で受け取ったコードを`--code`で渡す
+    Auth {
+        /// 認可画面からリダイレクトされたURLの`code`
+        #[arg(long)]
+        code: Option<String>,
+        /// Garoonではなく、Outlookの転写先をデバイスコードで認可する
+        #[arg(long, conflicts_with = "code")]
+        outlook: bool,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// GoogleカレンダーにインポートできるCSV
+    Csv,
+    /// Outlook、Appleカレンダー、ThunderbirdなどにインポートできるiCalendar (RFC 5545)
+    Ics,
+}
+impl ExportFormat {
+    /// 出力先を指定しなかった場合のファイル名
+    pub fn default_path(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "./events.csv",
+            ExportFormat::Ics => "./events.ics",
+        }
+    }
+}
+
+/// 予定を取得する期間。日付は出力タイムゾーン(未設定ならUTC)で解釈する
+#[derive(Clone, Debug, Default, Args)]
+pub struct RangeArgs {
+    /// 取得を開始する日 (YYYY-MM-DD)。省略時は現在時刻から
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// 取得する最終日 (YYYY-MM-DD)
+    #[arg(long, conflicts_with = "days")]
+    pub to: Option<NaiveDate>,
+    /// 取得する日数。省略時は7日
+    #[arg(long)]
+    pub days: Option<u32>,
+}
+impl RangeArgs {
+    /// 日数を指定しなかった場合に使う日数
+    pub fn with_default_days(mut self, days: Option<u32>) -> Self {
+        self.days = self.days.or(days);
+        self
+    }
+
+    pub fn to_range(&self, time_zone: &Tz, now: DateTime<Utc>) -> Result<DateTimeRange, Error> {
+        let start = match self.from {
+            Some(from) => start_of_day(from, time_zone),
+            None => now,
+        };
+        let end = match self.to {
+            Some(to) => start_of_day(to.succ_opt().unwrap_or(to), time_zone),
+            None => start + Duration::days(self.days.unwrap_or(DEFAULT_DAYS).into()),
+        };
+
+        DateTimeRange::try_new(start, end).map_err(|e| Error::Config(format!("invalid range: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use clap::Parser;
+
+    use crate::apis::garoon::GaroonTargetType;
+    use crate::cli::{Cli, Command, RangeArgs};
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn date(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let cli = Cli::parse_from(["tensha", "--config", "team.toml", "export", "--profile", "work", "--from", "2024-05-01", "--days", "3", "-o", "out.csv"]);
+
+        assert_eq!(cli.config, Some("team.toml".into()));
+        assert_eq!(cli.profile, Some("work".to_string()));
+        match cli.command {
+            Command::Export { range, output, format } => {
+                assert_eq!(range.from, date(2024, 5, 1));
+                assert_eq!(range.days, Some(3));
+                assert_eq!(output, Some("out.csv".into()));
+                assert_eq!(format, None);
+            }
+            command => panic!("unexpected command: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn test_parse_toとdaysは同時に指定できない() {
+        let result = Cli::try_parse_from(["tensha", "sync", "--to", "2024-05-10", "--days", "3"]);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_to_range_省略時() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 10, 3, 0, 0).unwrap();
+
+        let range = RangeArgs::default().to_range(&chrono_tz::UTC, now).unwrap();
+
+        assert_eq!(range, DateTimeRange::new(now, Utc.with_ymd_and_hms(2024, 5, 17, 3, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_to_range_日付指定() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 10, 3, 0, 0).unwrap();
+        let args = RangeArgs { from: date(2024, 5, 1), to: date(2024, 5, 31), days: None };
+
+        let range = args.to_range(&chrono_tz::Asia::Tokyo, now).unwrap();
+
+        // 東京の5/1 0時から5/31の終わりまで
+        assert_eq!(range, DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 4, 30, 15, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 5, 31, 15, 0, 0).unwrap(),
+        ));
+    }
+
+    #[test]
+    fn test_to_range_終了日が開始日より前() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 10, 3, 0, 0).unwrap();
+        let args = RangeArgs { from: date(2024, 5, 10), to: date(2024, 5, 1), days: None };
+
+        assert!(args.to_range(&chrono_tz::UTC, now).is_err());
+    }
+
+    #[test]
+    fn test_parse_lookup() {
+        let cli = Cli::parse_from(["tensha", "lookup", "facility", "room-a"]);
+
+        assert!(matches!(cli.command, Command::Lookup { target_type: GaroonTargetType::Facility, code } if code == "room-a"));
+    }
+
+    #[test]
+    fn test_parse_auth() {
+        let cli = Cli::parse_from(["tensha", "auth", "--code", "abc"]);
+
+        assert!(matches!(cli.command, Command::Auth { code: Some(code), outlook: false } if code == "abc"));
+    }
+
+    #[test]
+    fn test_parse_sync_dry_run() {
+        let cli = Cli::parse_from(["tensha", "sync", "--dry-run", "--days", "3"]);
+
+        assert!(matches!(cli.command, Command::Sync { range: RangeArgs { days: Some(3), .. }, dry_run: true }));
+    }
+
+    #[test]
+    fn test_parse_serve() {
+        let cli = Cli::parse_from(["tensha", "serve", "--bind", "0.0.0.0:9000", "--days", "30"]);
+
+        match cli.command {
+            Command::Serve { bind, token, refresh_minutes, range } => {
+                assert_eq!(bind.to_string(), "0.0.0.0:9000");
+                assert_eq!(token, None);
+                assert_eq!(refresh_minutes, 15);
+                assert_eq!(range.days, Some(30));
+            }
+            command => panic!("unexpected command: {:?}", command),
+        }
+    }
+}
@@ -0,0 +1,472 @@
+pub mod profile;
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono_tz::Tz;
+use dotenv::dotenv;
+use crate::apis::caldav::CalDavClient;
+use crate::apis::garoon::{GaroonGetEventsOptions, GaroonTarget};
+use crate::apis::garoon_auth::{GaroonAuth, GaroonOAuth2Auth, GaroonOAuth2Config, GaroonSessionAuth};
+use crate::apis::garoon_rest::GaroonRestClient;
+use crate::apis::http_client::{BasicAuth, ClientIdentity, HttpClientOptions};
+use crate::apis::microsoft_auth::{MicrosoftOAuth, MicrosoftOAuthConfig};
+use crate::apis::microsoft_graph_rest::MicrosoftGraphRestClient;
+use crate::apis::retry::RetryPolicy;
+use crate::apis::google_calendar_rest::GoogleCalendarRestClient;
+use crate::cli::ExportFormat;
+use crate::config::profile::{check_url, required, ConfigFile, GaroonAuthKind, Profile, SinkSettings, SourceSettings, DEFAULT_OUTLOOK_TOKEN_PATH, DEFAULT_STATE_PATH};
+use crate::error::Error;
+use crate::models::event_filter::EventFilter;
+use crate::models::sync_plan::DeletionGuard;
+use crate::repositories::sync_state_repository::JsonSyncStateRepository;
+use crate::repositories::token_repository::JsonTokenRepository;
+use crate::services::calendar_sync_service::CalendarSyncService;
+use crate::services::export_events_service::CsvColumn;
+use crate::sinks::caldav_sink::CalDavSink;
+use crate::sinks::csv_sink::CsvSink;
+use crate::sinks::event_sink::EventSink;
+use crate::sinks::google_calendar_sink::GoogleCalendarSink;
+use crate::sinks::ics_sink::IcsSink;
+use crate::sinks::outlook_sink::OutlookSink;
+use crate::sources::caldav_source::CalDavSource;
+use crate::sources::event_source::EventSource;
+use crate::sources::garoon_source::{FetchChunking, GaroonSource};
+use crate::sources::ics_file_source::IcsFileSource;
+use crate::sources::json_source::JsonSource;
+use crate::utils::time_zone::parse_time_zone;
+
+/// 設定ファイルのプロファイルに、`.env`と環境変数の値を重ねる
+pub fn load_profile(path: Option<&Path>, name: Option<&str>) -> Result<Profile, Error> {
+    dotenv().ok();
+
+    let config = ConfigFile::load(path)?;
+    config.check_shared_files(name, |name| env::var(name).ok())?;
+    let mut profile = config.profile(name)?;
+    profile.apply_env(|name| env::var(name).ok())?;
+
+    Ok(profile)
+}
+
+/// `serve`で配信するプロファイルと、その名前。`name`を省略した場合は設定ファイルの全てのプロファイル
+pub fn load_profiles(path: Option<&Path>, name: Option<&str>) -> Result<Vec<(String, Profile)>, Error> {
+    dotenv().ok();
+
+    let config = ConfigFile::load(path)?;
+    config.check_shared_files(name, |name| env::var(name).ok())?;
+    let names: Vec<String> = match name {
+        Some(name) => vec![name.to_string()],
+        None => config.profiles.keys().cloned().collect(),
+    };
+    // 設定ファイルがなければ、環境変数だけで設定したプロファイルを`default`として配信する
+    if names.is_empty() {
+        return Ok(vec![("default".to_string(), load_profile(path, None)?)]);
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            let mut profile = config.profile(Some(&name))?;
+            profile.apply_env(|name| env::var(name).ok())?;
+            Ok((name, profile))
+        })
+        .collect()
+}
+
+pub fn initialize_rest_clients(profile: &Profile) -> Result<GaroonRestClient, Error> {
+    let garoon = &profile.garoon;
+    let base_url = required(&garoon.base_url, "garoon.base_url", "GAROON_BASE_URL")?;
+    check_url(base_url, "garoon.base_url")?;
+
+    let client = match garoon.auth {
+        GaroonAuthKind::Password => {
+            let user_id = required(&garoon.user_id, "garoon.user_id", "GAROON_USER_ID")?;
+            let password = required(&garoon.password, "garoon.password", "GAROON_PASSWORD")?;
+            let auth = GaroonAuth { user_id: user_id.to_string(), password: password.to_string() };
+            GaroonRestClient::new(base_url.to_string(), auth)
+        }
+        GaroonAuthKind::OAuth2 => GaroonRestClient::new(base_url.to_string(), initialize_garoon_oauth(profile)?),
+        GaroonAuthKind::Session => {
+            let user_id = required(&garoon.user_id, "garoon.user_id", "GAROON_USER_ID")?;
+            let password = required(&garoon.password, "garoon.password", "GAROON_PASSWORD")?;
+            let login_url = garoon.session.login_url.clone()
+                .unwrap_or_else(|| format!("{}/api/auth/login.json", origin(base_url)));
+            let auth = GaroonSessionAuth::new(login_url, user_id.to_string(), password.to_string());
+            GaroonRestClient::new(base_url.to_string(), auth)
+        }
+    };
+
+    Ok(client
+        .with_client(initialize_garoon_http_client(profile)?)
+        .with_retry_policy(initialize_retry_policy(profile)))
+}
+
+/// コードで指定された場合は、Garoonに問い合わせてIDを求める
+pub async fn initialize_target(profile: &Profile, client: &GaroonRestClient) -> Result<Option<GaroonTarget>, Error> {
+    let Some(target) = &profile.garoon.target else {
+        return Ok(None);
+    };
+
+    match (&target.id, &target.code) {
+        (Some(id), _) => Ok(Some(GaroonTarget::new(target.target_type, id.clone()))),
+        (None, Some(code)) => Ok(Some(client.find_target(target.target_type, code).await?)),
+        (None, None) => Err(Error::Config("garoon.target requires exactly one of id and code".to_string())),
+    }
+}
+
+/// Garoonへの接続に使うHTTPクライアント。OAuth 2.0のトークン取得やログインにも使う
+pub fn initialize_garoon_http_client(profile: &Profile) -> Result<reqwest::Client, Error> {
+    let connection = &profile.garoon.connection;
+    let basic_auth = match &connection.basic_auth_user {
+        Some(user) => Some(BasicAuth {
+            user: user.clone(),
+            password: required(&connection.basic_auth_password, "garoon.connection.basic_auth_password", "GAROON_BASIC_AUTH_PASSWORD")?.to_string(),
+        }),
+        None => None,
+    };
+    let identity = connection.client_certificate.clone().map(|certificate| match &connection.client_key {
+        Some(key) => ClientIdentity::Pem { certificate, key: key.clone() },
+        None => ClientIdentity::Pkcs12 {
+            path: certificate,
+            password: connection.client_certificate_password.clone().unwrap_or_default(),
+        },
+    });
+
+    HttpClientOptions {
+        basic_auth,
+        identity,
+        ca_bundle: connection.ca_bundle.clone(),
+        proxy: connection.proxy.clone(),
+        timeout: connection.timeout_secs.map(Duration::from_secs),
+    }
+        .build()
+}
+
+pub fn initialize_retry_policy(profile: &Profile) -> RetryPolicy {
+    let retry = &profile.garoon.retry;
+    let default = RetryPolicy::default();
+
+    RetryPolicy {
+        max_attempts: retry.max_attempts.unwrap_or(default.max_attempts),
+        base_delay: retry.base_delay_ms.map(Duration::from_millis).unwrap_or(default.base_delay),
+        max_delay: retry.max_delay_ms.map(Duration::from_millis).unwrap_or(default.max_delay),
+    }
+}
+
+/// `tensha auth`でトークンを取得するときにも使う
+pub fn initialize_garoon_oauth(profile: &Profile) -> Result<GaroonOAuth2Auth, Error> {
+    let base_url = required(&profile.garoon.base_url, "garoon.base_url", "GAROON_BASE_URL")?;
+    check_url(base_url, "garoon.base_url")?;
+    let oauth2 = &profile.garoon.oauth2;
+    let client_id = required(&oauth2.client_id, "garoon.oauth2.client_id", "GAROON_OAUTH_CLIENT_ID")?;
+    let client_secret = required(&oauth2.client_secret, "garoon.oauth2.client_secret", "GAROON_OAUTH_CLIENT_SECRET")?;
+    let redirect_uri = oauth2.redirect_uri.as_deref()
+        .ok_or_else(|| Error::Config("garoon.oauth2.redirect_uri is required".to_string()))?;
+
+    let config = GaroonOAuth2Config {
+        authorization_url: oauth2.authorization_url.clone().unwrap_or_else(|| format!("{}/oauth2/authorization", origin(base_url))),
+        token_url: oauth2.token_url.clone().unwrap_or_else(|| format!("{}/oauth2/token", origin(base_url))),
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string(),
+        redirect_uri: redirect_uri.to_string(),
+        scope: oauth2.scope.clone().unwrap_or("g:Schedule:read".to_string()),
+    };
+    Ok(GaroonOAuth2Auth::new(config, JsonTokenRepository::new(profile.token_path())))
+}
+
+/// `https://example.cybozu.com/g`なら`https://example.cybozu.com`
+fn origin(base_url: &str) -> String {
+    reqwest::Url::parse(base_url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or(base_url.to_string())
+}
+
+pub fn initialize_google_client(profile: &Profile) -> Result<GoogleCalendarRestClient, Error> {
+    let google = &profile.google;
+    let base_url = google.base_url.clone().unwrap_or("https://www.googleapis.com".to_string());
+    check_url(&base_url, "google.base_url")?;
+    let calendar_id = required(&google.calendar_id, "google.calendar_id", "GOOGLE_CALENDAR_ID")?;
+    let access_token = required(&google.access_token, "google.access_token", "GOOGLE_ACCESS_TOKEN")?;
+
+    Ok(GoogleCalendarRestClient::new(base_url, calendar_id.to_string(), access_token.to_string()))
+}
+
+pub fn initialize_state_repository(profile: &Profile) -> Result<JsonSyncStateRepository, Error> {
+    let path = profile.sync.state_path.clone().unwrap_or(PathBuf::from(DEFAULT_STATE_PATH));
+
+    Ok(JsonSyncStateRepository::new(path))
+}
+
+pub fn initialize_deletion_guard(profile: &Profile) -> Result<DeletionGuard, Error> {
+    let default = DeletionGuard::default();
+    let max_ratio = profile.sync.max_delete_ratio.unwrap_or(default.max_ratio);
+    let min_count = profile.sync.min_delete_count.unwrap_or(default.min_count);
+
+    Ok(DeletionGuard::new(max_ratio, min_count))
+}
+
+/// CSVに出力する列。設定しなければ全ての列
+pub fn initialize_csv_columns(profile: &Profile) -> Vec<CsvColumn> {
+    profile.output.csv_columns.clone().unwrap_or_else(|| CsvColumn::ALL.to_vec())
+}
+
+/// 未設定の場合は、予定ごとのタイムゾーンで表示する
+pub fn initialize_output_time_zone(profile: &Profile) -> Result<Option<Tz>, Error> {
+    profile
+        .output
+        .time_zone
+        .as_deref()
+        .map(|time_zone| parse_time_zone(time_zone).map_err(|e| Error::Config(format!("output.time_zone: {}", e))))
+        .transpose()
+}
+
+/// CSVやiCalendarなどの出力先と形式。コマンドラインで指定した値を優先する
+pub fn initialize_output(profile: &Profile, path: Option<PathBuf>, format: Option<ExportFormat>) -> (PathBuf, ExportFormat) {
+    let format = format.or(profile.output.format).unwrap_or(ExportFormat::Csv);
+    let path = path.or(profile.output.path.clone()).unwrap_or(PathBuf::from(format.default_path()));
+
+    (path, format)
+}
+
+pub fn initialize_filter(profile: &Profile) -> EventFilter {
+    EventFilter::new(profile.filters.exclude_subjects.clone(), profile.filters.exclude_private)
+}
+
+pub fn initialize_fetch_options(profile: &Profile) -> Result<GaroonGetEventsOptions, Error> {
+    let fetch = &profile.fetch;
+    let order_by = fetch
+        .order_by
+        .as_deref()
+        .map(|order_by| order_by.parse().map_err(|e| Error::Config(format!("fetch.order_by: {}", e))))
+        .transpose()?;
+
+    Ok(GaroonGetEventsOptions {
+        keyword: fetch.keyword.clone(),
+        exclude_from_search: fetch.exclude_from_search.clone(),
+        fields: fetch.fields.clone(),
+        order_by,
+        show_private: fetch.show_private,
+        limit: fetch.limit,
+    })
+}
+
+/// 同時に問い合わせる数の既定値
+const DEFAULT_FETCH_CONCURRENCY: usize = 4;
+
+/// `fetch.chunk_days`を指定しなければ、期間を分けずに取得する
+pub fn initialize_chunking(profile: &Profile) -> Option<FetchChunking> {
+    profile.fetch.chunk_days.map(|days| FetchChunking {
+        size: chrono::Duration::days(days.into()),
+        concurrency: profile.fetch.concurrency.unwrap_or(DEFAULT_FETCH_CONCURRENCY),
+    })
+}
+
+pub fn initialize_google_sink(profile: &Profile) -> Result<GoogleCalendarSink<GoogleCalendarRestClient, JsonSyncStateRepository>, Error> {
+    let sink = GoogleCalendarSink::new(initialize_google_client(profile)?, initialize_state_repository(profile)?)
+        .with_deletion_guard(initialize_deletion_guard(profile)?);
+
+    Ok(sink)
+}
+
+/// CSVやiCalendarのファイルへの転写先
+pub fn initialize_file_sink(profile: &Profile, path: PathBuf, format: ExportFormat) -> Result<Box<dyn EventSink>, Error> {
+    let sink: Box<dyn EventSink> = match format {
+        ExportFormat::Csv => Box::new(
+            CsvSink::new(path)
+                .with_time_zone(initialize_output_time_zone(profile)?)
+                .with_columns(initialize_csv_columns(profile)),
+        ),
+        ExportFormat::Ics => Box::new(IcsSink::new(path)),
+    };
+
+    Ok(sink)
+}
+
+pub fn initialize_sinks(profile: &Profile) -> Result<Vec<Box<dyn EventSink>>, Error> {
+    profile
+        .sinks()
+        .into_iter()
+        .map(|sink| match sink {
+            SinkSettings::Google => Ok(Box::new(initialize_google_sink(profile)?) as Box<dyn EventSink>),
+            SinkSettings::Csv { path } => initialize_file_sink(profile, path.unwrap_or(PathBuf::from(ExportFormat::Csv.default_path())), ExportFormat::Csv),
+            SinkSettings::Ics { path } => initialize_file_sink(profile, path.unwrap_or(PathBuf::from(ExportFormat::Ics.default_path())), ExportFormat::Ics),
+            SinkSettings::Caldav { .. } => Ok(Box::new(initialize_caldav_sink(profile, &sink)?) as Box<dyn EventSink>),
+            SinkSettings::Outlook { .. } => Ok(Box::new(initialize_outlook_sink(profile, &sink)?) as Box<dyn EventSink>),
+        })
+        .collect()
+}
+
+/// カレンダーを探すのは最初の同期の時で、ここではサーバーに問い合わせない
+pub fn initialize_caldav_sink(profile: &Profile, sink: &SinkSettings) -> Result<CalDavSink<JsonSyncStateRepository>, Error> {
+    let SinkSettings::Caldav { url, calendar, user, password, .. } = sink else {
+        return Err(Error::Config("not a caldav sink".to_string()));
+    };
+    check_url(url, "sinks.url")?;
+    let basic_auth = user.clone().map(|user| BasicAuth { user, password: password.clone().unwrap_or_default() });
+    let state_repository = JsonSyncStateRepository::new(profile.state_path(sink).unwrap_or_default());
+
+    Ok(CalDavSink::new(CalDavClient::new().with_basic_auth(basic_auth), url.clone(), state_repository)
+        .with_calendar(calendar.clone())
+        .with_deletion_guard(initialize_deletion_guard(profile)?))
+}
+
+pub fn initialize_outlook_sink(profile: &Profile, sink: &SinkSettings) -> Result<OutlookSink<MicrosoftGraphRestClient, JsonSyncStateRepository>, Error> {
+    let SinkSettings::Outlook { calendar_id, base_url, .. } = sink else {
+        return Err(Error::Config("not an outlook sink".to_string()));
+    };
+    let base_url = base_url.clone().unwrap_or("https://graph.microsoft.com/v1.0".to_string());
+    check_url(&base_url, "sinks.base_url")?;
+    let client = MicrosoftGraphRestClient::new(base_url, initialize_microsoft_oauth(sink)?).with_calendar_id(calendar_id.clone());
+    let state_repository = JsonSyncStateRepository::new(profile.state_path(sink).unwrap_or_default());
+
+    Ok(OutlookSink::new(client, state_repository).with_deletion_guard(initialize_deletion_guard(profile)?))
+}
+
+/// Outlookの転写先の認可に使う。予定の読み書きと、リフレッシュトークンの発行を求める
+pub fn initialize_microsoft_oauth(sink: &SinkSettings) -> Result<MicrosoftOAuth, Error> {
+    let SinkSettings::Outlook { client_id, tenant, token_path, authority_url, .. } = sink else {
+        return Err(Error::Config("not an outlook sink".to_string()));
+    };
+    let tenant = tenant.as_deref().unwrap_or("organizations");
+    let authority_url = authority_url.clone().unwrap_or_else(|| format!("https://login.microsoftonline.com/{}/oauth2/v2.0", tenant));
+    check_url(&authority_url, "sinks.authority_url")?;
+    let config = MicrosoftOAuthConfig {
+        authority_url,
+        client_id: client_id.clone(),
+        scope: "offline_access Calendars.ReadWrite".to_string(),
+    };
+    let token_path = token_path.clone().unwrap_or(PathBuf::from(DEFAULT_OUTLOOK_TOKEN_PATH));
+
+    Ok(MicrosoftOAuth::new(config, JsonTokenRepository::new(token_path)))
+}
+
+/// Garoonの取得対象をコードで指定した場合は、Garoonに問い合わせてIDを求める
+pub async fn initialize_sources(profile: &Profile) -> Result<Vec<Box<dyn EventSource>>, Error> {
+    // タイムゾーンの指定がないファイルの日時は、出力と同じタイムゾーンで解釈する
+    let time_zone = initialize_output_time_zone(profile)?.unwrap_or(Tz::UTC);
+    let mut sources: Vec<Box<dyn EventSource>> = vec![];
+    for source in profile.sources() {
+        match source {
+            SourceSettings::Garoon => {
+                let client = initialize_rest_clients(profile)?;
+                let target = initialize_target(profile, &client).await?;
+                let source = GaroonSource::new(client)
+                    .with_target(target)
+                    .with_options(initialize_fetch_options(profile)?)
+                    .with_chunking(initialize_chunking(profile));
+                sources.push(Box::new(source));
+            }
+            SourceSettings::Ics { path } => sources.push(Box::new(IcsFileSource::new(path).with_time_zone(time_zone))),
+            SourceSettings::Caldav { url, user, password, .. } => {
+                check_url(&url, "sources.url")?;
+                let basic_auth = user.map(|user| BasicAuth { user, password: password.unwrap_or_default() });
+                let client = CalDavClient::new().with_basic_auth(basic_auth);
+                sources.push(Box::new(CalDavSource::new(client, url).with_time_zone(time_zone)));
+            }
+            SourceSettings::Json { path } => sources.push(Box::new(JsonSource::new(path))),
+        }
+    }
+
+    Ok(sources)
+}
+
+/// 転写先は`sync`でだけ必要なので、`CalendarSyncService::with_sinks`で別に渡す
+pub fn initialize_service(profile: &Profile, sources: Vec<Box<dyn EventSource>>) -> Result<CalendarSyncService, Error> {
+    let service = CalendarSyncService::new(sources).with_filter(initialize_filter(profile));
+
+    Ok(service)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::profile::Profile;
+
+    fn profile() -> Profile {
+        let mut profile = Profile::default();
+        profile.garoon.base_url = Some("https://example.com".to_string());
+        profile.garoon.user_id = Some("user".to_string());
+        profile.garoon.password = Some("password".to_string());
+        profile.google.calendar_id = Some("primary".to_string());
+        profile.google.access_token = Some("token".to_string());
+
+        profile
+    }
+
+    #[test]
+    fn test_initialize_rest_clients() {
+        use super::initialize_rest_clients;
+
+        let result = initialize_rest_clients(&profile());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_initialize_google_client() {
+        use super::initialize_google_client;
+
+        let result = initialize_google_client(&profile());
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_initialize_sinks() {
+        use super::initialize_sinks;
+        use crate::config::profile::SinkSettings;
+
+        let mut profile = profile();
+        profile.sinks = vec![
+            SinkSettings::Google,
+            SinkSettings::Csv { path: None },
+            SinkSettings::Ics { path: Some("./work.ics".into()) },
+            SinkSettings::Caldav {
+                url: "https://dav.example.com/".to_string(),
+                calendar: Some("work".to_string()),
+                user: None,
+                password: None,
+                password_env: None,
+                state_path: None,
+            },
+            SinkSettings::Outlook {
+                client_id: "client".to_string(),
+                tenant: None,
+                calendar_id: None,
+                token_path: None,
+                state_path: None,
+                base_url: None,
+                authority_url: None,
+            },
+        ];
+
+        let names: Vec<String> = initialize_sinks(&profile).unwrap().iter().map(|sink| sink.name()).collect();
+        assert_eq!(names, vec!["google", "csv (./events.csv)", "ics (./work.ics)", "caldav (https://dav.example.com/)", "outlook"]);
+    }
+
+    #[test]
+    fn test_initialize_rest_clients_不正なurl() {
+        use super::initialize_rest_clients;
+        use crate::error::Error;
+
+        let mut profile = profile();
+        profile.garoon.base_url = Some("example.com".to_string());
+
+        let result = initialize_rest_clients(&profile);
+        assert!(matches!(result, Err(Error::Config(message)) if message.starts_with("garoon.base_url is not a valid url")));
+    }
+
+    #[test]
+    fn test_initialize_garoon_oauth_既定のurl() {
+        use super::initialize_garoon_oauth;
+        use crate::config::profile::GaroonAuthKind;
+
+        let mut profile = profile();
+        profile.garoon.base_url = Some("https://example.cybozu.com/g".to_string());
+        profile.garoon.auth = GaroonAuthKind::OAuth2;
+        profile.garoon.oauth2.client_id = Some("client".to_string());
+        profile.garoon.oauth2.client_secret = Some("secret".to_string());
+        profile.garoon.oauth2.redirect_uri = Some("http://localhost:8080/callback".to_string());
+
+        let auth = initialize_garoon_oauth(&profile).unwrap();
+        let url = auth.authorization_url("state").unwrap();
+        assert!(url.as_str().starts_with("https://example.cybozu.com/oauth2/authorization?client_id=client"));
+    }
+}
@@ -0,0 +1,1077 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use reqwest::Url;
+use serde::Deserialize;
+
+use clap::ValueEnum;
+
+use crate::apis::garoon::{GaroonOrderBy, GaroonSearchField, GaroonTargetType};
+use crate::cli::ExportFormat;
+use crate::error::Error;
+use crate::services::export_events_service::CsvColumn;
+use crate::utils::time_zone::parse_time_zone;
+
+/// 設定ファイルを指定しない場合に読み込むファイル
+pub const DEFAULT_CONFIG_PATH: &str = "./tensha.toml";
+/// Googleカレンダーとの同期状態の既定の保存先
+pub const DEFAULT_STATE_PATH: &str = "./tensha_state.json";
+/// CalDAVとの同期状態の既定の保存先
+pub const DEFAULT_CALDAV_STATE_PATH: &str = "./tensha_caldav_state.json";
+/// Outlookとの同期状態の既定の保存先
+pub const DEFAULT_OUTLOOK_STATE_PATH: &str = "./tensha_outlook_state.json";
+/// GaroonのOAuth 2.0のトークンの既定の保存先
+pub const DEFAULT_TOKEN_PATH: &str = "./tensha_token.json";
+/// Microsoftのトークンの既定の保存先
+pub const DEFAULT_OUTLOOK_TOKEN_PATH: &str = "./tensha_outlook_token.json";
+
+/// `tensha.toml`の内容。取得元のアカウントと転写先の組み合わせをプロファイルとして名前を付けて設定する
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub(crate) default_profile: Option<String>,
+    #[serde(default)]
+    pub(crate) profiles: BTreeMap<String, Profile>,
+}
+impl ConfigFile {
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        toml::from_str(content).map_err(|e| Error::Config(e.to_string()))
+    }
+
+    /// `path`を省略した場合、既定のファイルがなければ環境変数だけで設定する
+    pub fn load(path: Option<&Path>) -> Result<Self, Error> {
+        let required = path.is_some();
+        let path = path.map(Path::to_path_buf).unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| Error::Config(format!("failed to parse {}: {}", path.display(), e))),
+            Err(e) if !required && e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(Error::Config(format!("failed to read {}: {}", path.display(), e))),
+        }
+    }
+
+    /// `name`を省略した場合は`default_profile`を使う。それもなければプロファイルが1つのときだけそれを使う
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, Error> {
+        match name.or(self.default_profile.as_deref()) {
+            Some(name) => self.profiles.get(name).cloned().ok_or_else(|| {
+                let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                Error::Config(format!("profile `{}` is not defined (available: {})", name, names.join(", ")))
+            }),
+            None if self.profiles.len() <= 1 => Ok(self.profiles.values().next().cloned().unwrap_or_default()),
+            None => Err(Error::Config(
+                "multiple profiles are defined; choose one with --profile or set default_profile".to_string(),
+            )),
+        }
+    }
+
+    /// 同期状態やトークンのファイルを複数のプロファイルで共有していないか確認する。
+    /// 同期状態を共有すると、別のプロファイルが同期した予定を削除された予定とみなしてしまう。
+    /// 環境変数で指定した保存先は、`profile`と同じ規則で`name`から選ぶプロファイルにだけ適用する
+    pub fn check_shared_files(&self, name: Option<&str>, var: impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+        let selected = match name.or(self.default_profile.as_deref()) {
+            Some(name) => Some(name),
+            None if self.profiles.len() == 1 => self.profiles.keys().next().map(String::as_str),
+            None => None,
+        };
+        let mut owners: BTreeMap<PathBuf, &str> = BTreeMap::new();
+        let mut problems = vec![];
+        for (name, profile) in &self.profiles {
+            let mut profile = profile.clone();
+            if selected == Some(name.as_str()) {
+                override_with(&mut profile.sync.state_path, var("TENSHA_STATE_PATH").map(PathBuf::from));
+            }
+            // プロファイル内での重複は`validate`で確認する
+            for path in profile.files().into_iter().collect::<BTreeSet<PathBuf>>() {
+                match owners.get(&path) {
+                    Some(owner) => problems.push(format!(
+                        "profiles {} and {} cannot share {}; set a separate state_path or token_path for each profile",
+                        owner,
+                        name,
+                        path.display(),
+                    )),
+                    None => {
+                        owners.insert(path, name);
+                    }
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(Error::Config(problems.join("\n")))
+    }
+}
+
+/// 1つの同期の設定。省略した項目は環境変数で補える
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub(crate) garoon: GaroonSettings,
+    /// Garoon以外からも取得する場合に指定する。省略時はGaroonだけから取得する
+    pub(crate) sources: Vec<SourceSettings>,
+    pub(crate) fetch: FetchSettings,
+    pub(crate) filters: FilterSettings,
+    pub(crate) output: OutputSettings,
+    pub(crate) sink: SinkKind,
+    /// 複数の転写先に反映する場合に指定する。省略時は`sink`だけに反映する
+    pub(crate) sinks: Vec<SinkSettings>,
+    pub(crate) google: GoogleSettings,
+    pub(crate) sync: SyncSettings,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GaroonSettings {
+    pub(crate) base_url: Option<String>,
+    pub(crate) user_id: Option<String>,
+    pub(crate) password: Option<String>,
+    /// パスワードを設定ファイルに書かずに、この名前の環境変数から読む
+    pub(crate) password_env: Option<String>,
+    /// 予定を取得する対象。省略時はログインユーザー
+    pub(crate) target: Option<GaroonTargetSettings>,
+    pub(crate) auth: GaroonAuthKind,
+    pub(crate) oauth2: GaroonOAuth2Settings,
+    pub(crate) session: GaroonSessionSettings,
+    pub(crate) connection: GaroonConnectionSettings,
+    pub(crate) retry: GaroonRetrySettings,
+}
+
+/// ユーザー・組織・施設のいずれかを、IDかコード(ユーザーならログイン名)で指定する
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GaroonTargetSettings {
+    #[serde(rename = "type")]
+    pub(crate) target_type: GaroonTargetType,
+    pub(crate) id: Option<String>,
+    pub(crate) code: Option<String>,
+}
+
+/// Garoonの認証方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GaroonAuthKind {
+    /// `X-Cybozu-Authorization`ヘッダーによるパスワード認証
+    #[default]
+    Password,
+    /// OAuth 2.0の認可コードフロー
+    #[serde(rename = "oauth2")]
+    OAuth2,
+    /// ログインして得たセッションCookie
+    Session,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GaroonOAuth2Settings {
+    pub(crate) client_id: Option<String>,
+    pub(crate) client_secret: Option<String>,
+    /// クライアントシークレットを設定ファイルに書かずに、この名前の環境変数から読む
+    pub(crate) client_secret_env: Option<String>,
+    pub(crate) redirect_uri: Option<String>,
+    /// 省略時は`g:Schedule:read`
+    pub(crate) scope: Option<String>,
+    /// 省略時は`base_url`と同じホストの`/oauth2/authorization`
+    pub(crate) authorization_url: Option<String>,
+    /// 省略時は`base_url`と同じホストの`/oauth2/token`
+    pub(crate) token_url: Option<String>,
+    /// 取得したトークンの保存先。省略時は`./tensha_token.json`
+    pub(crate) token_path: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GaroonSessionSettings {
+    /// 省略時は`base_url`と同じホストの`/api/auth/login.json`
+    pub(crate) login_url: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FetchSettings {
+    /// 取得する日数
+    pub(crate) days: Option<u32>,
+    /// Garoon側で件名や本文を検索する文字列
+    pub(crate) keyword: Option<String>,
+    /// `keyword`で検索しない項目 (subject, company, notes, comments)
+    pub(crate) exclude_from_search: Vec<GaroonSearchField>,
+    /// 取得する項目。省略時は全ての項目
+    pub(crate) fields: Vec<String>,
+    /// 並び順 (`start asc`, `updatedAt desc`など)
+    pub(crate) order_by: Option<String>,
+    /// 非公開の予定を取得するか
+    pub(crate) show_private: Option<bool>,
+    /// 取得する予定の最大件数
+    pub(crate) limit: Option<usize>,
+    /// 指定すると、この日数ごとに期間を分けて並行して取得する
+    pub(crate) chunk_days: Option<u32>,
+    /// 期間を分けた場合に、同時に問い合わせる数の上限。省略時は4
+    pub(crate) concurrency: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilterSettings {
+    /// 件名にいずれかを含む予定は転写しない
+    pub(crate) exclude_subjects: Vec<String>,
+    /// 非公開の予定を転写しない
+    pub(crate) exclude_private: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSettings {
+    pub(crate) format: Option<ExportFormat>,
+    pub(crate) path: Option<PathBuf>,
+    pub(crate) time_zone: Option<String>,
+    /// CSVに件名と開始日に続けて出力する列。省略時は全ての列
+    pub(crate) csv_columns: Option<Vec<CsvColumn>>,
+}
+
+/// Garoonの手前のBasic認証やSecure Accessなど、接続経路の設定
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GaroonConnectionSettings {
+    pub(crate) basic_auth_user: Option<String>,
+    pub(crate) basic_auth_password: Option<String>,
+    /// Basic認証のパスワードを設定ファイルに書かずに、この名前の環境変数から読む
+    pub(crate) basic_auth_password_env: Option<String>,
+    /// クライアント証明書。`client_key`を指定した場合はPEM、しなければPKCS#12として読む
+    pub(crate) client_certificate: Option<PathBuf>,
+    /// PKCS#12ファイルのパスワード
+    pub(crate) client_certificate_password: Option<String>,
+    pub(crate) client_certificate_password_env: Option<String>,
+    /// PKCS#8形式の秘密鍵 (PEM)
+    pub(crate) client_key: Option<PathBuf>,
+    /// 追加で信頼するCA証明書 (PEM)
+    pub(crate) ca_bundle: Option<PathBuf>,
+    pub(crate) proxy: Option<String>,
+    /// 1回のリクエストの制限時間 (秒)
+    pub(crate) timeout_secs: Option<u64>,
+}
+
+/// 一時的なエラー (429, 5xx, タイムアウト) の再試行
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GaroonRetrySettings {
+    /// 最初のリクエストを含む試行回数。1なら再試行しない
+    pub(crate) max_attempts: Option<u32>,
+    pub(crate) base_delay_ms: Option<u64>,
+    pub(crate) max_delay_ms: Option<u64>,
+}
+
+/// `sources`に並べる取得元。`type`で種類を指定する
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SourceSettings {
+    /// `garoon`の設定で取得する
+    Garoon,
+    /// iCalendarファイル。タイムゾーンの指定がない日時は`output.time_zone`で解釈する
+    Ics { path: PathBuf },
+    /// CalDAVのカレンダーコレクション
+    Caldav {
+        url: String,
+        user: Option<String>,
+        password: Option<String>,
+        /// パスワードを設定ファイルに書かずに、この名前の環境変数から読む
+        password_env: Option<String>,
+    },
+    /// Garoonの予定取得APIのレスポンスを保存したファイル
+    Json { path: PathBuf },
+}
+
+/// 転写先の種類
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkKind {
+    #[default]
+    Google,
+}
+
+/// `sinks`に並べる転写先。`type`で種類を指定する
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SinkSettings {
+    /// `google`の設定で同期する
+    Google,
+    /// 省略時は`./events.csv`。列とタイムゾーンは`output`の設定に従う
+    Csv { path: Option<PathBuf> },
+    /// 省略時は`./events.ics`
+    Ics { path: Option<PathBuf> },
+    /// CalDAVのカレンダー。`url`がカレンダーでなければ、カレンダーホームから`calendar`を探す
+    Caldav {
+        url: String,
+        /// カレンダーのパスの末尾か表示名。省略時は最初のカレンダー
+        calendar: Option<String>,
+        user: Option<String>,
+        password: Option<String>,
+        /// パスワードを設定ファイルに書かずに、この名前の環境変数から読む
+        password_env: Option<String>,
+        /// 省略時は`./tensha_caldav_state.json`
+        state_path: Option<PathBuf>,
+    },
+    /// Microsoft Graph経由のOutlookカレンダー。`tensha auth --outlook`でデバイスコードを使って認可する
+    Outlook {
+        /// Microsoft Entra IDに登録した、パブリッククライアントのアプリケーションID
+        client_id: String,
+        /// 省略時は`organizations`(職場・学校アカウント)
+        tenant: Option<String>,
+        /// 省略時は既定のカレンダー
+        calendar_id: Option<String>,
+        /// 省略時は`./tensha_outlook_token.json`
+        token_path: Option<PathBuf>,
+        /// 省略時は`./tensha_outlook_state.json`
+        state_path: Option<PathBuf>,
+        /// 省略時は`https://graph.microsoft.com/v1.0`
+        base_url: Option<String>,
+        /// 省略時は`https://login.microsoftonline.com/{tenant}/oauth2/v2.0`
+        authority_url: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GoogleSettings {
+    pub(crate) base_url: Option<String>,
+    pub(crate) calendar_id: Option<String>,
+    pub(crate) access_token: Option<String>,
+    /// アクセストークンを設定ファイルに書かずに、この名前の環境変数から読む
+    pub(crate) access_token_env: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncSettings {
+    pub(crate) state_path: Option<PathBuf>,
+    pub(crate) max_delete_ratio: Option<f64>,
+    pub(crate) min_delete_count: Option<usize>,
+}
+
+impl Profile {
+    /// 環境変数で個別の項目を上書きし、`*_env`で指定された秘密情報を読み込む
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+        override_with(&mut self.garoon.base_url, var("GAROON_BASE_URL"));
+        override_with(&mut self.garoon.user_id, var("GAROON_USER_ID"));
+        self.apply_target_env(&var)?;
+        if let Some(auth) = parse_auth_kind(var("GAROON_AUTH"))? {
+            self.garoon.auth = auth;
+        }
+        override_with(&mut self.garoon.oauth2.client_id, var("GAROON_OAUTH_CLIENT_ID"));
+        override_with(&mut self.garoon.connection.basic_auth_user, var("GAROON_BASIC_AUTH_USER"));
+        override_with(&mut self.garoon.connection.client_certificate, var("GAROON_CLIENT_CERTIFICATE").map(PathBuf::from));
+        override_with(&mut self.garoon.connection.proxy, var("GAROON_PROXY"));
+        override_with(&mut self.garoon.retry.max_attempts, parse_var(&var, "GAROON_RETRY_MAX_ATTEMPTS")?);
+        override_with(&mut self.fetch.days, parse_var(&var, "TENSHA_FETCH_DAYS")?);
+        override_with(&mut self.fetch.chunk_days, parse_var(&var, "TENSHA_FETCH_CHUNK_DAYS")?);
+        override_with(&mut self.fetch.concurrency, parse_var(&var, "TENSHA_FETCH_CONCURRENCY")?);
+        override_with(&mut self.output.path, var("TENSHA_OUTPUT_PATH").map(PathBuf::from));
+        override_with(&mut self.output.time_zone, var("TENSHA_OUTPUT_TIME_ZONE"));
+        override_with(&mut self.google.base_url, var("GOOGLE_CALENDAR_BASE_URL"));
+        override_with(&mut self.google.calendar_id, var("GOOGLE_CALENDAR_ID"));
+        override_with(&mut self.sync.state_path, var("TENSHA_STATE_PATH").map(PathBuf::from));
+        override_with(&mut self.sync.max_delete_ratio, parse_var(&var, "TENSHA_MAX_DELETE_RATIO")?);
+        override_with(&mut self.sync.min_delete_count, parse_var(&var, "TENSHA_MIN_DELETE_COUNT")?);
+
+        self.garoon.password = match (var("GAROON_PASSWORD"), &self.garoon.password_env) {
+            (Some(password), _) => Some(password),
+            (None, Some(name)) => Some(var(name).ok_or_else(|| not_set(name, "garoon.password_env"))?),
+            (None, None) => self.garoon.password.take(),
+        };
+        self.garoon.oauth2.client_secret = match (var("GAROON_OAUTH_CLIENT_SECRET"), &self.garoon.oauth2.client_secret_env) {
+            (Some(client_secret), _) => Some(client_secret),
+            (None, Some(name)) => Some(var(name).ok_or_else(|| not_set(name, "garoon.oauth2.client_secret_env"))?),
+            (None, None) => self.garoon.oauth2.client_secret.take(),
+        };
+        let connection = &mut self.garoon.connection;
+        connection.basic_auth_password = match (var("GAROON_BASIC_AUTH_PASSWORD"), &connection.basic_auth_password_env) {
+            (Some(password), _) => Some(password),
+            (None, Some(name)) => Some(var(name).ok_or_else(|| not_set(name, "garoon.connection.basic_auth_password_env"))?),
+            (None, None) => connection.basic_auth_password.take(),
+        };
+        connection.client_certificate_password = match (var("GAROON_CLIENT_CERTIFICATE_PASSWORD"), &connection.client_certificate_password_env) {
+            (Some(password), _) => Some(password),
+            (None, Some(name)) => Some(var(name).ok_or_else(|| not_set(name, "garoon.connection.client_certificate_password_env"))?),
+            (None, None) => connection.client_certificate_password.take(),
+        };
+        self.google.access_token = match (var("GOOGLE_ACCESS_TOKEN"), &self.google.access_token_env) {
+            (Some(access_token), _) => Some(access_token),
+            (None, Some(name)) => Some(var(name).ok_or_else(|| not_set(name, "google.access_token_env"))?),
+            (None, None) => self.google.access_token.take(),
+        };
+        for source in &mut self.sources {
+            if let SourceSettings::Caldav { password: password @ None, password_env: Some(name), .. } = source {
+                *password = Some(var(name).ok_or_else(|| not_set(name, "sources.password_env"))?);
+            }
+        }
+        for sink in &mut self.sinks {
+            if let SinkSettings::Caldav { password: password @ None, password_env: Some(name), .. } = sink {
+                *password = Some(var(name).ok_or_else(|| not_set(name, "sinks.password_env"))?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `GAROON_TARGET_ID`か`GAROON_TARGET_CODE`を設定した場合は、設定ファイルの対象を置き換える
+    fn apply_target_env(&mut self, var: &impl Fn(&str) -> Option<String>) -> Result<(), Error> {
+        let target_type = var("GAROON_TARGET_TYPE")
+            .map(|value| {
+                GaroonTargetType::from_str(&value, false)
+                    .map_err(|_| Error::Config(format!("GAROON_TARGET_TYPE must be one of user, organization, facility, got {}", value)))
+            })
+            .transpose()?;
+        let (id, code) = (var("GAROON_TARGET_ID"), var("GAROON_TARGET_CODE"));
+
+        if id.is_some() || code.is_some() {
+            self.garoon.target = Some(GaroonTargetSettings { target_type: target_type.unwrap_or_default(), id, code });
+        } else if let (Some(target), Some(target_type)) = (&mut self.garoon.target, target_type) {
+            target.target_type = target_type;
+        }
+
+        Ok(())
+    }
+
+    pub fn fetch_days(&self) -> Option<u32> {
+        self.fetch.days
+    }
+
+    /// 予定を取得する取得元
+    pub fn sources(&self) -> Vec<SourceSettings> {
+        if self.sources.is_empty() {
+            return vec![SourceSettings::Garoon];
+        }
+
+        self.sources.clone()
+    }
+
+    /// `sync`で反映する転写先
+    pub fn sinks(&self) -> Vec<SinkSettings> {
+        if !self.sinks.is_empty() {
+            return self.sinks.clone();
+        }
+
+        match self.sink {
+            SinkKind::Google => vec![SinkSettings::Google],
+        }
+    }
+
+    /// 転写先ごとの同期状態の保存先。同期状態を持たない転写先は`None`
+    pub fn state_path(&self, sink: &SinkSettings) -> Option<PathBuf> {
+        match sink {
+            SinkSettings::Google => Some(self.sync.state_path.clone().unwrap_or(PathBuf::from(DEFAULT_STATE_PATH))),
+            SinkSettings::Caldav { state_path, .. } => Some(state_path.clone().unwrap_or(PathBuf::from(DEFAULT_CALDAV_STATE_PATH))),
+            SinkSettings::Outlook { state_path, .. } => Some(state_path.clone().unwrap_or(PathBuf::from(DEFAULT_OUTLOOK_STATE_PATH))),
+            SinkSettings::Csv { .. } | SinkSettings::Ics { .. } => None,
+        }
+    }
+
+    /// GaroonのOAuth 2.0のトークンの保存先
+    pub fn token_path(&self) -> PathBuf {
+        self.garoon.oauth2.token_path.clone().unwrap_or(PathBuf::from(DEFAULT_TOKEN_PATH))
+    }
+
+    /// 同期状態とトークンなど、このプロファイルが書き込むファイル
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = self.sinks().iter().filter_map(|sink| self.state_path(sink)).collect();
+        if self.sources().contains(&SourceSettings::Garoon) && self.garoon.auth == GaroonAuthKind::OAuth2 {
+            files.push(self.token_path());
+        }
+        for sink in &self.sinks {
+            if let SinkSettings::Outlook { token_path, .. } = sink {
+                files.push(token_path.clone().unwrap_or(PathBuf::from(DEFAULT_OUTLOOK_TOKEN_PATH)));
+            }
+        }
+
+        files
+    }
+
+    /// 転写先も含めた全ての項目を確認し、問題をまとめて返す。`sync`と`plan`で使う
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut problems = self.source_problems();
+        problems.extend(self.sink_problems());
+
+        report(problems)
+    }
+
+    /// 取得と出力に関する項目だけを確認する。転写先に反映しない`fetch`・`export`・`serve`で使う
+    pub fn validate_sources(&self) -> Result<(), Error> {
+        report(self.source_problems())
+    }
+
+    fn source_problems(&self) -> Vec<Error> {
+        let mut problems = vec![];
+
+        if self.sources().contains(&SourceSettings::Garoon) {
+            problems.extend(self.garoon_problems());
+        }
+        for source in &self.sources {
+            if let SourceSettings::Caldav { url, user, password, .. } = source {
+                problems.extend(check_url(url, "sources.url").err());
+                if user.is_some() && password.is_none() {
+                    problems.push(Error::Config(format!("sources.password is required for {} (or set sources.password_env)", url)));
+                }
+            }
+        }
+        if self.fetch.days == Some(0) {
+            problems.push(Error::Config("fetch.days must be greater than 0".to_string()));
+        }
+        if let Some(order_by) = &self.fetch.order_by {
+            problems.extend(order_by.parse::<GaroonOrderBy>().err().map(|e| Error::Config(format!("fetch.order_by: {}", e))));
+        }
+        if self.fetch.limit == Some(0) {
+            problems.push(Error::Config("fetch.limit must be greater than 0".to_string()));
+        }
+        if self.fetch.chunk_days == Some(0) {
+            problems.push(Error::Config("fetch.chunk_days must be greater than 0".to_string()));
+        }
+        if self.fetch.concurrency == Some(0) {
+            problems.push(Error::Config("fetch.concurrency must be greater than 0".to_string()));
+        }
+        if let Some(time_zone) = &self.output.time_zone {
+            problems.extend(parse_time_zone(time_zone).err().map(|e| Error::Config(format!("output.time_zone: {}", e))));
+        }
+
+        problems
+    }
+
+    fn sink_problems(&self) -> Vec<Error> {
+        let mut problems = vec![];
+        let google_sinks = self.sinks().iter().filter(|sink| **sink == SinkSettings::Google).count();
+        if google_sinks > 0 {
+            if let Some(base_url) = &self.google.base_url {
+                problems.extend(check_url(base_url, "google.base_url").err());
+            }
+            problems.extend(required(&self.google.calendar_id, "google.calendar_id", "GOOGLE_CALENDAR_ID").err());
+            problems.extend(required(&self.google.access_token, "google.access_token", "GOOGLE_ACCESS_TOKEN").err());
+        }
+        if google_sinks > 1 {
+            // 同期状態を共有するため、同じカレンダーに二重に反映してしまう
+            problems.push(Error::Config("sinks can contain google only once".to_string()));
+        }
+        for sink in &self.sinks {
+            match sink {
+                SinkSettings::Caldav { url, user, password, .. } => {
+                    problems.extend(check_url(url, "sinks.url").err());
+                    if user.is_some() && password.is_none() {
+                        problems.push(Error::Config(format!("sinks.password is required for {} (or set sinks.password_env)", url)));
+                    }
+                }
+                SinkSettings::Outlook { base_url, authority_url, .. } => {
+                    problems.extend(base_url.as_deref().and_then(|url| check_url(url, "sinks.base_url").err()));
+                    problems.extend(authority_url.as_deref().and_then(|url| check_url(url, "sinks.authority_url").err()));
+                }
+                SinkSettings::Google | SinkSettings::Csv { .. } | SinkSettings::Ics { .. } => {}
+            }
+        }
+        // 同期状態やトークンを共有すると、互いの記録を上書きしてしまう
+        let mut files = HashSet::new();
+        for path in self.files() {
+            if !files.insert(path.clone()) && google_sinks <= 1 {
+                problems.push(Error::Config(format!("sinks cannot share the file {}; set a separate state_path or token_path", path.display())));
+            }
+        }
+        if let Some(ratio) = self.sync.max_delete_ratio {
+            if !(0.0..=1.0).contains(&ratio) {
+                problems.push(Error::Config(format!("sync.max_delete_ratio must be between 0 and 1, got {}", ratio)));
+            }
+        }
+
+        problems
+    }
+
+    /// Garoonから取得する場合だけ確認する項目
+    fn garoon_problems(&self) -> Vec<Error> {
+        let mut problems = vec![];
+
+        match required(&self.garoon.base_url, "garoon.base_url", "GAROON_BASE_URL") {
+            Ok(base_url) => problems.extend(check_url(base_url, "garoon.base_url").err()),
+            Err(e) => problems.push(e),
+        }
+        match self.garoon.auth {
+            GaroonAuthKind::Password | GaroonAuthKind::Session => {
+                problems.extend(required(&self.garoon.user_id, "garoon.user_id", "GAROON_USER_ID").err());
+                problems.extend(required(&self.garoon.password, "garoon.password", "GAROON_PASSWORD").err());
+            }
+            GaroonAuthKind::OAuth2 => {
+                let oauth2 = &self.garoon.oauth2;
+                problems.extend(required(&oauth2.client_id, "garoon.oauth2.client_id", "GAROON_OAUTH_CLIENT_ID").err());
+                problems.extend(required(&oauth2.client_secret, "garoon.oauth2.client_secret", "GAROON_OAUTH_CLIENT_SECRET").err());
+                if oauth2.redirect_uri.is_none() {
+                    problems.push(Error::Config("garoon.oauth2.redirect_uri is required".to_string()));
+                }
+            }
+        }
+        if let Some(target) = &self.garoon.target {
+            if target.id.is_some() == target.code.is_some() {
+                problems.push(Error::Config("garoon.target requires exactly one of id and code".to_string()));
+            }
+        }
+        let connection = &self.garoon.connection;
+        if connection.basic_auth_user.is_some() {
+            problems.extend(required(&connection.basic_auth_password, "garoon.connection.basic_auth_password", "GAROON_BASIC_AUTH_PASSWORD").err());
+            if self.garoon.auth == GaroonAuthKind::OAuth2 {
+                // どちらもAuthorizationヘッダーを使うため
+                problems.push(Error::Config("garoon.connection.basic_auth_user cannot be used with garoon.auth = \"oauth2\"".to_string()));
+            }
+        }
+        if connection.client_key.is_some() && connection.client_certificate.is_none() {
+            problems.push(Error::Config("garoon.connection.client_key requires garoon.connection.client_certificate".to_string()));
+        }
+        if let Some(proxy) = &connection.proxy {
+            problems.extend(check_url(proxy, "garoon.connection.proxy").err());
+        }
+        if self.garoon.retry.max_attempts == Some(0) {
+            problems.push(Error::Config("garoon.retry.max_attempts must be greater than 0".to_string()));
+        }
+
+        problems
+    }
+}
+
+/// 必須の項目。未設定の場合は設定ファイルのキーと環境変数の両方を案内する
+pub(crate) fn required<'a>(value: &'a Option<String>, key: &str, env: &str) -> Result<&'a str, Error> {
+    value
+        .as_deref()
+        .ok_or_else(|| Error::Config(format!("{} is required (or set {})", key, env)))
+}
+
+pub(crate) fn check_url(value: &str, key: &str) -> Result<(), Error> {
+    Url::parse(value)
+        .map(|_| ())
+        .map_err(|e| Error::Config(format!("{} is not a valid url: {}", key, e)))
+}
+
+fn report(problems: Vec<Error>) -> Result<(), Error> {
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let messages: Vec<String> = problems.iter().map(|problem| format!("  - {}", problem)).collect();
+    Err(Error::Config(format!("invalid profile:\n{}", messages.join("\n"))))
+}
+
+fn override_with<T>(target: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *target = value;
+    }
+}
+
+fn parse_var<T>(var: &impl Fn(&str) -> Option<String>, name: &str) -> Result<Option<T>, Error>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    var(name)
+        .map(|value| value.parse::<T>().map_err(|e| Error::Config(format!("{}: {}", name, e))))
+        .transpose()
+}
+
+fn parse_auth_kind(value: Option<String>) -> Result<Option<GaroonAuthKind>, Error> {
+    value
+        .map(|value| match value.as_str() {
+            "password" => Ok(GaroonAuthKind::Password),
+            "oauth2" => Ok(GaroonAuthKind::OAuth2),
+            "session" => Ok(GaroonAuthKind::Session),
+            _ => Err(Error::Config(format!("GAROON_AUTH must be one of password, oauth2, session, got {}", value))),
+        })
+        .transpose()
+}
+
+fn not_set(name: &str, key: &str) -> Error {
+    Error::Config(format!("environment variable {} referenced by {} is not set", name, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::cli::ExportFormat;
+    use crate::services::export_events_service::CsvColumn;
+    use crate::apis::garoon::{GaroonSearchField, GaroonTargetType};
+    use crate::config::profile::{ConfigFile, GaroonAuthKind, GaroonTargetSettings, Profile, SinkSettings, SourceSettings};
+    use crate::error::Error;
+
+    const CONFIG: &str = r#"
+default_profile = "work"
+
+[profiles.work.garoon]
+base_url = "https://example.cybozu.com/g"
+user_id = "taro"
+password_env = "TENSHA_WORK_PASSWORD"
+
+[profiles.work.garoon.target]
+type = "facility"
+code = "room-a"
+
+[profiles.work.fetch]
+days = 14
+keyword = "定例"
+exclude_from_search = ["notes"]
+order_by = "start desc"
+
+[profiles.work.filters]
+exclude_subjects = ["昼休み"]
+exclude_private = true
+
+[profiles.work.output]
+format = "csv"
+path = "./work.csv"
+time_zone = "Asia/Tokyo"
+csv_columns = ["location", "private"]
+
+[profiles.work.google]
+calendar_id = "work@group.calendar.google.com"
+access_token = "token"
+
+[profiles.private.garoon]
+base_url = "https://example.cybozu.com/g"
+user_id = "hanako"
+password = "secret"
+
+[profiles.private.google]
+calendar_id = "primary"
+access_token = "token"
+
+[[profiles.private.sources]]
+type = "garoon"
+
+[[profiles.private.sources]]
+type = "ics"
+path = "./holidays.ics"
+
+[[profiles.private.sinks]]
+type = "google"
+
+[[profiles.private.sinks]]
+type = "ics"
+path = "./private.ics"
+"#;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn test_profile_既定のプロファイル() {
+        let config = ConfigFile::parse(CONFIG).unwrap();
+
+        let mut profile = config.profile(None).unwrap();
+        profile.apply_env(env(&[("TENSHA_WORK_PASSWORD", "password")])).unwrap();
+
+        assert_eq!(profile.garoon.user_id, Some("taro".to_string()));
+        assert_eq!(profile.garoon.password, Some("password".to_string()));
+        assert_eq!(profile.garoon.target, Some(GaroonTargetSettings {
+            target_type: GaroonTargetType::Facility,
+            id: None,
+            code: Some("room-a".to_string()),
+        }));
+        assert_eq!(profile.fetch.days, Some(14));
+        assert_eq!(profile.fetch.exclude_from_search, vec![GaroonSearchField::Notes]);
+        assert_eq!(profile.filters.exclude_subjects, vec!["昼休み".to_string()]);
+        assert_eq!(profile.output.format, Some(ExportFormat::Csv));
+        assert_eq!(profile.output.csv_columns, Some(vec![CsvColumn::Location, CsvColumn::Private]));
+        assert!(profile.validate().is_ok());
+    }
+
+    #[test]
+    fn test_profile_名前を指定() {
+        let config = ConfigFile::parse(CONFIG).unwrap();
+
+        let profile = config.profile(Some("private")).unwrap();
+
+        assert_eq!(profile.garoon.user_id, Some("hanako".to_string()));
+        assert!(config.profile(Some("unknown")).is_err());
+    }
+
+    #[test]
+    fn test_sources_複数の取得元() {
+        let config = ConfigFile::parse(CONFIG).unwrap();
+
+        let private = config.profile(Some("private")).unwrap();
+        let work = config.profile(Some("work")).unwrap();
+
+        assert_eq!(private.sources(), vec![SourceSettings::Garoon, SourceSettings::Ics { path: "./holidays.ics".into() }]);
+        // 省略時はGaroonだけ
+        assert_eq!(work.sources(), vec![SourceSettings::Garoon]);
+    }
+
+    #[test]
+    fn test_validate_garoonから取得しない場合はgaroonの設定は不要() {
+        let config = ConfigFile::parse(r#"
+[[profiles.team.sources]]
+type = "caldav"
+url = "https://dav.example.com/calendars/team/"
+user = "team"
+password_env = "TEAM_CALDAV_PASSWORD"
+
+[[profiles.team.sinks]]
+type = "ics"
+path = "./team.ics"
+"#).unwrap();
+        let mut profile = config.profile(Some("team")).unwrap();
+        assert!(profile.validate().is_err());
+
+        profile.apply_env(env(&[("TEAM_CALDAV_PASSWORD", "secret")])).unwrap();
+
+        assert!(profile.validate().is_ok(), "{:?}", profile.validate());
+        assert!(matches!(&profile.sources[0], SourceSettings::Caldav { password: Some(password), .. } if password == "secret"));
+    }
+
+    #[test]
+    fn test_sinks_複数の転写先() {
+        let config = ConfigFile::parse(CONFIG).unwrap();
+
+        let private = config.profile(Some("private")).unwrap();
+        let work = config.profile(Some("work")).unwrap();
+
+        assert_eq!(private.sinks(), vec![SinkSettings::Google, SinkSettings::Ics { path: Some("./private.ics".into()) }]);
+        // 省略時は`sink`の転写先だけ
+        assert_eq!(work.sinks(), vec![SinkSettings::Google]);
+        assert!(ConfigFile::parse("[[profiles.work.sinks]]\ntype = \"fax\"").is_err());
+    }
+
+    #[test]
+    fn test_validate_ファイルだけに出力する場合はgoogleの設定は不要() {
+        let mut profile = ConfigFile::parse(CONFIG).unwrap().profile(Some("private")).unwrap();
+        profile.google = Default::default();
+        assert!(profile.validate().is_err());
+
+        profile.sinks = vec![SinkSettings::Csv { path: None }];
+        assert!(profile.validate().is_ok());
+
+        let mut profile = ConfigFile::parse(CONFIG).unwrap().profile(Some("private")).unwrap();
+        profile.sinks = vec![SinkSettings::Google, SinkSettings::Google];
+        assert!(matches!(profile.validate(), Err(Error::Config(message)) if message.contains("google only once")));
+    }
+
+    #[test]
+    fn test_validate_caldavの転写先() {
+        let config = ConfigFile::parse(r#"
+[profiles.team.google]
+calendar_id = "team@group.calendar.google.com"
+access_token = "token"
+
+[[profiles.team.sources]]
+type = "ics"
+path = "./team.ics"
+
+[[profiles.team.sinks]]
+type = "google"
+
+[[profiles.team.sinks]]
+type = "caldav"
+url = "https://dav.example.com/"
+calendar = "team"
+user = "team"
+password_env = "TEAM_CALDAV_PASSWORD"
+
+[[profiles.team.sinks]]
+type = "caldav"
+url = "https://dav.example.com/calendars/team/shared/"
+state_path = "./tensha_caldav_state.json"
+"#).unwrap();
+        let mut profile = config.profile(Some("team")).unwrap();
+        profile.apply_env(env(&[("TEAM_CALDAV_PASSWORD", "secret")])).unwrap();
+        assert!(matches!(&profile.sinks[1], SinkSettings::Caldav { password: Some(password), .. } if password == "secret"));
+
+        // 省略時の保存先と重なる
+        let result = profile.validate();
+        assert!(matches!(result, Err(Error::Config(message)) if message.contains("share the file ./tensha_caldav_state.json")));
+
+        if let SinkSettings::Caldav { state_path, .. } = &mut profile.sinks[2] {
+            *state_path = Some("./shared_state.json".into());
+        }
+        assert!(profile.validate().is_ok(), "{:?}", profile.validate());
+    }
+
+    #[test]
+    fn test_validate_outlookの転写先() {
+        let config = ConfigFile::parse(r#"
+[[profiles.partner.sources]]
+type = "ics"
+path = "./partner.ics"
+
+[[profiles.partner.sinks]]
+type = "outlook"
+client_id = "00000000-0000-0000-0000-000000000000"
+calendar_id = "AAMkAGI2"
+authority_url = "login.microsoftonline.com/contoso"
+"#).unwrap();
+        let mut profile = config.profile(Some("partner")).unwrap();
+        assert!(matches!(profile.validate(), Err(Error::Config(message)) if message.contains("sinks.authority_url")));
+
+        if let SinkSettings::Outlook { authority_url, .. } = &mut profile.sinks[0] {
+            *authority_url = None;
+        }
+        assert!(profile.validate().is_ok(), "{:?}", profile.validate());
+        assert_eq!(profile.state_path(&profile.sinks[0]), Some("./tensha_outlook_state.json".into()));
+
+        // 同期状態を分けても、既定のトークンの保存先が重なる
+        let mut second = profile.sinks[0].clone();
+        if let SinkSettings::Outlook { state_path, .. } = &mut second {
+            *state_path = Some("./second_state.json".into());
+        }
+        profile.sinks.push(second);
+        let result = profile.validate();
+        assert!(matches!(result, Err(Error::Config(message)) if message.contains("share the file ./tensha_outlook_token.json")));
+    }
+
+    #[test]
+    fn test_check_shared_files() {
+        let config = ConfigFile::parse(r#"
+[profiles.a.garoon]
+auth = "oauth2"
+
+[profiles.b.garoon]
+auth = "oauth2"
+"#).unwrap();
+
+        // 既定の同期状態とトークンのファイルを共有する
+        let message = config.check_shared_files(Some("a"), env(&[])).unwrap_err().to_string();
+        assert!(message.contains("profiles a and b cannot share ./tensha_state.json"), "{}", message);
+        assert!(message.contains("profiles a and b cannot share ./tensha_token.json"), "{}", message);
+
+        let config = ConfigFile::parse(r#"
+[profiles.a.sync]
+state_path = "./a_state.json"
+
+[profiles.b.sync]
+state_path = "./b_state.json"
+"#).unwrap();
+        assert!(config.check_shared_files(Some("a"), env(&[])).is_ok());
+        // 環境変数で指定した保存先は選んだプロファイルにだけ適用される
+        assert!(config.check_shared_files(Some("a"), env(&[("TENSHA_STATE_PATH", "./state.json")])).is_ok());
+        assert!(config.check_shared_files(Some("a"), env(&[("TENSHA_STATE_PATH", "./b_state.json")])).is_err());
+    }
+
+    #[test]
+    fn test_apply_env_環境変数で上書き() {
+        let mut profile = ConfigFile::parse(CONFIG).unwrap().profile(Some("private")).unwrap();
+
+        profile.apply_env(env(&[("GAROON_USER_ID", "jiro"), ("TENSHA_MAX_DELETE_RATIO", "0.2")])).unwrap();
+
+        assert_eq!(profile.garoon.user_id, Some("jiro".to_string()));
+        assert_eq!(profile.garoon.password, Some("secret".to_string()));
+        assert_eq!(profile.sync.max_delete_ratio, Some(0.2));
+    }
+
+    #[test]
+    fn test_apply_env_不正な値() {
+        let mut profile = Profile::default();
+
+        let result = profile.apply_env(env(&[("TENSHA_MIN_DELETE_COUNT", "many")]));
+
+        assert!(matches!(result, Err(Error::Config(message)) if message.starts_with("TENSHA_MIN_DELETE_COUNT")));
+    }
+
+    #[test]
+    fn test_apply_env_参照先の環境変数がない() {
+        let mut profile = ConfigFile::parse(CONFIG).unwrap().profile(Some("work")).unwrap();
+
+        let result = profile.apply_env(env(&[]));
+
+        assert!(matches!(result, Err(Error::Config(message)) if message.contains("TENSHA_WORK_PASSWORD")));
+    }
+
+    #[test]
+    fn test_validate_問題をまとめて報告する() {
+        let mut profile = Profile::default();
+        profile.google.base_url = Some("not a url".to_string());
+        profile.sync.max_delete_ratio = Some(1.5);
+        profile.fetch.order_by = Some("subject".to_string());
+
+        let message = profile.validate().unwrap_err().to_string();
+
+        assert!(message.contains("garoon.base_url is required (or set GAROON_BASE_URL)"));
+        assert!(message.contains("garoon.user_id is required"));
+        assert!(message.contains("google.base_url is not a valid url"));
+        assert!(message.contains("sync.max_delete_ratio must be between 0 and 1"));
+        assert!(message.contains("fetch.order_by: order must start with start, createdAt or updatedAt"));
+    }
+
+    #[test]
+    fn test_validate_sources_転写先の設定は確認しない() {
+        let mut profile = Profile::default();
+        profile.garoon.base_url = Some("https://example.cybozu.com/g".to_string());
+        profile.garoon.user_id = Some("taro".to_string());
+        profile.garoon.password = Some("password".to_string());
+
+        // 既定の転写先はGoogleカレンダーだが、取得と出力だけなら認証情報は不要
+        assert!(profile.validate_sources().is_ok());
+        assert!(profile.validate().unwrap_err().to_string().contains("google.calendar_id is required"));
+    }
+
+    #[test]
+    fn test_parse_未知のキー() {
+        let result = ConfigFile::parse("[profiles.work.garoon]\nbase_ur = \"https://example.com\"\n");
+
+        assert!(matches!(result, Err(Error::Config(message)) if message.contains("base_ur")));
+    }
+
+    #[test]
+    fn test_validate_oauth2ではパスワード不要() {
+        let config = ConfigFile::parse(r#"
+[profiles.work.garoon]
+base_url = "https://example.cybozu.com/g"
+auth = "oauth2"
+
+[profiles.work.garoon.oauth2]
+client_id = "client"
+client_secret_env = "TENSHA_OAUTH_SECRET"
+redirect_uri = "http://localhost:8080/callback"
+
+[profiles.work.google]
+calendar_id = "primary"
+access_token = "token"
+"#).unwrap();
+
+        let mut profile = config.profile(None).unwrap();
+        profile.apply_env(env(&[("TENSHA_OAUTH_SECRET", "secret")])).unwrap();
+
+        assert_eq!(profile.garoon.auth, GaroonAuthKind::OAuth2);
+        assert_eq!(profile.garoon.oauth2.client_secret, Some("secret".to_string()));
+        assert!(profile.validate().is_ok());
+
+        profile.garoon.oauth2.client_id = None;
+        assert!(profile.validate().unwrap_err().to_string().contains("garoon.oauth2.client_id is required"));
+    }
+
+    #[test]
+    fn test_validate_接続設定() {
+        let mut profile = ConfigFile::parse(r#"
+[profiles.work.garoon]
+base_url = "https://example.cybozu.com/g"
+user_id = "taro"
+password = "password"
+
+[profiles.work.garoon.connection]
+basic_auth_user = "proxy-user"
+client_key = "./client.key"
+proxy = "proxy.example.com"
+"#).unwrap().profile(None).unwrap();
+
+        profile.apply_env(env(&[])).unwrap();
+        let message = profile.validate().unwrap_err().to_string();
+
+        assert!(message.contains("garoon.connection.basic_auth_password is required (or set GAROON_BASIC_AUTH_PASSWORD)"));
+        assert!(message.contains("garoon.connection.client_key requires garoon.connection.client_certificate"));
+        assert!(message.contains("garoon.connection.proxy is not a valid url"));
+    }
+
+    #[test]
+    fn test_apply_env_対象() {
+        let mut profile = ConfigFile::parse(CONFIG).unwrap().profile(Some("work")).unwrap();
+
+        profile.apply_env(env(&[("TENSHA_WORK_PASSWORD", "password"), ("GAROON_TARGET_ID", "42")])).unwrap();
+
+        assert_eq!(profile.garoon.target, Some(GaroonTargetSettings {
+            target_type: GaroonTargetType::User,
+            id: Some("42".to_string()),
+            code: None,
+        }));
+    }
+}
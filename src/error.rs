@@ -0,0 +1,187 @@
+use std::fmt;
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+/// tenshaの処理で発生するエラー
+#[derive(Debug, Error)]
+pub enum Error {
+    /// 環境変数などの設定が不足している、または不正
+    #[error("invalid configuration: {0}")]
+    Config(String),
+    /// 取得元・転写先の認証に失敗した
+    #[error("authentication failed: {message}")]
+    Auth {
+        message: String,
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+    /// 通信に失敗した
+    #[error("request failed: {0}")]
+    Transport(#[source] reqwest::Error),
+    /// Garoon APIがエラーを返した
+    #[error(transparent)]
+    Garoon(GaroonApiError),
+    /// 取得した予定を解釈できない
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    /// CalDAVサーバーの応答を解釈できない
+    #[error("unexpected CalDAV response: {0}")]
+    CalDav(String),
+    /// Microsoft Graphの応答を解釈できない
+    #[error("unexpected Microsoft Graph response: {0}")]
+    Graph(String),
+    /// 転写先への反映に失敗した
+    #[error("failed to write `{event_id}` to the sink: {source}")]
+    Sink {
+        event_id: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+impl Error {
+    pub fn auth(message: impl Into<String>) -> Self {
+        Error::Auth { message: message.into(), source: None }
+    }
+
+    pub fn sink(event_id: impl Into<String>, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Error::Sink { event_id: event_id.into(), source: source.into() }
+    }
+}
+impl From<reqwest::Error> for Error {
+    fn from(value: reqwest::Error) -> Self {
+        match value.status() {
+            Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => Error::Auth {
+                message: value.to_string(),
+                source: Some(Box::new(value)),
+            },
+            _ => Error::Transport(value),
+        }
+    }
+}
+
+impl From<GaroonApiError> for Error {
+    fn from(value: GaroonApiError) -> Self {
+        match value.status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Auth {
+                message: value.to_string(),
+                source: Some(Box::new(value)),
+            },
+            _ => Error::Garoon(value),
+        }
+    }
+}
+
+/// Garoon APIのエラーレスポンス。本文がJSONでない場合は、本文をそのまま`message`に入れる
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub struct GaroonApiError {
+    pub status: StatusCode,
+    pub error_code: Option<String>,
+    pub message: Option<String>,
+    pub cause: Option<String>,
+    pub counter_measure: Option<String>,
+}
+impl GaroonApiError {
+    pub fn from_body(status: StatusCode, body: &str) -> Self {
+        #[derive(Default, Deserialize)]
+        #[serde(rename_all = "camelCase", default)]
+        struct Body {
+            error_code: Option<String>,
+            message: Option<String>,
+            cause: Option<String>,
+            counter_measure: Option<String>,
+        }
+
+        let body = serde_json::from_str::<Body>(body).unwrap_or_else(|_| Body {
+            message: Some(body.trim().to_string()).filter(|message| !message.is_empty()),
+            ..Default::default()
+        });
+
+        Self {
+            status,
+            error_code: body.error_code,
+            message: body.message,
+            cause: body.cause,
+            counter_measure: body.counter_measure,
+        }
+    }
+}
+impl fmt::Display for GaroonApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Garoon API returned {}", self.status)?;
+        if let Some(error_code) = &self.error_code {
+            write!(f, " ({})", error_code)?;
+        }
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        if let Some(cause) = &self.cause {
+            write!(f, " cause: {}", cause)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 予定や日時の値を解釈できない
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("invalid date time `{value}`: {source}")]
+    DateTime {
+        value: String,
+        #[source]
+        source: chrono::ParseError,
+    },
+    #[error("unknown time zone: {0}")]
+    TimeZone(String),
+    #[error("start `{start}` must be before end `{end}`")]
+    InvalidRange { start: String, end: String },
+    #[error("invalid repeat rule: {0}")]
+    Recurrence(String),
+    #[error("{0} is missing")]
+    Missing(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::StatusCode;
+
+    use crate::error::{Error, GaroonApiError, ParseError};
+
+    #[test]
+    fn test_parse_error_message() {
+        let error = Error::from(ParseError::TimeZone("Mars/Olympus".to_string()));
+
+        assert_eq!(error.to_string(), "unknown time zone: Mars/Olympus");
+    }
+
+    #[test]
+    fn test_sink_error_message() {
+        let error = Error::sink("1", "Google Calendar returned no event id");
+
+        assert_eq!(error.to_string(), "failed to write `1` to the sink: Google Calendar returned no event id");
+    }
+
+    #[test]
+    fn test_garoon_api_error_本文を解釈する() {
+        let body = r#"{"id":"1","errorCode":"GRN_SCHD_13208","message":"予定が見つかりません。","cause":"削除された可能性があります。","counterMeasure":"再読み込みしてください。"}"#;
+
+        let error = GaroonApiError::from_body(StatusCode::NOT_FOUND, body);
+
+        assert_eq!(error.error_code, Some("GRN_SCHD_13208".to_string()));
+        assert_eq!(error.counter_measure, Some("再読み込みしてください。".to_string()));
+        assert_eq!(
+            Error::from(error).to_string(),
+            "Garoon API returned 404 Not Found (GRN_SCHD_13208): 予定が見つかりません。 cause: 削除された可能性があります。"
+        );
+    }
+
+    #[test]
+    fn test_garoon_api_error_json以外の本文() {
+        let error = GaroonApiError::from_body(StatusCode::UNAUTHORIZED, "Unauthorized\n");
+
+        assert_eq!(error.message, Some("Unauthorized".to_string()));
+        assert!(matches!(Error::from(error), Error::Auth { .. }));
+    }
+}
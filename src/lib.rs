@@ -0,0 +1,12 @@
+pub mod models;
+pub mod apis;
+pub mod repositories;
+pub mod services;
+pub mod sinks;
+pub mod sources;
+pub mod config;
+pub mod output;
+pub mod server;
+pub mod utils;
+pub mod error;
+pub mod cli;
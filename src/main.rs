@@ -1,22 +1,234 @@
-use crate::config::{initialize_rest_clients, initialize_service};
-use crate::services::export_events_service::export;
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use chrono::Utc;
+use chrono_tz::Tz;
+use clap::Parser;
+use tensha::cli::{Cli, Command, RangeArgs};
+use tensha::config::profile::{Profile, SinkSettings, SourceSettings};
+use tensha::config::{initialize_deletion_guard, initialize_file_sink, initialize_garoon_http_client, initialize_garoon_oauth, initialize_microsoft_oauth, initialize_output, initialize_output_time_zone, initialize_rest_clients, initialize_service, initialize_sinks, initialize_sources, initialize_state_repository, load_profile, load_profiles};
+use tensha::models::event::FetchedEvents;
+use tensha::output;
+use tensha::repositories::sync_state_repository::SyncStateRepository;
+use tensha::services::calendar_sync_service::CalendarSyncService;
+use tensha::server::FeedServer;
+use tensha::services::ics_feed_service::{FeedSource, IcsFeed};
+use tensha::sinks::event_sink::{plan_all, write_all};
+use tokio::net::TcpListener;
 
-mod models;
-mod apis;
-mod services;
-mod config;
-mod output;
-mod utils;
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    let garoon_client = initialize_rest_clients().unwrap();
-    let service = initialize_service(garoon_client).unwrap();
-
-    let events = service.get_garoon_events().await?;
-    output::print_results(&events);
-    
-    export(&events, "./events.csv")?;
-    
+    let cli = Cli::parse();
+    // serveは1つのプロファイルに限らないので、プロファイルを読み込む前に分ける
+    if let Command::Serve { bind, token, refresh_minutes, range } = cli.command {
+        let profiles = load_profiles(cli.config.as_deref(), cli.profile.as_deref())?;
+        return serve(profiles, bind, token, Duration::from_secs(refresh_minutes * 60), range).await;
+    }
+    let profile = load_profile(cli.config.as_deref(), cli.profile.as_deref())?;
+
+    match cli.command {
+        Command::Fetch { range } => {
+            let service = build_service(&profile, range).await?;
+            let output_time_zone = initialize_output_time_zone(&profile)?;
+            let fetched = service.get_events().await?;
+            output::print_results(fetched.events(), output_time_zone);
+            output::print_skipped(fetched.skipped());
+        }
+        Command::Export { range, output, format } => {
+            let service = build_service(&profile, range).await?;
+            let (output, format) = initialize_output(&profile, output, format);
+            let sinks = vec![initialize_file_sink(&profile, output, format)?];
+            let fetched = service.get_events().await?;
+            output::print_skipped(fetched.skipped());
+            for outcome in write_all(&sinks, fetched, service.fetch_range()).await {
+                outcome.result?;
+            }
+        }
+        Command::Sync { range, dry_run: false } => {
+            profile.validate()?;
+            let service = build_service(&profile, range).await?.with_sinks(initialize_sinks(&profile)?);
+            let outcomes = service.sync_events().await?;
+            let mut failures = 0;
+            for outcome in outcomes {
+                match outcome.result {
+                    Ok(report) => println!("{}: {}", outcome.name, report),
+                    Err(e) => {
+                        failures += 1;
+                        eprintln!("{}: failed: {}", outcome.name, e);
+                    }
+                }
+            }
+            if failures > 0 {
+                bail!("{} sink(s) failed", failures);
+            }
+        }
+        Command::Plan { range } | Command::Sync { range, dry_run: true } => {
+            profile.validate()?;
+            let service = build_service(&profile, range).await?;
+            let output_time_zone = initialize_output_time_zone(&profile)?;
+            let sinks = initialize_sinks(&profile)?;
+            let deletion_guard = initialize_deletion_guard(&profile)?;
+            let fetched = service.get_events().await?;
+            let plans = plan_all(&sinks, fetched, service.fetch_range());
+            if plans.is_empty() {
+                bail!("this profile has no stateful sink (google, caldav, outlook) to plan");
+            }
+            for sink_plan in plans {
+                let Some(plan) = sink_plan.result? else {
+                    continue;
+                };
+                println!("{}:", sink_plan.name);
+                output::print_plan(&plan, output_time_zone);
+                if let Err(e) = plan.check_deletions(&deletion_guard) {
+                    eprintln!("warning: {}: {}", sink_plan.name, e);
+                }
+            }
+        }
+        Command::Doctor => doctor(&profile).await?,
+        Command::Lookup { target_type, code } => {
+            let target = initialize_rest_clients(&profile)?.find_target(target_type, &code).await?;
+            println!("{}", target.id());
+        }
+        Command::Auth { outlook: true, .. } => authorize_outlook(&profile).await?,
+        Command::Auth { code, .. } => authorize(&profile, code).await?,
+        Command::Serve { .. } => unreachable!("serve is handled before loading a profile"),
+    }
+
+    Ok(())
+}
+
+/// 取得元だけを設定する。転写先に反映する場合は、呼び出し側で転写先の設定も確認する
+async fn build_service(profile: &Profile, range: RangeArgs) -> Result<CalendarSyncService, anyhow::Error> {
+    profile.validate_sources()?;
+
+    let output_time_zone = initialize_output_time_zone(profile)?;
+    let fetch_range = range
+        .with_default_days(profile.fetch_days())
+        .to_range(&output_time_zone.unwrap_or(Tz::UTC), Utc::now())?;
+    let service = initialize_service(profile, initialize_sources(profile).await?)?.with_fetch_range(fetch_range);
+
+    Ok(service)
+}
+
+/// プロファイルごとに、取得するたびに期間を決め直してGaroonから予定を取得する
+struct ProfileFeedSource {
+    profile: Profile,
+    range: RangeArgs,
+}
+#[async_trait]
+impl FeedSource for ProfileFeedSource {
+    async fn fetch(&self) -> Result<FetchedEvents, anyhow::Error> {
+        let fetched = build_service(&self.profile, self.range.clone()).await?.get_events().await?;
+        output::print_skipped(fetched.skipped());
+
+        Ok(fetched)
+    }
+}
+
+async fn serve(profiles: Vec<(String, Profile)>, bind: SocketAddr, token: Option<String>, refresh_interval: Duration, range: RangeArgs) -> Result<(), anyhow::Error> {
+    let Some(token) = token.or_else(|| env::var("TENSHA_SERVE_TOKEN").ok()) else {
+        bail!("serve requires a token: set TENSHA_SERVE_TOKEN or pass --token");
+    };
+    let mut server = FeedServer::new(token)?;
+    for (name, profile) in profiles {
+        profile.validate_sources().map_err(|e| anyhow!("profile `{}`: {}", name, e))?;
+        let source = ProfileFeedSource { profile, range: range.clone() };
+        server = server.with_feed(name, IcsFeed::new(source, refresh_interval));
+    }
+
+    let listener = TcpListener::bind(bind).await?;
+    for path in server.paths() {
+        println!("serving http://{}{}?token=<token>", listener.local_addr()?, path);
+    }
+
+    Arc::new(server).serve(listener).await
+}
+
+/// コードがなければ認可画面のURLを表示し、あればトークンに交換して保存する
+async fn authorize(profile: &Profile, code: Option<String>) -> Result<(), anyhow::Error> {
+    let auth = initialize_garoon_oauth(profile)?;
+    match code {
+        None => {
+            let state = Utc::now().timestamp_millis().to_string();
+            println!("Open the following URL, then run `tensha auth --code <code>` with the code in the redirected URL:");
+            println!("{}", auth.authorization_url(&state)?);
+        }
+        Some(code) => {
+            auth.exchange_code(&initialize_garoon_http_client(profile)?, &code).await?;
+            println!("saved the OAuth token");
+        }
+    }
+
+    Ok(())
+}
+
+/// Outlookの転写先ごとにデバイスコードを表示し、入力されるのを待ってトークンを保存する
+async fn authorize_outlook(profile: &Profile) -> Result<(), anyhow::Error> {
+    let sinks: Vec<SinkSettings> = profile.sinks().into_iter().filter(|sink| matches!(sink, SinkSettings::Outlook { .. })).collect();
+    if sinks.is_empty() {
+        bail!("this profile has no outlook sink");
+    }
+
+    let client = reqwest::Client::new();
+    for sink in sinks {
+        let auth = initialize_microsoft_oauth(&sink)?;
+        let device_code = auth.request_device_code(&client).await?;
+        println!("{}", device_code.message());
+        auth.poll_token(&client, &device_code).await?;
+        println!("saved the Microsoft OAuth token");
+    }
+
+    Ok(())
+}
+
+/// 設定を順に確認し、最後に取得元から1日分の予定を取得してみる
+async fn doctor(profile: &Profile) -> Result<(), anyhow::Error> {
+    let mut failures = 0;
+    let mut report = |name: &str, result: Result<(), String>| match result {
+        Ok(()) => println!("ok  {}", name),
+        Err(e) => {
+            failures += 1;
+            println!("NG  {}: {}", name, e);
+        }
+    };
+
+    report("profile", profile.validate_sources().map_err(|e| e.to_string()));
+    if profile.sources().contains(&SourceSettings::Garoon) {
+        report("Garoon settings", initialize_rest_clients(profile).map(|_| ()).map_err(|e| e.to_string()));
+    }
+    report(
+        "sinks",
+        profile
+            .validate()
+            .map_err(|e| e.to_string())
+            .and_then(|_| initialize_sinks(profile).map(|_| ()).map_err(|e| e.to_string())),
+    );
+    report("deletion guard", initialize_deletion_guard(profile).map(|_| ()).map_err(|e| e.to_string()));
+    report("output time zone", initialize_output_time_zone(profile).map(|_| ()).map_err(|e| e.to_string()));
+    report(
+        "sync state",
+        initialize_state_repository(profile)
+            .map_err(anyhow::Error::from)
+            .and_then(|repository| repository.load())
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    );
+
+    let range = RangeArgs { days: Some(1), ..Default::default() };
+    let connection = match build_service(profile, range).await {
+        Ok(service) => service.get_events().await.map(|_| ()).map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    report("fetch events", connection);
+
+    if failures > 0 {
+        bail!("{} check(s) failed", failures);
+    }
+
     Ok(())
 }
@@ -1,32 +1,149 @@
-use chrono::{DateTime, Utc};
-use crate::apis::garoon::{GaroonEvent};
+use std::collections::HashSet;
+
+use chrono::{DateTime, FixedOffset, NaiveTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::apis::garoon::{GaroonEvent, GaroonEventType, GaroonVisibilityType};
+use crate::error::ParseError;
+use crate::models::recurrence::Recurrence;
+use crate::utils::date_range::DateRange;
 use crate::utils::date_time_range::DateTimeRange;
+use crate::utils::time_zone::parse_time_zone;
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Event {
+    pub(crate) id: EventId,
     pub(crate) title: Title,
-    pub(crate) duration: DateTimeRange,
-    attendees: Vec<Attendee>,
+    pub(crate) time: EventTime,
+    pub(crate) time_zones: EventTimeZones,
+    pub(crate) attendees: Vec<Attendee>,
+    /// 予定のメモ
+    pub(crate) description: Option<String>,
+    /// 予定の場所。Garoonでは施設名
+    pub(crate) location: Option<String>,
+    /// 非公開の予定か
+    pub(crate) private: bool,
+    pub(crate) updated_at: Option<DateTime<Utc>>,
+    /// 繰り返し予定の規則。Garoonから取得した直後は展開された1回分の予定がそれぞれ持つ
+    pub(crate) recurrence: Option<Recurrence>,
 }
 impl Event {
-    pub fn new(title: Title, duration: DateTimeRange, attendees: Vec<Attendee>) -> Self {
+    pub fn new(id: EventId, title: Title, time: impl Into<EventTime>, attendees: Vec<Attendee>) -> Self {
         Self {
+            id,
             title,
-            duration,
+            time: time.into(),
+            time_zones: EventTimeZones::default(),
             attendees,
+            description: None,
+            location: None,
+            private: false,
+            updated_at: None,
+            recurrence: None,
+        }
+    }
+
+    pub fn with_time_zones(mut self, time_zones: EventTimeZones) -> Self {
+        self.time_zones = time_zones;
+        self
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_location(mut self, location: Option<String>) -> Self {
+        self.location = location;
+        self
+    }
+
+    pub fn with_private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    pub fn with_updated_at(mut self, updated_at: Option<DateTime<Utc>>) -> Self {
+        self.updated_at = updated_at;
+        self
+    }
+
+    pub fn with_recurrence(mut self, recurrence: Option<Recurrence>) -> Self {
+        self.recurrence = recurrence;
+        self
+    }
+
+    /// 予定が占める期間。繰り返し予定は初回から繰り返しの最終日の終わりまで
+    pub fn span(&self) -> DateTimeRange {
+        let span = self.time.span(&self.time_zones.start);
+        match &self.recurrence {
+            Some(recurrence) => DateTimeRange::new(span.start, recurrence.end().max(span.end)),
+            None => span,
         }
     }
+
+    /// RFC 5545のRRULE行とEXDATE行。繰り返し予定でなければ空
+    pub fn recurrence_lines(&self) -> Vec<String> {
+        self.recurrence.as_ref().map(|recurrence| recurrence.to_rfc5545(&self.time)).unwrap_or_default()
+    }
+
+    /// 同期済みの内容から変更があったかを判定するためのハッシュ値
+    pub fn content_hash(&self) -> String {
+        let attendees: Vec<&str> = self.attendees.iter().map(|attendee| attendee.display_name.as_str()).collect();
+        let fields = [
+            self.title.as_str(),
+            self.time.to_string(),
+            self.time_zones.to_string(),
+            attendees.join("\u{1f}"),
+            self.description.clone().unwrap_or_default(),
+            self.location.clone().unwrap_or_default(),
+            self.private.to_string(),
+            self.recurrence_lines().join("\n"),
+        ];
+
+        let mut hasher = Sha256::new();
+        for field in fields {
+            hasher.update(field);
+            hasher.update([0]);
+        }
+
+        hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
 }
-impl From<GaroonEvent> for Event {
-    fn from(value: GaroonEvent) -> Self {
+impl TryFrom<GaroonEvent> for Event {
+    type Error = ParseError;
+
+    fn try_from(value: GaroonEvent) -> Result<Self, Self::Error> {
+        let id = EventId::new(value.id);
         let title = Title::new(value.subject);
-        let start = DateTime::parse_from_rfc3339(&value.start.dateTime)
-            .unwrap()
-            .with_timezone(&Utc);
-        let end = DateTime::parse_from_rfc3339(&value.end.dateTime)
-            .unwrap()
-            .with_timezone(&Utc);
-        let duration = DateTimeRange::new(start, end);
+        let start = parse_date_time(&value.start.date_time)?;
+        let end = value
+            .end
+            .as_ref()
+            .map(|end| parse_date_time(&end.date_time))
+            .transpose()?;
+        let time = match end {
+            // 開始時刻のみの予定は終了日時を持たないか、持っていても意味がない
+            None => EventTime::StartOnly { start: start.with_timezone(&Utc) },
+            Some(_) if value.is_start_only => EventTime::StartOnly { start: start.with_timezone(&Utc) },
+            // 終日予定はGaroonのタイムゾーンでの日付として扱う
+            Some(end) if value.is_all_day || value.event_type == GaroonEventType::AllDay => {
+                let start_date = start.date_naive();
+                let mut end_date = end.date_naive();
+                if end.time() == NaiveTime::MIN && end_date > start_date {
+                    end_date = end_date.pred_opt().unwrap();
+                }
+                EventTime::AllDay(DateRange::try_new(start_date, end_date)?)
+            }
+            Some(end) => EventTime::Timed(DateTimeRange::try_new(start.with_timezone(&Utc), end.with_timezone(&Utc))?),
+        };
+        // タイムゾーン名が解釈できない場合にUTCとみなすと終日予定などの日時がずれるので、変換できない予定にする
+        let start_time_zone = parse_time_zone(&value.start.time_zone)?;
+        let end_time_zone = match &value.end {
+            Some(end) => parse_time_zone(&end.time_zone)?,
+            None => start_time_zone,
+        };
         let attendees = value
             .attendees
             .iter()
@@ -35,7 +152,178 @@ impl From<GaroonEvent> for Event {
             })
             .collect();
 
-        Self::new(title, duration, attendees)
+        let description = Some(value.notes).filter(|notes| !notes.is_empty());
+        let facilities: Vec<&str> = value.facilities.iter().map(|facility| facility.name.as_str()).collect();
+        let location = Some(facilities.join(", ")).filter(|location| !location.is_empty());
+        let private = value.visibility_type != GaroonVisibilityType::Public;
+        let recurrence = match (&value.event_type, &value.repeat_info) {
+            (GaroonEventType::Repeating, Some(repeat_info)) => Some(Recurrence::from_garoon(repeat_info, start_time_zone)?),
+            _ => None,
+        };
+
+        Ok(Self::new(id, title, time, attendees)
+            .with_time_zones(EventTimeZones::new(start_time_zone, end_time_zone))
+            .with_description(description)
+            .with_location(location)
+            .with_private(private)
+            .with_updated_at(value.updated_at)
+            .with_recurrence(recurrence))
+    }
+}
+
+fn parse_date_time(value: &str) -> Result<DateTime<FixedOffset>, ParseError> {
+    DateTime::parse_from_rfc3339(value).map_err(|source| ParseError::DateTime { value: value.to_string(), source })
+}
+
+/// 変換できずに読み飛ばした予定
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkippedEvent {
+    pub(crate) id: EventId,
+    pub(crate) error: ParseError,
+}
+
+/// 取得元から取得した予定。変換できなかった予定は読み飛ばして記録する
+#[derive(Clone, Debug, Default)]
+pub struct FetchedEvents {
+    pub(crate) events: Vec<Event>,
+    pub(crate) skipped: Vec<SkippedEvent>,
+}
+impl FetchedEvents {
+    pub fn events(&self) -> &Vec<Event> {
+        &self.events
+    }
+
+    pub fn skipped(&self) -> &Vec<SkippedEvent> {
+        &self.skipped
+    }
+
+    /// 展開された繰り返し予定を、予定IDごとに初回の日時を持つ1件の予定にまとめる
+    pub fn merge_recurring(mut self) -> Self {
+        let mut seen = HashSet::new();
+        self.events.retain(|event| event.recurrence.is_none() || seen.insert(event.id.clone()));
+        for event in &mut self.events {
+            if let Some(recurrence) = &event.recurrence {
+                event.time = recurrence.series_time(&event.time);
+            }
+        }
+
+        self
+    }
+
+    /// 展開された繰り返し予定の回ごとに、予定IDに回の開始日時を付けた別の予定にする。
+    /// 繰り返し予定を扱えない転写先で、回ごとに同期状態を記録するために使う
+    pub fn key_instances(mut self) -> Self {
+        for event in &mut self.events {
+            if event.recurrence.take().is_some() {
+                event.id = EventId::new(format!("{}/{}", event.id.as_str(), event.time.instance_key()));
+            }
+        }
+
+        self
+    }
+}
+impl From<Vec<Event>> for FetchedEvents {
+    fn from(value: Vec<Event>) -> Self {
+        Self { events: value, skipped: vec![] }
+    }
+}
+impl FromIterator<GaroonEvent> for FetchedEvents {
+    fn from_iter<T: IntoIterator<Item = GaroonEvent>>(iter: T) -> Self {
+        let mut fetched = FetchedEvents::default();
+        for garoon_event in iter {
+            let id = EventId::new(garoon_event.id.clone());
+            match Event::try_from(garoon_event) {
+                Ok(event) => fetched.events.push(event),
+                Err(error) => fetched.skipped.push(SkippedEvent { id, error }),
+            }
+        }
+
+        fetched
+    }
+}
+
+/// 予定の日時。時刻のある予定、終日予定、開始時刻のみの予定を区別する
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventTime {
+    Timed(DateTimeRange),
+    AllDay(DateRange),
+    StartOnly { start: DateTime<Utc> },
+}
+impl EventTime {
+    /// 繰り返し予定の回を区別する開始日時。RFC 5545のRECURRENCE-IDと同じ形式
+    pub fn instance_key(&self) -> String {
+        match self {
+            EventTime::Timed(DateTimeRange { start, .. }) | EventTime::StartOnly { start } => start.format("%Y%m%dT%H%M%SZ").to_string(),
+            EventTime::AllDay(dates) => dates.start.format("%Y%m%d").to_string(),
+        }
+    }
+
+    /// 予定が占める期間。終日予定は`time_zone`での日付として扱う
+    pub fn span(&self, time_zone: &Tz) -> DateTimeRange {
+        match self {
+            EventTime::Timed(range) => range.clone(),
+            EventTime::AllDay(dates) => DateTimeRange::from_dates(dates, time_zone),
+            EventTime::StartOnly { start } => DateTimeRange::new(*start, *start),
+        }
+    }
+}
+impl From<DateTimeRange> for EventTime {
+    fn from(value: DateTimeRange) -> Self {
+        EventTime::Timed(value)
+    }
+}
+impl std::fmt::Display for EventTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EventTime::Timed(range) => write!(
+                f,
+                "{}/{}",
+                range.start.to_rfc3339_opts(SecondsFormat::Secs, true),
+                range.end.to_rfc3339_opts(SecondsFormat::Secs, true),
+            ),
+            EventTime::AllDay(dates) => write!(f, "{}/{}", dates.start, dates.end),
+            EventTime::StartOnly { start } => write!(f, "{}/", start.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        }
+    }
+}
+
+/// 予定の開始・終了それぞれのタイムゾーン
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct EventTimeZones {
+    pub(crate) start: Tz,
+    pub(crate) end: Tz,
+}
+impl EventTimeZones {
+    pub fn new(start: Tz, end: Tz) -> Self {
+        Self { start, end }
+    }
+}
+impl Default for EventTimeZones {
+    fn default() -> Self {
+        Self::new(Tz::UTC, Tz::UTC)
+    }
+}
+impl std::fmt::Display for EventTimeZones {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{} -> {}", self.start, self.end)
+        }
+    }
+}
+
+/// Garoonの予定ID
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EventId(String);
+impl EventId {
+    pub fn new(id: String) -> Self {
+        Self(id)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
@@ -58,7 +346,7 @@ impl From<String> for Title {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Attendee {
-    display_name: String,
+    pub(crate) display_name: String,
 }
 impl Attendee {
     pub fn new(display_name: String) -> Self {
@@ -69,31 +357,315 @@ impl Attendee {
 #[cfg(test)]
 mod tests {
     use chrono::{TimeZone, Utc};
-    use crate::apis::garoon::{GaroonAttendee, GaroonDateTime, GaroonEvent};
-    use crate::models::event::{Event, Title};
+    use crate::apis::garoon::{GaroonAttendee, GaroonDateTime, GaroonEvent, GaroonFacility, GaroonVisibilityType};
+    use chrono::NaiveDate;
+
+    use crate::apis::garoon::GaroonEventType;
+    use crate::error::ParseError;
+    use crate::models::event::{Attendee, Event, EventId, EventTime, EventTimeZones, FetchedEvents, Title};
+    use crate::utils::date_range::DateRange;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn garoon_date_time(date_time: &str) -> GaroonDateTime {
+        GaroonDateTime {
+            date_time: date_time.to_string(),
+            time_zone: "Asia/Tokyo".to_string(),
+        }
+    }
 
     #[test]
     fn test_from_garoon_event() {
         let garoon_event = GaroonEvent {
+            id: "123".to_string(),
             subject: "会議".to_string(),
+            notes: "資料を持参".to_string(),
+            visibility_type: GaroonVisibilityType::Private,
+            facilities: vec![
+                GaroonFacility { name: "会議室A".to_string(), ..Default::default() },
+                GaroonFacility { name: "会議室B".to_string(), ..Default::default() },
+            ],
+            updated_at: Some(Utc.with_ymd_and_hms(2020, 12, 1, 0, 0, 0).unwrap()),
             attendees: vec![
                 GaroonAttendee {
-                    name: "user1".to_string()
+                    name: "user1".to_string(),
+                    ..Default::default()
                 }
             ],
             start: GaroonDateTime {
-                dateTime: "2021-01-01T09:00:00+09:00".to_string(),
-                timeZone: "Asia/Tokyo".to_string(),
-            },
-            end: GaroonDateTime {
-                dateTime: "2021-01-01T10:00:00+09:00".to_string(),
-                timeZone: "Asia/Tokyo".to_string(),
+                date_time: "2021-01-01T09:00:00+09:00".to_string(),
+                time_zone: "Asia/Tokyo".to_string(),
             },
+            end: Some(GaroonDateTime {
+                date_time: "2021-01-01T10:00:00+09:00".to_string(),
+                time_zone: "Asia/Tokyo".to_string(),
+            }),
+            ..Default::default()
         };
-        let event = Event::from(garoon_event);
+        let event = Event::try_from(garoon_event).unwrap();
+        assert_eq!(event.id, EventId::new("123".to_string()));
         assert_eq!(event.title, Title::new("会議".to_string()));
         assert_eq!(event.attendees[0].display_name, "user1".to_string());
-        assert_eq!(event.duration.start, Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap());
-        assert_eq!(event.duration.end, Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap());
+        assert_eq!(event.time, EventTime::Timed(DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+        )));
+        assert_eq!(event.description, Some("資料を持参".to_string()));
+        assert_eq!(event.location, Some("会議室A, 会議室B".to_string()));
+        assert!(event.private);
+        assert_eq!(event.updated_at, Some(Utc.with_ymd_and_hms(2020, 12, 1, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let event = |title: &str| Event::new(
+            EventId::new("1".to_string()),
+            Title::new(title.to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![Attendee::new("user1".to_string())],
+        );
+
+        assert_eq!(event("会議").content_hash(), event("会議").content_hash());
+        assert_ne!(event("会議").content_hash(), event("打ち合わせ").content_hash());
+        assert_ne!(event("会議").content_hash(), event("会議").with_location(Some("会議室A".to_string())).content_hash());
+        assert_eq!(event("会議").content_hash().len(), 64);
+    }
+
+    #[test]
+    fn test_from_garoon_event_終日予定() {
+        let garoon_event = GaroonEvent {
+            id: "1".to_string(),
+            event_type: GaroonEventType::AllDay,
+            is_all_day: true,
+            start: garoon_date_time("2021-01-01T00:00:00+09:00"),
+            end: Some(garoon_date_time("2021-01-02T23:59:59+09:00")),
+            ..Default::default()
+        };
+
+        let event = Event::try_from(garoon_event).unwrap();
+
+        assert_eq!(event.time, EventTime::AllDay(DateRange::new(
+            NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2021, 1, 2).unwrap(),
+        )));
+    }
+
+    #[test]
+    fn test_from_garoon_event_終日予定_終了が翌日0時() {
+        let garoon_event = GaroonEvent {
+            id: "1".to_string(),
+            is_all_day: true,
+            start: garoon_date_time("2021-01-01T00:00:00+09:00"),
+            end: Some(garoon_date_time("2021-01-02T00:00:00+09:00")),
+            ..Default::default()
+        };
+
+        let event = Event::try_from(garoon_event).unwrap();
+
+        assert_eq!(event.time, EventTime::AllDay(DateRange::new(
+            NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+        )));
+    }
+
+    #[test]
+    fn test_from_garoon_event_開始時刻のみ() {
+        let garoon_event = GaroonEvent {
+            id: "1".to_string(),
+            is_start_only: true,
+            start: garoon_date_time("2021-01-01T09:00:00+09:00"),
+            end: Some(garoon_date_time("2021-01-01T09:00:00+09:00")),
+            ..Default::default()
+        };
+
+        let event = Event::try_from(garoon_event).unwrap();
+
+        assert_eq!(event.time, EventTime::StartOnly { start: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap() });
+        assert_eq!(event.span().start, event.span().end);
+    }
+
+    #[test]
+    fn test_from_garoon_event_開始と終了でタイムゾーンが異なる() {
+        let garoon_event = GaroonEvent {
+            id: "1".to_string(),
+            start: garoon_date_time("2021-01-01T09:00:00+09:00"),
+            end: Some(GaroonDateTime {
+                date_time: "2020-12-31T19:00:00-05:00".to_string(),
+                time_zone: "America/New_York".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let event = Event::try_from(garoon_event).unwrap();
+
+        assert_eq!(event.time_zones, EventTimeZones::new(chrono_tz::Asia::Tokyo, chrono_tz::America::New_York));
+        assert_eq!(event.span(), DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+        ));
+    }
+
+    #[test]
+    fn test_from_garoon_event_解釈できないタイムゾーン() {
+        let garoon_event = GaroonEvent {
+            id: "1".to_string(),
+            start: garoon_date_time("2021-01-01T09:00:00+09:00"),
+            end: Some(GaroonDateTime {
+                date_time: "2021-01-01T10:00:00+09:00".to_string(),
+                time_zone: "Mars/Olympus".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        let fetched: FetchedEvents = vec![garoon_event].into_iter().collect();
+
+        assert!(fetched.events.is_empty());
+        assert_eq!(fetched.skipped[0].error, ParseError::TimeZone("Mars/Olympus".to_string()));
+    }
+
+    #[test]
+    fn test_span_終日予定() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("休暇".to_string()),
+            EventTime::AllDay(DateRange::new(
+                NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+                NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(),
+            )),
+            vec![],
+        )
+            .with_time_zones(EventTimeZones::new(chrono_tz::Asia::Tokyo, chrono_tz::Asia::Tokyo));
+
+        assert_eq!(event.span(), DateTimeRange::new(
+            Utc.with_ymd_and_hms(2020, 12, 31, 15, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 1, 15, 0, 0).unwrap(),
+        ));
+    }
+
+    #[test]
+    fn test_try_from_garoon_event_不正な日時() {
+        let garoon_event = GaroonEvent {
+            id: "1".to_string(),
+            start: garoon_date_time("2021-01-01 09:00"),
+            end: Some(garoon_date_time("2021-01-01T10:00:00+09:00")),
+            ..Default::default()
+        };
+
+        let result = Event::try_from(garoon_event);
+
+        assert!(matches!(result, Err(ParseError::DateTime { value, .. }) if value == "2021-01-01 09:00"));
+    }
+
+    #[test]
+    fn test_try_from_garoon_event_終了が開始より前() {
+        let garoon_event = GaroonEvent {
+            id: "1".to_string(),
+            start: garoon_date_time("2021-01-01T10:00:00+09:00"),
+            end: Some(garoon_date_time("2021-01-01T09:00:00+09:00")),
+            ..Default::default()
+        };
+
+        let result = Event::try_from(garoon_event);
+
+        assert!(matches!(result, Err(ParseError::InvalidRange { .. })));
+    }
+
+    #[test]
+    fn test_fetched_events_from_iter() {
+        let garoon_events = vec![
+            GaroonEvent {
+                id: "1".to_string(),
+                start: garoon_date_time("invalid"),
+                ..Default::default()
+            },
+            GaroonEvent {
+                id: "2".to_string(),
+                start: garoon_date_time("2021-01-01T09:00:00+09:00"),
+                ..Default::default()
+            },
+        ];
+
+        let fetched: FetchedEvents = garoon_events.into_iter().collect();
+
+        assert_eq!(fetched.events().len(), 1);
+        assert_eq!(fetched.skipped().len(), 1);
+        assert_eq!(fetched.skipped()[0].id, EventId::new("1".to_string()));
+    }
+
+    /// 毎週金曜日 9:00〜10:00 (2024-04-01〜2024-09-30、2024-05-03を除く) の`date`の回
+    fn repeating_garoon_event(date: &str) -> GaroonEvent {
+        GaroonEvent {
+            id: "1".to_string(),
+            event_type: GaroonEventType::Repeating,
+            start: garoon_date_time(&format!("{}T09:00:00+09:00", date)),
+            end: Some(garoon_date_time(&format!("{}T10:00:00+09:00", date))),
+            repeat_info: Some(serde_json::from_str(r#"{
+                "type": "EVERY_WEEK",
+                "period": {"start": "2024-04-01", "end": "2024-09-30"},
+                "timeZone": "Asia/Tokyo",
+                "dayOfWeek": "FRI",
+                "exclusiveDateTimes": [{"start": "2024-05-03T00:00:00+09:00", "end": "2024-05-04T00:00:00+09:00"}]
+            }"#).unwrap()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_garoon_event_繰り返し予定() {
+        let event = Event::try_from(repeating_garoon_event("2024-05-10")).unwrap();
+
+        assert_eq!(event.recurrence_lines(), vec![
+            "RRULE:FREQ=WEEKLY;BYDAY=FR;UNTIL=20240930T145959Z".to_string(),
+            "EXDATE;TZID=Asia/Tokyo:20240503T090000".to_string(),
+        ]);
+        // 繰り返しの最終日まで占める
+        assert_eq!(event.span().end, Utc.with_ymd_and_hms(2024, 9, 30, 15, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_merge_recurring() {
+        let garoon_events = vec![
+            repeating_garoon_event("2024-05-10"),
+            repeating_garoon_event("2024-05-17"),
+            GaroonEvent {
+                id: "2".to_string(),
+                start: garoon_date_time("2024-05-10T13:00:00+09:00"),
+                ..Default::default()
+            },
+        ];
+        let fetched: FetchedEvents = garoon_events.into_iter().collect();
+
+        let merged = fetched.merge_recurring();
+
+        // 繰り返し予定は初回の日時を持つ1件にまとめる
+        assert_eq!(merged.events().len(), 2);
+        assert_eq!(merged.events()[0].time, EventTime::Timed(DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 4, 5, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 4, 5, 1, 0, 0).unwrap(),
+        )));
+        assert_eq!(merged.events()[1].id, EventId::new("2".to_string()));
+    }
+
+    #[test]
+    fn test_key_instances() {
+        let garoon_events = vec![
+            repeating_garoon_event("2024-05-10"),
+            repeating_garoon_event("2024-05-17"),
+            GaroonEvent {
+                id: "2".to_string(),
+                start: garoon_date_time("2024-05-10T13:00:00+09:00"),
+                ..Default::default()
+            },
+        ];
+        let fetched: FetchedEvents = garoon_events.into_iter().collect();
+
+        let keyed = fetched.key_instances();
+
+        // 繰り返し予定の回は、予定IDに回の開始日時を付けた別の予定になる
+        let ids: Vec<&str> = keyed.events().iter().map(|event| event.id.as_str()).collect();
+        assert_eq!(ids, vec!["1/20240510T000000Z", "1/20240517T000000Z", "2"]);
+        assert!(keyed.events().iter().all(|event| event.recurrence.is_none()));
     }
 }
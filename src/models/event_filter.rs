@@ -0,0 +1,56 @@
+use crate::models::event::Event;
+
+/// 転写しない予定の条件
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EventFilter {
+    /// 件名にいずれかを含む予定を除く
+    pub(crate) exclude_subjects: Vec<String>,
+    /// 非公開の予定を除く
+    pub(crate) exclude_private: bool,
+}
+impl EventFilter {
+    pub fn new(exclude_subjects: Vec<String>, exclude_private: bool) -> Self {
+        Self { exclude_subjects, exclude_private }
+    }
+
+    /// 転写する予定か
+    pub fn matches(&self, event: &Event) -> bool {
+        let title = event.title.as_str();
+        if self.exclude_subjects.iter().any(|subject| title.contains(subject.as_str())) {
+            return false;
+        }
+
+        !(self.exclude_private && event.private)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::models::event::{Event, EventId, Title};
+    use crate::models::event_filter::EventFilter;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn event(title: &str) -> Event {
+        Event::new(
+            EventId::new("1".to_string()),
+            Title::new(title.to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter = EventFilter::new(vec!["昼休み".to_string()], true);
+
+        assert!(filter.matches(&event("会議")));
+        assert!(!filter.matches(&event("【固定】昼休み")));
+        assert!(!filter.matches(&event("会議").with_private(true)));
+        assert!(EventFilter::default().matches(&event("昼休み").with_private(true)));
+    }
+}
@@ -1 +1,6 @@
-pub mod event;
\ No newline at end of file
+pub mod event;
+pub mod event_filter;
+pub mod oauth_token;
+pub mod recurrence;
+pub mod sync_plan;
+pub mod sync_state;
\ No newline at end of file
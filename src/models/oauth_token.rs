@@ -0,0 +1,37 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// OAuth 2.0のアクセストークン。リフレッシュトークンで更新しながらローカルに保存する
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OAuthToken {
+    pub(crate) access_token: String,
+    pub(crate) refresh_token: Option<String>,
+    pub(crate) expires_at: Option<DateTime<Utc>>,
+}
+impl OAuthToken {
+    pub fn new(access_token: String, refresh_token: Option<String>, expires_at: Option<DateTime<Utc>>) -> Self {
+        Self { access_token, refresh_token, expires_at }
+    }
+
+    /// リクエスト中に失効しないよう、期限の1分前から期限切れとみなす
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at - Duration::minutes(1) <= now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::models::oauth_token::OAuthToken;
+
+    #[test]
+    fn test_is_expired() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap();
+        let token = |expires_at| OAuthToken::new("access".to_string(), None, expires_at);
+
+        assert!(!token(None).is_expired(now));
+        assert!(!token(Some(now + Duration::minutes(5))).is_expired(now));
+        assert!(token(Some(now + Duration::seconds(30))).is_expired(now));
+    }
+}
@@ -0,0 +1,405 @@
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::apis::garoon::{GaroonDayOfMonth, GaroonDayOfWeek, GaroonRepeatInfo, GaroonRepeatType};
+use crate::error::ParseError;
+use crate::models::event::EventTime;
+use crate::utils::date_range::DateRange;
+use crate::utils::date_time_range::{start_of_day, DateTimeRange};
+use crate::utils::time_zone::parse_time_zone;
+
+/// 繰り返し予定の規則。RFC 5545のRRULE/EXDATEに変換できる
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Recurrence {
+    pub(crate) frequency: Frequency,
+    /// 繰り返す期間。終了日も含む
+    pub(crate) period: DateRange,
+    /// 繰り返しから除外した日。個別に変更した回は別の予定になり、ここに含まれる
+    pub(crate) exceptions: Vec<NaiveDate>,
+    /// 日付と時刻を解釈するタイムゾーン
+    pub(crate) time_zone: Tz,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    /// 月曜日から金曜日
+    Weekdays,
+    Weekly(Weekday),
+    /// 毎月第n週または最終週の曜日
+    MonthlyByWeekday { week: WeekOfMonth, weekday: Weekday },
+    MonthlyByDate(DayOfMonth),
+    Yearly { month: u32, day: u32 },
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WeekOfMonth {
+    /// 1〜4
+    Nth(u32),
+    Last,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DayOfMonth {
+    Day(u32),
+    Last,
+}
+
+impl Recurrence {
+    pub fn new(frequency: Frequency, period: DateRange, time_zone: Tz) -> Self {
+        Self { frequency, period, exceptions: vec![], time_zone }
+    }
+
+    pub fn with_exceptions(mut self, exceptions: Vec<NaiveDate>) -> Self {
+        self.exceptions = exceptions;
+        self
+    }
+
+    /// Garoonの`repeatInfo`から変換する。タイムゾーンが無いか解釈できない場合は`default_time_zone`を使う
+    pub fn from_garoon(repeat_info: &GaroonRepeatInfo, default_time_zone: Tz) -> Result<Self, ParseError> {
+        let time_zone = parse_time_zone(&repeat_info.time_zone).unwrap_or(default_time_zone);
+        let period = DateRange::try_new(repeat_info.period.start, repeat_info.period.end)?;
+        let weekday = || {
+            repeat_info
+                .day_of_week
+                .map(weekday)
+                .ok_or_else(|| ParseError::Recurrence(format!("{:?} requires dayOfWeek", repeat_info.repeat_type)))
+        };
+        let monthly_by_weekday = |week| Ok::<_, ParseError>(Frequency::MonthlyByWeekday { week, weekday: weekday()? });
+
+        let frequency = match repeat_info.repeat_type {
+            GaroonRepeatType::EveryDay => Frequency::Daily,
+            GaroonRepeatType::EveryWeekday => Frequency::Weekdays,
+            GaroonRepeatType::EveryWeek => Frequency::Weekly(weekday()?),
+            GaroonRepeatType::Every1stWeek => monthly_by_weekday(WeekOfMonth::Nth(1))?,
+            GaroonRepeatType::Every2ndWeek => monthly_by_weekday(WeekOfMonth::Nth(2))?,
+            GaroonRepeatType::Every3rdWeek => monthly_by_weekday(WeekOfMonth::Nth(3))?,
+            GaroonRepeatType::Every4thWeek => monthly_by_weekday(WeekOfMonth::Nth(4))?,
+            GaroonRepeatType::EveryLastWeek => monthly_by_weekday(WeekOfMonth::Last)?,
+            GaroonRepeatType::EveryMonth => match repeat_info.day_of_month {
+                Some(GaroonDayOfMonth::Day(day)) => Frequency::MonthlyByDate(DayOfMonth::Day(day)),
+                Some(GaroonDayOfMonth::EndOfMonth) => Frequency::MonthlyByDate(DayOfMonth::Last),
+                None => return Err(ParseError::Recurrence("EveryMonth requires dayOfMonth".to_string())),
+            },
+            // 毎年の繰り返しは、繰り返しの開始日と同じ月日
+            GaroonRepeatType::EveryYear => Frequency::Yearly { month: period.start.month(), day: period.start.day() },
+            GaroonRepeatType::Unknown => return Err(ParseError::Recurrence("unknown repeat type".to_string())),
+        };
+        let exceptions = repeat_info
+            .exclusive_date_times
+            .iter()
+            .map(|exclusive| exclusive.start.with_timezone(&time_zone).date_naive())
+            .collect();
+
+        Ok(Self::new(frequency, period, time_zone).with_exceptions(exceptions))
+    }
+
+    /// `date`が繰り返しの規則に当てはまるか。除外した日も当てはまるものとして扱う
+    pub fn matches(&self, date: NaiveDate) -> bool {
+        if date < self.period.start || date > self.period.end {
+            return false;
+        }
+
+        match self.frequency {
+            Frequency::Daily => true,
+            Frequency::Weekdays => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+            Frequency::Weekly(weekday) => date.weekday() == weekday,
+            Frequency::MonthlyByWeekday { week, weekday } => {
+                date.weekday() == weekday
+                    && match week {
+                        WeekOfMonth::Nth(n) => (date.day() - 1) / 7 + 1 == n,
+                        WeekOfMonth::Last => (date + Duration::days(7)).month() != date.month(),
+                    }
+            }
+            Frequency::MonthlyByDate(DayOfMonth::Day(day)) => date.day() == day,
+            Frequency::MonthlyByDate(DayOfMonth::Last) => date.succ_opt().is_none_or(|next| next.month() != date.month()),
+            Frequency::Yearly { month, day } => date.month() == month && date.day() == day,
+        }
+    }
+
+    /// 繰り返しの初回の日付。RRULEの起点(DTSTART)になる
+    pub fn first_date(&self) -> Option<NaiveDate> {
+        self.period.start.iter_days().take_while(|date| *date <= self.period.end).find(|date| self.matches(*date))
+    }
+
+    /// 展開された1回分の日時を、時刻と長さを保ったまま初回の日付に移す
+    pub fn series_time(&self, instance: &EventTime) -> EventTime {
+        match self.first_date() {
+            Some(first_date) => self.time_on(instance, first_date),
+            None => instance.clone(),
+        }
+    }
+
+    /// `time`を初回の日時とする回のいずれかが`period`と重なるか。除外した日の回は含めない
+    pub fn occurs_within(&self, time: &EventTime, period: &DateTimeRange) -> bool {
+        let span = time.span(&self.time_zone);
+        // 期間の開始より前に始まっても、期間に掛かる長さの回があるので、その日数だけ遡って調べる
+        let from = period.start.with_timezone(&self.time_zone).date_naive() - (span.end - span.start) - Duration::days(1);
+        let to = period.end.with_timezone(&self.time_zone).date_naive();
+
+        from.max(self.period.start)
+            .iter_days()
+            .take_while(|date| *date <= to.min(self.period.end))
+            .filter(|date| self.matches(*date) && !self.exceptions.contains(date))
+            .any(|date| period.overlaps(&self.time_on(time, date).span(&self.time_zone)))
+    }
+
+    /// 時刻と長さを保ったまま`date`の回の日時にする
+    fn time_on(&self, time: &EventTime, date: NaiveDate) -> EventTime {
+        match time {
+            EventTime::Timed(range) => {
+                let start = self.move_to(&range.start, date);
+                EventTime::Timed(DateTimeRange::new(start, start + (range.end - range.start)))
+            }
+            EventTime::AllDay(dates) => {
+                let length = dates.end - dates.start;
+                EventTime::AllDay(DateRange::new(date, date + length))
+            }
+            EventTime::StartOnly { start } => EventTime::StartOnly { start: self.move_to(start, date) },
+        }
+    }
+
+    /// 繰り返しの最終日の終わり
+    pub fn end(&self) -> DateTime<Utc> {
+        start_of_day(self.period.exclusive_end(), &self.time_zone)
+    }
+
+    /// RFC 5545のRRULE行とEXDATE行。`time`は初回の日時
+    pub fn to_rfc5545(&self, time: &EventTime) -> Vec<String> {
+        let mut lines = vec![self.rrule(time)];
+        if let Some(exdate) = self.exdate(time) {
+            lines.push(exdate);
+        }
+
+        lines
+    }
+
+    fn rrule(&self, time: &EventTime) -> String {
+        let rule = match self.frequency {
+            Frequency::Daily => "FREQ=DAILY".to_string(),
+            Frequency::Weekdays => "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR".to_string(),
+            Frequency::Weekly(weekday) => format!("FREQ=WEEKLY;BYDAY={}", by_day(weekday)),
+            Frequency::MonthlyByWeekday { week: WeekOfMonth::Nth(n), weekday } => format!("FREQ=MONTHLY;BYDAY={}{}", n, by_day(weekday)),
+            Frequency::MonthlyByWeekday { week: WeekOfMonth::Last, weekday } => format!("FREQ=MONTHLY;BYDAY=-1{}", by_day(weekday)),
+            Frequency::MonthlyByDate(DayOfMonth::Day(day)) => format!("FREQ=MONTHLY;BYMONTHDAY={}", day),
+            Frequency::MonthlyByDate(DayOfMonth::Last) => "FREQ=MONTHLY;BYMONTHDAY=-1".to_string(),
+            Frequency::Yearly { month, day } => format!("FREQ=YEARLY;BYMONTH={};BYMONTHDAY={}", month, day),
+        };
+        // 終日予定のUNTILは日付、時刻のある予定はUTCの日時で指定する
+        let until = match time {
+            EventTime::AllDay(_) => self.period.end.format("%Y%m%d").to_string(),
+            _ => (self.end() - Duration::seconds(1)).format("%Y%m%dT%H%M%SZ").to_string(),
+        };
+
+        format!("RRULE:{};UNTIL={}", rule, until)
+    }
+
+    fn exdate(&self, time: &EventTime) -> Option<String> {
+        if self.exceptions.is_empty() {
+            return None;
+        }
+
+        let line = match time {
+            EventTime::AllDay(_) => {
+                let dates: Vec<String> = self.exceptions.iter().map(|date| date.format("%Y%m%d").to_string()).collect();
+                format!("EXDATE;VALUE=DATE:{}", dates.join(","))
+            }
+            EventTime::Timed(range) => self.exdate_date_times(&range.start),
+            EventTime::StartOnly { start } => self.exdate_date_times(start),
+        };
+
+        Some(line)
+    }
+
+    /// 除外した日の、初回と同じ時刻を並べる
+    fn exdate_date_times(&self, start: &DateTime<Utc>) -> String {
+        let local_time = start.with_timezone(&self.time_zone).time();
+        let date_times: Vec<String> = self
+            .exceptions
+            .iter()
+            .map(|date| date.and_time(local_time).format("%Y%m%dT%H%M%S").to_string())
+            .collect();
+
+        format!("EXDATE;TZID={}:{}", self.time_zone.name(), date_times.join(","))
+    }
+
+    fn move_to(&self, date_time: &DateTime<Utc>, date: NaiveDate) -> DateTime<Utc> {
+        let local = date.and_time(date_time.with_timezone(&self.time_zone).time());
+        match self.time_zone.from_local_datetime(&local) {
+            LocalResult::Single(moved) | LocalResult::Ambiguous(moved, _) => moved.with_timezone(&Utc),
+            // 夏時間の切り替えで存在しない時刻は、日数だけずらして近似する
+            LocalResult::None => *date_time + (date - date_time.with_timezone(&self.time_zone).date_naive()),
+        }
+    }
+}
+
+fn weekday(day_of_week: GaroonDayOfWeek) -> Weekday {
+    match day_of_week {
+        GaroonDayOfWeek::Sun => Weekday::Sun,
+        GaroonDayOfWeek::Mon => Weekday::Mon,
+        GaroonDayOfWeek::Tue => Weekday::Tue,
+        GaroonDayOfWeek::Wed => Weekday::Wed,
+        GaroonDayOfWeek::Thu => Weekday::Thu,
+        GaroonDayOfWeek::Fri => Weekday::Fri,
+        GaroonDayOfWeek::Sat => Weekday::Sat,
+    }
+}
+
+fn by_day(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc, Weekday};
+    use chrono_tz::Asia;
+
+    use crate::apis::garoon::GaroonEvent;
+    use crate::models::event::EventTime;
+    use crate::models::recurrence::{DayOfMonth, Frequency, Recurrence, WeekOfMonth};
+    use crate::utils::date_range::DateRange;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn recurrence(frequency: Frequency) -> Recurrence {
+        Recurrence::new(frequency, DateRange::new(date(4, 1), date(9, 30)), Asia::Tokyo)
+    }
+
+    /// 2024-04-01 09:00〜10:00 (Asia/Tokyo)
+    fn timed() -> EventTime {
+        EventTime::Timed(DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 4, 1, 1, 0, 0).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn test_from_garoon() {
+        let json = r#"{
+            "id": "123",
+            "eventType": "REPEATING",
+            "subject": "定例",
+            "start": {"dateTime": "2024-05-10T09:00:00+09:00", "timeZone": "Asia/Tokyo"},
+            "end": {"dateTime": "2024-05-10T10:00:00+09:00", "timeZone": "Asia/Tokyo"},
+            "repeatInfo": {
+                "type": "EVERY_WEEK",
+                "period": {"start": "2024-04-01", "end": "2024-09-30"},
+                "time": {"start": "09:00:00", "end": "10:00:00"},
+                "timeZone": "Asia/Tokyo",
+                "dayOfWeek": "FRI",
+                "exclusiveDateTimes": [{"start": "2024-05-03T00:00:00+09:00", "end": "2024-05-04T00:00:00+09:00"}]
+            }
+        }"#;
+        let event: GaroonEvent = serde_json::from_str(json).unwrap();
+
+        let recurrence = Recurrence::from_garoon(event.repeat_info.as_ref().unwrap(), chrono_tz::UTC).unwrap();
+
+        assert_eq!(recurrence.frequency, Frequency::Weekly(Weekday::Fri));
+        assert_eq!(recurrence.period, DateRange::new(date(4, 1), date(9, 30)));
+        assert_eq!(recurrence.exceptions, vec![date(5, 3)]);
+        assert_eq!(recurrence.time_zone, Asia::Tokyo);
+        assert_eq!(recurrence.first_date(), Some(date(4, 5)));
+    }
+
+    #[test]
+    fn test_rrule() {
+        let cases = [
+            (Frequency::Daily, "FREQ=DAILY"),
+            (Frequency::Weekdays, "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"),
+            (Frequency::Weekly(Weekday::Fri), "FREQ=WEEKLY;BYDAY=FR"),
+            (Frequency::MonthlyByWeekday { week: WeekOfMonth::Nth(2), weekday: Weekday::Tue }, "FREQ=MONTHLY;BYDAY=2TU"),
+            (Frequency::MonthlyByWeekday { week: WeekOfMonth::Last, weekday: Weekday::Fri }, "FREQ=MONTHLY;BYDAY=-1FR"),
+            (Frequency::MonthlyByDate(DayOfMonth::Day(15)), "FREQ=MONTHLY;BYMONTHDAY=15"),
+            (Frequency::MonthlyByDate(DayOfMonth::Last), "FREQ=MONTHLY;BYMONTHDAY=-1"),
+            (Frequency::Yearly { month: 4, day: 1 }, "FREQ=YEARLY;BYMONTH=4;BYMONTHDAY=1"),
+        ];
+
+        for (frequency, rule) in cases {
+            // 繰り返しの最終日の終わりをUTCで表す
+            assert_eq!(recurrence(frequency).to_rfc5545(&timed()), vec![format!("RRULE:{};UNTIL=20240930T145959Z", rule)]);
+        }
+    }
+
+    #[test]
+    fn test_to_rfc5545_除外日() {
+        let recurrence = recurrence(Frequency::Weekly(Weekday::Fri)).with_exceptions(vec![date(5, 3), date(8, 16)]);
+        let all_day = EventTime::AllDay(DateRange::new(date(4, 5), date(4, 5)));
+
+        assert_eq!(recurrence.to_rfc5545(&timed()), vec![
+            "RRULE:FREQ=WEEKLY;BYDAY=FR;UNTIL=20240930T145959Z".to_string(),
+            "EXDATE;TZID=Asia/Tokyo:20240503T090000,20240816T090000".to_string(),
+        ]);
+        assert_eq!(recurrence.to_rfc5545(&all_day), vec![
+            "RRULE:FREQ=WEEKLY;BYDAY=FR;UNTIL=20240930".to_string(),
+            "EXDATE;VALUE=DATE:20240503,20240816".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_first_date() {
+        let first_date = |frequency| recurrence(frequency).first_date();
+
+        assert_eq!(first_date(Frequency::Daily), Some(date(4, 1)));
+        assert_eq!(first_date(Frequency::Weekly(Weekday::Fri)), Some(date(4, 5)));
+        assert_eq!(first_date(Frequency::MonthlyByWeekday { week: WeekOfMonth::Nth(2), weekday: Weekday::Tue }), Some(date(4, 9)));
+        assert_eq!(first_date(Frequency::MonthlyByWeekday { week: WeekOfMonth::Last, weekday: Weekday::Fri }), Some(date(4, 26)));
+        assert_eq!(first_date(Frequency::MonthlyByDate(DayOfMonth::Day(31))), Some(date(5, 31)));
+        assert_eq!(first_date(Frequency::MonthlyByDate(DayOfMonth::Last)), Some(date(4, 30)));
+        assert_eq!(first_date(Frequency::Yearly { month: 1, day: 1 }), None);
+    }
+
+    #[test]
+    fn test_series_time() {
+        let recurrence = recurrence(Frequency::Weekly(Weekday::Fri));
+        let instance = EventTime::Timed(DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 5, 10, 1, 30, 0).unwrap(),
+        ));
+
+        assert_eq!(recurrence.series_time(&instance), EventTime::Timed(DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 4, 5, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 4, 5, 1, 30, 0).unwrap(),
+        )));
+        assert_eq!(
+            recurrence.series_time(&EventTime::AllDay(DateRange::new(date(5, 10), date(5, 11)))),
+            EventTime::AllDay(DateRange::new(date(4, 5), date(4, 6))),
+        );
+    }
+
+    #[test]
+    fn test_occurs_within() {
+        // 毎週金曜日の9時から10時 (東京)
+        let recurrence = recurrence(Frequency::Weekly(Weekday::Fri)).with_exceptions(vec![date(5, 17)]);
+        let first = EventTime::Timed(DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 4, 5, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 4, 5, 1, 0, 0).unwrap(),
+        ));
+        let period = |from: u32, to: u32| DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, from, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 5, to, 0, 0, 0).unwrap(),
+        );
+
+        assert!(recurrence.occurs_within(&first, &period(6, 13)));
+        // 金曜日を含まない期間
+        assert!(!recurrence.occurs_within(&first, &period(11, 17)));
+        // 期間内の回が除外されている
+        assert!(!recurrence.occurs_within(&first, &period(13, 20)));
+        // 期間の開始より前に始まり、期間に掛かる回
+        assert!(recurrence.occurs_within(&first, &DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 10, 0, 30, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 5, 10, 0, 45, 0).unwrap(),
+        )));
+    }
+}
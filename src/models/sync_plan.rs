@@ -0,0 +1,444 @@
+use std::collections::HashSet;
+
+use anyhow::bail;
+
+use crate::models::event::{Event, EventId, EventTime, EventTimeZones, FetchedEvents, SkippedEvent};
+use crate::models::sync_state::{SyncRecord, SyncState};
+use crate::utils::date_time_range::DateTimeRange;
+
+/// 取得したGaroonの予定と同期済みの記録を突き合わせた、転写先への反映内容
+#[derive(Debug)]
+pub struct SyncPlan {
+    pub(crate) creates: Vec<Event>,
+    pub(crate) updates: Vec<SyncUpdate>,
+    pub(crate) deletes: Vec<SyncRecord>,
+    pub(crate) unchanged: usize,
+    /// 変更のない予定のうち、記録し直す同期済みの予定。古い同期状態にない期間などを補う
+    pub(crate) refreshes: Vec<SyncRecord>,
+    /// 取得期間内にあった同期済みの予定の件数
+    pub(crate) synced_in_period: usize,
+    /// 変換できずに反映を見送った予定
+    pub(crate) skipped: Vec<SkippedEvent>,
+}
+impl SyncPlan {
+    /// `events`は`period`の期間で取得したGaroonの予定。
+    /// 期間内の同期済みの予定のうち、取得結果に含まれないものはGaroonで削除されたとみなす。
+    /// 変換できなかった予定はGaroonには存在するため、削除しない。
+    pub fn new(events: impl Into<FetchedEvents>, state: &SyncState, period: &DateTimeRange) -> Self {
+        let FetchedEvents { events, skipped } = events.into();
+        let mut creates = vec![];
+        let mut updates = vec![];
+        let mut unchanged = 0;
+        let mut refreshes = vec![];
+        let fetched_ids: HashSet<EventId> = events
+            .iter()
+            .map(|event| event.id.clone())
+            .chain(skipped.iter().map(|skipped| skipped.id.clone()))
+            .collect();
+        // 回ごとに記録した繰り返し予定(`予定ID/開始日時`)も、変換できなかった場合は削除しない
+        let skipped_series: Vec<String> = skipped.iter().map(|skipped| format!("{}/", skipped.id.as_str())).collect();
+
+        let synced_in_period: Vec<&SyncRecord> = state
+            .records()
+            .filter(|record| record.overlaps(period))
+            .collect();
+        let deletes = synced_in_period
+            .iter()
+            .filter(|record| !fetched_ids.contains(&record.garoon_id()))
+            .filter(|record| !skipped_series.iter().any(|prefix| record.garoon_id.starts_with(prefix)))
+            .map(|record| (*record).clone())
+            .collect();
+
+        for event in events {
+            match state.get(&event.id) {
+                None => creates.push(event),
+                Some(record) if record.content_hash != event.content_hash() => {
+                    updates.push(SyncUpdate { event, record: record.clone() })
+                }
+                Some(record) => {
+                    unchanged += 1;
+                    let refreshed = SyncRecord::new(&event, record.google_event_id.clone());
+                    if refreshed != *record {
+                        refreshes.push(refreshed);
+                    }
+                }
+            }
+        }
+
+        Self { creates, updates, deletes, unchanged, refreshes, synced_in_period: synced_in_period.len(), skipped }
+    }
+
+    /// 変更のない予定の記録を、今回取得した内容で記録し直す
+    pub fn refresh(&self, state: &mut SyncState) {
+        for record in &self.refreshes {
+            state.upsert(record.clone());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.creates.is_empty() && self.updates.is_empty() && self.deletes.is_empty()
+    }
+
+    /// 削除件数が異常に多い場合はエラーにする
+    pub fn check_deletions(&self, guard: &DeletionGuard) -> Result<(), anyhow::Error> {
+        if self.deletes.len() < guard.min_count || self.synced_in_period == 0 {
+            return Ok(());
+        }
+
+        let ratio = self.deletes.len() as f64 / self.synced_in_period as f64;
+        if ratio > guard.max_ratio {
+            bail!(
+                "refusing to delete {} of {} synced events ({:.0}% > {:.0}%); Garoon may have returned an incomplete result",
+                self.deletes.len(),
+                self.synced_in_period,
+                ratio * 100.0,
+                guard.max_ratio * 100.0,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// 認証の不具合などでGaroonから空の結果が返った場合に、転写先の予定をまとめて消さないための閾値
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeletionGuard {
+    /// 取得期間内の同期済みの予定のうち、削除してよい割合
+    pub(crate) max_ratio: f64,
+    /// この件数未満の削除は割合によらず許可する
+    pub(crate) min_count: usize,
+}
+impl DeletionGuard {
+    pub fn new(max_ratio: f64, min_count: usize) -> Self {
+        Self { max_ratio, min_count }
+    }
+}
+impl Default for DeletionGuard {
+    fn default() -> Self {
+        Self::new(0.5, 3)
+    }
+}
+
+#[derive(Debug)]
+pub struct SyncUpdate {
+    pub(crate) event: Event,
+    pub(crate) record: SyncRecord,
+}
+impl SyncUpdate {
+    /// 前回同期した時点の内容から変わった項目
+    pub fn changes(&self) -> Vec<FieldChange> {
+        let attendees: Vec<String> = self.event.attendees.iter().map(|attendee| attendee.display_name.clone()).collect();
+        let candidates = [
+            FieldChange::new("title", FieldValue::Text(self.record.title.clone()), FieldValue::Text(self.event.title.as_str())),
+            FieldChange::new(
+                "time",
+                match self.record.time() {
+                    Some(time) => FieldValue::Time(time, self.record.time_zones()),
+                    None => FieldValue::Text(String::new()),
+                },
+                FieldValue::Time(self.event.time.clone(), self.event.time_zones),
+            ),
+            FieldChange::new(
+                "time_zone",
+                FieldValue::Text(self.record.time_zones().to_string()),
+                FieldValue::Text(self.event.time_zones.to_string()),
+            ),
+            FieldChange::new("attendees", FieldValue::List(self.record.attendees.clone()), FieldValue::List(attendees)),
+            FieldChange::new(
+                "description",
+                FieldValue::Text(self.record.description.clone().unwrap_or_default()),
+                FieldValue::Text(self.event.description.clone().unwrap_or_default()),
+            ),
+            FieldChange::new(
+                "location",
+                FieldValue::Text(self.record.location.clone().unwrap_or_default()),
+                FieldValue::Text(self.event.location.clone().unwrap_or_default()),
+            ),
+            FieldChange::new("private", FieldValue::Flag(self.record.private), FieldValue::Flag(self.event.private)),
+        ];
+
+        candidates.into_iter().filter(|change| change.before != change.after).collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldChange {
+    pub(crate) field: &'static str,
+    pub(crate) before: FieldValue,
+    pub(crate) after: FieldValue,
+}
+impl FieldChange {
+    pub fn new(field: &'static str, before: FieldValue, after: FieldValue) -> Self {
+        Self { field, before, after }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldValue {
+    Text(String),
+    Time(EventTime, EventTimeZones),
+    List(Vec<String>),
+    Flag(bool),
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use crate::error::ParseError;
+    use crate::models::event::{Event, EventId, FetchedEvents, SkippedEvent, Title};
+    use crate::models::recurrence::{DayOfMonth, Frequency, Recurrence};
+    use crate::utils::date_range::DateRange;
+    use crate::models::sync_plan::{DeletionGuard, FieldChange, FieldValue, SyncPlan};
+    use crate::models::sync_state::{SyncRecord, SyncState};
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn event(id: &str, title: &str) -> Event {
+        Event::new(
+            EventId::new(id.to_string()),
+            Title::new(title.to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+    }
+
+    fn period() -> DateTimeRange {
+        DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 8, 0, 0, 0).unwrap(),
+        )
+    }
+
+    fn synced_state(count: usize) -> SyncState {
+        let mut state = SyncState::default();
+        for i in 0..count {
+            state.upsert(SyncRecord::new(&event(&i.to_string(), "会議"), format!("google-{}", i)));
+        }
+
+        state
+    }
+
+    #[test]
+    fn test_new() {
+        let mut state = SyncState::default();
+        state.upsert(SyncRecord::new(&event("2", "打ち合わせ"), "google-2".to_string()));
+        state.upsert(SyncRecord::new(&event("3", "定例"), "google-3".to_string()));
+
+        let plan = SyncPlan::new(
+            vec![event("1", "会議"), event("2", "打ち合わせ(変更)"), event("3", "定例")],
+            &state,
+            &period(),
+        );
+
+        assert_eq!(plan.creates.len(), 1);
+        assert_eq!(plan.creates[0].id, EventId::new("1".to_string()));
+        assert_eq!(plan.updates.len(), 1);
+        assert_eq!(plan.updates[0].event.id, EventId::new("2".to_string()));
+        assert_eq!(plan.updates[0].record.google_event_id, "google-2".to_string());
+        assert!(plan.deletes.is_empty());
+        assert_eq!(plan.unchanged, 1);
+        assert!(!plan.is_empty());
+    }
+
+    #[test]
+    fn test_changes() {
+        let mut state = SyncState::default();
+        state.upsert(SyncRecord::new(&event("1", "打ち合わせ"), "google-1".to_string()));
+
+        let plan = SyncPlan::new(vec![event("1", "打ち合わせ(変更)")], &state, &period());
+
+        assert_eq!(plan.updates[0].changes(), vec![
+            FieldChange::new(
+                "title",
+                FieldValue::Text("打ち合わせ".to_string()),
+                FieldValue::Text("打ち合わせ(変更)".to_string()),
+            ),
+        ]);
+    }
+
+    #[test]
+    fn test_new_削除された予定() {
+        let mut state = SyncState::default();
+        state.upsert(SyncRecord::new(&event("1", "会議"), "google-1".to_string()));
+        state.upsert(SyncRecord::new(&event("2", "打ち合わせ"), "google-2".to_string()));
+
+        let plan = SyncPlan::new(vec![event("1", "会議")], &state, &period());
+
+        assert!(plan.creates.is_empty());
+        assert!(plan.updates.is_empty());
+        assert_eq!(plan.deletes.len(), 1);
+        assert_eq!(plan.deletes[0].google_event_id, "google-2".to_string());
+        assert_eq!(plan.synced_in_period, 2);
+    }
+
+    #[test]
+    fn test_new_取得期間外の予定は削除しない() {
+        let state = synced_state(2);
+        let next_week = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 1, 8, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 15, 0, 0, 0).unwrap(),
+        );
+
+        let plan = SyncPlan::new(vec![], &state, &next_week);
+
+        assert!(plan.deletes.is_empty());
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn test_new_期間を記録していない予定は削除しない() {
+        let mut state = synced_state(2);
+        let mut record = state.get(&EventId::new("1".to_string())).unwrap().clone();
+        record.duration = None;
+        state.upsert(record);
+
+        let plan = SyncPlan::new(vec![], &state, &period());
+
+        assert_eq!(plan.deletes.len(), 1);
+        assert_eq!(plan.deletes[0].garoon_id, "0");
+        assert_eq!(plan.synced_in_period, 1);
+    }
+
+    #[test]
+    fn test_refresh_期間を記録していない予定を記録し直す() {
+        // Setup: 期間を記録する前の同期状態
+        let mut state = synced_state(2);
+        let mut record = state.get(&EventId::new("1".to_string())).unwrap().clone();
+        record.duration = None;
+        record.time = None;
+        record.time_zones = None;
+        state.upsert(record);
+        let events = vec![event("0", "会議"), event("1", "会議")];
+
+        // Exercise
+        let plan = SyncPlan::new(events, &state, &period());
+        plan.refresh(&mut state);
+
+        // Asserts: 変更はないが、記録し直した予定は次の実行で削除できる
+        assert!(plan.is_empty());
+        assert_eq!(plan.unchanged, 2);
+        assert_eq!(plan.refreshes.len(), 1);
+        assert_eq!(plan.refreshes[0].google_event_id, "google-1");
+        let plan = SyncPlan::new(vec![event("0", "会議")], &state, &period());
+        assert_eq!(plan.deletes.len(), 1);
+        assert_eq!(plan.deletes[0].garoon_id, "1");
+        assert!(plan.refreshes.is_empty());
+    }
+
+    #[test]
+    fn test_new_期間内に回のない繰り返し予定は削除しない() {
+        // 2021年の毎月15日の繰り返し予定
+        let recurrence = Recurrence::new(
+            Frequency::MonthlyByDate(DayOfMonth::Day(15)),
+            DateRange::new(NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2021, 12, 31).unwrap()),
+            chrono_tz::UTC,
+        );
+        let series = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("月次報告".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 15, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 15, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+            .with_recurrence(Some(recurrence));
+        let mut state = SyncState::default();
+        state.upsert(SyncRecord::new(&series, "google-1".to_string()));
+
+        // 3月15日を含まない1週間
+        let plan = SyncPlan::new(vec![], &state, &DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 3, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 8, 0, 0, 0).unwrap(),
+        ));
+        assert!(plan.deletes.is_empty());
+        assert_eq!(plan.synced_in_period, 0);
+
+        // 3月15日を含む1週間に回がなければ、Garoonで削除されている
+        let plan = SyncPlan::new(vec![], &state, &DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 3, 12, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 19, 0, 0, 0).unwrap(),
+        ));
+        assert_eq!(plan.deletes.len(), 1);
+    }
+
+    #[test]
+    fn test_refresh_規則を記録していない繰り返し予定を記録し直す() {
+        // Setup: 規則を記録する前の同期状態にある、毎月15日の繰り返し予定
+        let recurrence = Recurrence::new(
+            Frequency::MonthlyByDate(DayOfMonth::Day(15)),
+            DateRange::new(NaiveDate::from_ymd_opt(2021, 1, 1).unwrap(), NaiveDate::from_ymd_opt(2021, 12, 31).unwrap()),
+            chrono_tz::UTC,
+        );
+        let series = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("月次報告".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 15, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 15, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+            .with_recurrence(Some(recurrence));
+        let mut record = SyncRecord::new(&series, "google-1".to_string());
+        record.recurrence = None;
+        let mut state = SyncState::default();
+        state.upsert(record);
+        // 3月15日を含む1週間
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 3, 12, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 3, 19, 0, 0, 0).unwrap(),
+        );
+
+        // Exercise
+        let plan = SyncPlan::new(vec![series], &state, &period);
+        plan.refresh(&mut state);
+
+        // Asserts: 記録し直した後は、期間内に回がなければGaroonで削除されたとみなす
+        assert_eq!(plan.refreshes.len(), 1);
+        assert!(plan.refreshes[0].recurrence.is_some());
+        let plan = SyncPlan::new(vec![], &state, &period);
+        assert_eq!(plan.deletes.len(), 1);
+    }
+
+    #[test]
+    fn test_check_deletions() {
+        let guard = DeletionGuard::new(0.5, 3);
+
+        // 10件中5件の削除は許可する
+        let events: Vec<Event> = (5..10).map(|i| event(&i.to_string(), "会議")).collect();
+        let plan = SyncPlan::new(events, &synced_state(10), &period());
+        assert!(plan.check_deletions(&guard).is_ok());
+
+        // Garoonから空の結果が返り、全件が削除対象になった場合は中止する
+        let plan = SyncPlan::new(vec![], &synced_state(10), &period());
+        assert!(plan.check_deletions(&guard).is_err());
+
+        // 閾値の件数未満なら割合によらず許可する
+        let plan = SyncPlan::new(vec![], &synced_state(2), &period());
+        assert!(plan.check_deletions(&guard).is_ok());
+    }
+
+    #[test]
+    fn test_new_変換できなかった予定は削除しない() {
+        let mut state = SyncState::default();
+        state.upsert(SyncRecord::new(&event("1", "会議"), "google-1".to_string()));
+        // 回ごとに記録した繰り返し予定
+        state.upsert(SyncRecord::new(&event("1/20210101T000000Z", "会議"), "outlook-1".to_string()));
+        let fetched = FetchedEvents {
+            events: vec![],
+            skipped: vec![SkippedEvent {
+                id: EventId::new("1".to_string()),
+                error: ParseError::TimeZone("Mars/Olympus".to_string()),
+            }],
+        };
+
+        let plan = SyncPlan::new(fetched, &state, &period());
+
+        assert!(plan.deletes.is_empty());
+        assert_eq!(plan.skipped.len(), 1);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::event::{Event, EventId, EventTime, EventTimeZones};
+use crate::models::recurrence::Recurrence;
+use crate::utils::date_time_range::DateTimeRange;
+
+/// 前回までに同期した予定の記録
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SyncState {
+    records: BTreeMap<String, SyncRecord>,
+}
+impl SyncState {
+    pub fn get(&self, id: &EventId) -> Option<&SyncRecord> {
+        self.records.get(id.as_str())
+    }
+
+    pub fn upsert(&mut self, record: SyncRecord) {
+        self.records.insert(record.garoon_id.clone(), record);
+    }
+
+    pub fn remove(&mut self, id: &EventId) -> Option<SyncRecord> {
+        self.records.remove(id.as_str())
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &SyncRecord> {
+        self.records.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+/// Garoonの予定と、それを転写した先の予定の対応
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SyncRecord {
+    pub(crate) garoon_id: String,
+    pub(crate) google_event_id: String,
+    pub(crate) content_hash: String,
+    /// 予定が占める期間。取得期間内の予定かを判定するために使う。
+    /// 期間を記録する前の同期状態にはないので、次に記録し直すまでは削除の対象にしない
+    #[serde(default)]
+    pub(crate) duration: Option<DateTimeRange>,
+    /// 差分表示のために、同期した時点の内容も残す
+    #[serde(default)]
+    pub(crate) time: Option<EventTime>,
+    #[serde(default)]
+    pub(crate) time_zones: Option<EventTimeZones>,
+    /// 繰り返し予定の規則。取得期間内に回があるかを判定するために使う
+    #[serde(default)]
+    pub(crate) recurrence: Option<Recurrence>,
+    #[serde(default)]
+    pub(crate) title: String,
+    #[serde(default)]
+    pub(crate) attendees: Vec<String>,
+    #[serde(default)]
+    pub(crate) description: Option<String>,
+    #[serde(default)]
+    pub(crate) location: Option<String>,
+    #[serde(default)]
+    pub(crate) private: bool,
+}
+impl SyncRecord {
+    pub fn new(event: &Event, google_event_id: String) -> Self {
+        Self {
+            garoon_id: event.id.as_str().to_string(),
+            google_event_id,
+            content_hash: event.content_hash(),
+            duration: Some(event.span()),
+            time: Some(event.time.clone()),
+            time_zones: Some(event.time_zones),
+            recurrence: event.recurrence.clone(),
+            title: event.title.as_str(),
+            attendees: event.attendees.iter().map(|attendee| attendee.display_name.clone()).collect(),
+            description: event.description.clone(),
+            location: event.location.clone(),
+            private: event.private,
+        }
+    }
+
+    pub fn garoon_id(&self) -> EventId {
+        EventId::new(self.garoon_id.clone())
+    }
+
+    /// 同期した時点の予定の日時
+    pub fn time(&self) -> Option<EventTime> {
+        self.time.clone().or_else(|| self.duration.clone().map(EventTime::Timed))
+    }
+
+    pub fn time_zones(&self) -> EventTimeZones {
+        self.time_zones.unwrap_or_default()
+    }
+
+    /// 取得期間内の予定か。繰り返し予定は、期間内に回がある場合だけ期間内とみなす。
+    /// Garoonは期間内の回しか返さないので、回のない繰り返し予定を期間内とみなすと削除されたと誤ってしまう
+    pub fn overlaps(&self, period: &DateTimeRange) -> bool {
+        let Some(duration) = &self.duration else {
+            return false;
+        };
+        if !period.overlaps(duration) {
+            return false;
+        }
+
+        match (&self.recurrence, self.time()) {
+            (Some(recurrence), Some(time)) => recurrence.occurs_within(&time, period),
+            // 規則を記録する前の繰り返し予定は、回が期間内にあるか分からないので、次に記録し直すまでは期間外とみなす
+            (None, Some(time)) => time.span(&self.time_zones().start) == *duration,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::models::event::{Event, EventId, Title};
+    use crate::models::sync_state::{SyncRecord, SyncState};
+    use crate::utils::date_time_range::DateTimeRange;
+
+    #[test]
+    fn test_upsert_and_remove() {
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("会議".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        );
+        let mut state = SyncState::default();
+
+        state.upsert(SyncRecord::new(&event, "google-1".to_string()));
+        state.upsert(SyncRecord::new(&event, "google-2".to_string()));
+        assert_eq!(state.len(), 1);
+        assert_eq!(state.get(&event.id).unwrap().google_event_id, "google-2".to_string());
+        assert_eq!(state.get(&event.id).unwrap().content_hash, event.content_hash());
+
+        let removed = state.remove(&event.id);
+        assert_eq!(removed.unwrap().garoon_id, "1".to_string());
+        assert!(state.is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_期間を記録する前の同期状態() {
+        let json = r#"{"records":{"1":{"garoon_id":"1","google_event_id":"google-1","content_hash":"abc"}}}"#;
+
+        let state: SyncState = serde_json::from_str(json).unwrap();
+
+        let record = state.get(&EventId::new("1".to_string())).unwrap();
+        assert_eq!(record.google_event_id, "google-1");
+        assert_eq!(record.duration, None);
+        assert_eq!(record.time(), None);
+    }
+}
@@ -1,7 +1,145 @@
-use crate::models::event::Event;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
 
-pub fn print_results(events: &Vec<Event>) {
+use crate::models::event::{Event, EventTime, EventTimeZones, SkippedEvent};
+use crate::models::sync_plan::{FieldValue, SyncPlan};
+use crate::utils::time_zone::localize;
+
+/// `time_zone`を指定しない場合、予定の日時はそれぞれのタイムゾーンで表示する
+pub fn print_results(events: &Vec<Event>, time_zone: Option<Tz>) {
     for event in events {
-        println!("{:?}", event);
+        println!("{}", format_event(event, time_zone.as_ref()));
+    }
+}
+
+/// 変換できずに読み飛ばした予定を警告として出力する
+pub fn print_skipped(skipped: &Vec<SkippedEvent>) {
+    for skipped in skipped {
+        eprintln!("{}", format_skipped(skipped));
+    }
+}
+
+pub fn print_plan(plan: &SyncPlan, time_zone: Option<Tz>) {
+    print!("{}", render_plan(plan, time_zone.as_ref()));
+}
+
+fn render_plan(plan: &SyncPlan, time_zone: Option<&Tz>) -> String {
+    let mut lines = vec![];
+
+    for event in &plan.creates {
+        lines.push(format!("+ {}", format_event(event, time_zone)));
+    }
+    for update in &plan.updates {
+        lines.push(format!("~ {}", format_event(&update.event, time_zone)));
+        for change in update.changes() {
+            lines.push(format!(
+                "    {}: {} -> {}",
+                change.field,
+                format_value(&change.before, time_zone),
+                format_value(&change.after, time_zone),
+            ));
+        }
+    }
+    for record in &plan.deletes {
+        let time = record.time().map(|time| format_time(&time, &record.time_zones(), time_zone)).unwrap_or_default();
+        lines.push(format!("- {} {}", time, record.title));
+    }
+    for skipped in &plan.skipped {
+        lines.push(format!("! {}", format_skipped(skipped)));
+    }
+
+    lines.push(format!(
+        "作成: {}件, 更新: {}件, 削除: {}件, 変更なし: {}件",
+        plan.creates.len(),
+        plan.updates.len(),
+        plan.deletes.len(),
+        plan.unchanged,
+    ));
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn format_skipped(skipped: &SkippedEvent) -> String {
+    format!("skipped Garoon event {}: {}", skipped.id.as_str(), skipped.error)
+}
+
+fn format_event(event: &Event, time_zone: Option<&Tz>) -> String {
+    let mut line = format!("{} {}", format_time(&event.time, &event.time_zones, time_zone), event.title.as_str());
+    if !event.attendees.is_empty() {
+        let names: Vec<&str> = event.attendees.iter().map(|attendee| attendee.display_name.as_str()).collect();
+        line.push_str(&format!(" ({})", names.join(", ")));
+    }
+
+    line
+}
+
+fn format_time(time: &EventTime, time_zones: &EventTimeZones, time_zone: Option<&Tz>) -> String {
+    match time {
+        EventTime::Timed(range) => format!(
+            "{} - {}",
+            format_date_time(&range.start, &time_zones.start, time_zone),
+            format_date_time(&range.end, &time_zones.end, time_zone),
+        ),
+        EventTime::AllDay(dates) if dates.start == dates.end => format!("{} (終日)", dates.start),
+        EventTime::AllDay(dates) => format!("{} - {} (終日)", dates.start, dates.end),
+        EventTime::StartOnly { start } => format!("{} -", format_date_time(start, &time_zones.start, time_zone)),
+    }
+}
+
+fn format_value(value: &FieldValue, time_zone: Option<&Tz>) -> String {
+    match value {
+        FieldValue::Text(text) => format!("{:?}", text),
+        FieldValue::Time(time, time_zones) => format_time(time, time_zones, time_zone),
+        FieldValue::List(items) => format!("[{}]", items.join(", ")),
+        FieldValue::Flag(flag) => flag.to_string(),
+    }
+}
+
+fn format_date_time(date_time: &DateTime<Utc>, event_time_zone: &Tz, time_zone: Option<&Tz>) -> String {
+    localize(date_time, event_time_zone, time_zone).format("%Y-%m-%d %H:%M").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::models::event::{Attendee, Event, EventId, Title};
+    use crate::models::sync_plan::SyncPlan;
+    use crate::models::sync_state::{SyncRecord, SyncState};
+    use crate::output::render_plan;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn event(id: &str, title: &str, hour: u32) -> Event {
+        Event::new(
+            EventId::new(id.to_string()),
+            Title::new(title.to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, hour, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, hour + 1, 0, 0).unwrap(),
+            ),
+            vec![Attendee::new("user1".to_string())],
+        )
+    }
+
+    #[test]
+    fn test_render_plan() {
+        let mut state = SyncState::default();
+        state.upsert(SyncRecord::new(&event("2", "打ち合わせ", 1), "google-2".to_string()));
+        state.upsert(SyncRecord::new(&event("3", "定例", 2), "google-3".to_string()));
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 8, 0, 0, 0).unwrap(),
+        );
+
+        let plan = SyncPlan::new(vec![event("1", "会議", 0), event("2", "打ち合わせ", 3)], &state, &period);
+
+        assert_eq!(
+            render_plan(&plan, Some(&chrono_tz::Asia::Tokyo)),
+            "+ 2021-01-01 09:00 - 2021-01-01 10:00 会議 (user1)\n\
+            ~ 2021-01-01 12:00 - 2021-01-01 13:00 打ち合わせ (user1)\n    \
+            time: 2021-01-01 10:00 - 2021-01-01 11:00 -> 2021-01-01 12:00 - 2021-01-01 13:00\n\
+            - 2021-01-01 11:00 - 2021-01-01 12:00 定例\n\
+            作成: 1件, 更新: 1件, 削除: 1件, 変更なし: 0件\n"
+        );
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,2 @@
+pub mod sync_state_repository;
+pub mod token_repository;
\ No newline at end of file
@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(test)]
+use mockall::automock;
+
+use crate::models::sync_state::SyncState;
+
+#[cfg_attr(test, automock)]
+pub trait SyncStateRepository {
+    fn load(&self) -> Result<SyncState, anyhow::Error>;
+    fn save(&self, state: &SyncState) -> Result<(), anyhow::Error>;
+}
+
+/// 同期状態をJSONファイルに保存する
+pub struct JsonSyncStateRepository {
+    path: PathBuf,
+}
+impl JsonSyncStateRepository {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+impl SyncStateRepository for JsonSyncStateRepository {
+    fn load(&self) -> Result<SyncState, anyhow::Error> {
+        if !self.path.exists() {
+            return Ok(SyncState::default());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save(&self, state: &SyncState) -> Result<(), anyhow::Error> {
+        // 書き込み途中で落ちても前回の状態が壊れないよう、一時ファイルに書いてから置き換える
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(state)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::models::event::{Event, EventId, Title};
+    use crate::models::sync_state::{SyncRecord, SyncState};
+    use crate::repositories::sync_state_repository::{JsonSyncStateRepository, SyncStateRepository};
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("tensha_{}_{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_load_ファイルなし() {
+        let repository = JsonSyncStateRepository::new(temp_path("not_found"));
+
+        let state = repository.load().unwrap();
+
+        assert!(state.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        // Setup: 同期済みの予定を1件持つ状態を作成する
+        let path = temp_path("save_and_load");
+        let repository = JsonSyncStateRepository::new(&path);
+        let event = Event::new(
+            EventId::new("1".to_string()),
+            Title::new("会議".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        );
+        let mut state = SyncState::default();
+        state.upsert(SyncRecord::new(&event, "google-1".to_string()));
+
+        // Exercise: 保存してから読み込む
+        repository.save(&state).unwrap();
+        let loaded = repository.load();
+        fs::remove_file(&path).unwrap();
+
+        // Asserts
+        assert_eq!(loaded.unwrap(), state);
+    }
+}
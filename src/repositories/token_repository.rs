@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::PathBuf;
+
+#[cfg(test)]
+use mockall::automock;
+
+use crate::models::oauth_token::OAuthToken;
+
+#[cfg_attr(test, automock)]
+pub trait TokenRepository {
+    fn load(&self) -> Result<Option<OAuthToken>, anyhow::Error>;
+    fn save(&self, token: &OAuthToken) -> Result<(), anyhow::Error>;
+}
+
+/// OAuth 2.0のトークンをJSONファイルに保存する
+pub struct JsonTokenRepository {
+    path: PathBuf,
+}
+impl JsonTokenRepository {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+impl TokenRepository for JsonTokenRepository {
+    fn load(&self) -> Result<Option<OAuthToken>, anyhow::Error> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    fn save(&self, token: &OAuthToken) -> Result<(), anyhow::Error> {
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(token)?)?;
+        // リフレッシュトークンはパスワードと同等なので、所有者以外は読めないようにする
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tmp_path, fs::Permissions::from_mode(0o600))?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::models::oauth_token::OAuthToken;
+    use crate::repositories::token_repository::{JsonTokenRepository, TokenRepository};
+
+    #[test]
+    fn test_save_and_load() {
+        // Setup
+        let path = std::env::temp_dir().join(format!("tensha_token_{}.json", std::process::id()));
+        let repository = JsonTokenRepository::new(&path);
+        let token = OAuthToken::new("access".to_string(), Some("refresh".to_string()), None);
+
+        // Exercise: 保存前と保存後に読み込む
+        let before = repository.load().unwrap();
+        repository.save(&token).unwrap();
+        let after = repository.load();
+        fs::remove_file(&path).unwrap();
+
+        // Asserts
+        assert_eq!(before, None);
+        assert_eq!(after.unwrap(), Some(token));
+    }
+}
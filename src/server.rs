@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::header::{HeaderValue, ALLOW, CACHE_CONTROL, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Response, StatusCode, Uri};
+use hyper_util::rt::TokioIo;
+use reqwest::Url;
+use tokio::net::TcpListener;
+
+use crate::error::Error;
+use crate::services::ics_feed_service::{FeedSource, IcsFeed};
+
+/// URLに含めるトークンの最短の長さ。推測されにくいよう、ある程度の長さを求める
+pub const MIN_TOKEN_LENGTH: usize = 16;
+
+/// プロファイルごとの予定を`/{プロファイル名}.ics?token={トークン}`で配信するHTTPサーバー
+pub struct FeedServer<S: FeedSource> {
+    feeds: BTreeMap<String, IcsFeed<S>>,
+    token: String,
+}
+
+impl<S: FeedSource + 'static> FeedServer<S> {
+    pub fn new(token: String) -> Result<Self, Error> {
+        if token.chars().count() < MIN_TOKEN_LENGTH {
+            return Err(Error::Config(format!("the serve token must be at least {} characters", MIN_TOKEN_LENGTH)));
+        }
+
+        Ok(Self { feeds: BTreeMap::new(), token })
+    }
+
+    pub fn with_feed(mut self, name: impl Into<String>, feed: IcsFeed<S>) -> Self {
+        self.feeds.insert(name.into(), feed);
+        self
+    }
+
+    /// 配信する予定のパス。トークンは含まない
+    pub fn paths(&self) -> Vec<String> {
+        self.feeds.keys().map(|name| format!("/{}.ics", name)).collect()
+    }
+
+    /// 接続ごとにタスクを起こして応答する。受け付けに失敗しても警告を出して待ち受けを続ける
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> Result<(), anyhow::Error> {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("warning: failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            let server = self.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.respond(request.method(), request.uri()).await) }
+                });
+                if let Err(e) = http1::Builder::new().serve_connection(TokioIo::new(stream), service).await {
+                    eprintln!("warning: failed to serve a connection: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn respond(&self, method: &Method, uri: &Uri) -> Response<Full<Bytes>> {
+        if method != Method::GET && method != Method::HEAD {
+            let mut response = text(StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+            response.headers_mut().insert(ALLOW, HeaderValue::from_static("GET, HEAD"));
+            return response;
+        }
+        // プロファイル名を推測されないよう、トークンを先に確かめる
+        if !self.authorized(uri) {
+            return text(StatusCode::FORBIDDEN, "invalid token");
+        }
+        let Some(feed) = uri.path().strip_prefix('/').and_then(|path| path.strip_suffix(".ics")).and_then(|name| self.feeds.get(name)) else {
+            return text(StatusCode::NOT_FOUND, "not found");
+        };
+
+        match feed.body().await {
+            Ok(body) => {
+                let body = if method == Method::HEAD { String::new() } else { body };
+                let mut response = Response::new(Full::new(Bytes::from(body)));
+                response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/calendar; charset=utf-8"));
+                response.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+                response
+            }
+            Err(e) => {
+                eprintln!("warning: failed to fetch events for {}: {}", uri.path(), e);
+                text(StatusCode::BAD_GATEWAY, "failed to fetch events")
+            }
+        }
+    }
+
+    fn authorized(&self, uri: &Uri) -> bool {
+        // 相対URIのクエリ文字列をデコードするために、仮のオリジンを付ける
+        let Ok(url) = Url::parse(&format!("http://localhost{}", uri)) else {
+            return false;
+        };
+
+        url.query_pairs().any(|(key, value)| key == "token" && constant_time_eq(value.as_bytes(), self.token.as_bytes()))
+    }
+}
+
+fn text(status: StatusCode, body: &'static str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from_static(body.as_bytes())));
+    *response.status_mut() = status;
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; charset=utf-8"));
+    response
+}
+
+/// 一致するまでの長さから推測されないよう、全てのバイトを比較する
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (x, y)| difference | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use chrono::{TimeZone, Utc};
+    use reqwest::StatusCode;
+    use tokio::net::TcpListener;
+
+    use crate::models::event::{Event, EventId, FetchedEvents, Title};
+    use crate::server::*;
+    use crate::services::ics_feed_service::MockFeedSource;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    /// `work`プロファイルの予定を配信するサーバーを起動し、そのURLを返す
+    async fn start_server() -> String {
+        let mut source = MockFeedSource::new();
+        source.expect_fetch().returning(|| {
+            Ok(FetchedEvents::from(vec![Event::new(
+                EventId::new("1".to_string()),
+                Title::new("会議".to_string()),
+                DateTimeRange::new(
+                    Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2024, 5, 10, 1, 0, 0).unwrap(),
+                ),
+                vec![],
+            )]))
+        });
+        let server = FeedServer::new(TOKEN.to_string()).unwrap().with_feed("work", IcsFeed::new(source, Duration::from_secs(60)));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(Arc::new(server).serve(listener));
+
+        url
+    }
+
+    #[tokio::test]
+    async fn serve_予定を配信する() {
+        // Setup
+        let url = start_server().await;
+
+        // Exercise
+        let response = reqwest::get(format!("{}/work.ics?token={}", url, TOKEN)).await.unwrap();
+
+        // Asserts
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/calendar; charset=utf-8");
+        let body = response.text().await.unwrap();
+        assert!(body.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(body.contains("SUMMARY:会議\r\n"));
+    }
+
+    #[tokio::test]
+    async fn serve_トークンが違う() {
+        let url = start_server().await;
+
+        let get = |path: String| reqwest::get(format!("{}{}", url, path));
+
+        assert_eq!(get("/work.ics".to_string()).await.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(get("/work.ics?token=wrong".to_string()).await.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(get("/private.ics?token=wrong".to_string()).await.unwrap().status(), StatusCode::FORBIDDEN);
+        assert_eq!(get(format!("/private.ics?token={}", TOKEN)).await.unwrap().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_new_短いトークン() {
+        assert!(FeedServer::<MockFeedSource>::new("short".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token1"));
+    }
+}
@@ -1,106 +1,181 @@
 use anyhow::Result;
 
-use crate::apis::garoon::{GaroonGetEventsClient, GaroonGetEventsRequest};
-use crate::models::event::Event;
+use crate::models::event::FetchedEvents;
+use crate::models::event_filter::EventFilter;
+use crate::sinks::event_sink::{write_all, EventSink, SinkOutcome};
+use crate::sources::event_source::{fetch_all, EventSource};
 use crate::utils::date_time_range::DateTimeRange;
 
-pub struct CalendarSyncService<G: GaroonGetEventsClient> {
-    client: G,
+pub struct CalendarSyncService {
+    sources: Vec<Box<dyn EventSource>>,
+    sinks: Vec<Box<dyn EventSink>>,
+    fetch_range: DateTimeRange,
+    filter: EventFilter,
 }
-impl<G> CalendarSyncService<G> where G: GaroonGetEventsClient + Send + Sync {
-    pub fn new(client: G) -> Self {
-        CalendarSyncService { client }
+impl CalendarSyncService {
+    /// 複数の取得元を指定した場合は、それぞれの予定をまとめて1つの予定として扱う
+    pub fn new(sources: Vec<Box<dyn EventSource>>) -> Self {
+        CalendarSyncService {
+            sources,
+            sinks: vec![],
+            fetch_range: default_fetch_range(),
+            filter: EventFilter::default(),
+        }
     }
-    
-    pub async fn sync_events(&self) -> Result<(), anyhow::Error> {
-        todo!()
+
+    /// 予定を取得する期間。省略時は現在から1週間
+    pub fn with_fetch_range(mut self, fetch_range: DateTimeRange) -> Self {
+        self.fetch_range = fetch_range;
+        self
     }
-    
-    pub async fn get_garoon_events(&self) -> Result<Vec<Event>, anyhow::Error> {
-        let events = self.client
-            .get(GaroonGetEventsRequest { period: self.fetch_range() })
-            .await?
-            .iter()
-            .map(|garoon_event| Event::from(garoon_event.clone()))
-            .collect();
-        
-        Ok(events)
+
+    /// `sync_events`で反映する転写先
+    pub fn with_sinks(mut self, sinks: Vec<Box<dyn EventSink>>) -> Self {
+        self.sinks = sinks;
+        self
     }
-    
-    fn fetch_range(&self) -> DateTimeRange {
-        DateTimeRange::new(
-            chrono::Utc::now(),
-            chrono::Utc::now() + chrono::Duration::weeks(1),
-        )
+
+    /// 条件に合わない予定は取得しなかったものとして扱い、同期済みであれば転写先から削除する
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn fetch_range(&self) -> &DateTimeRange {
+        &self.fetch_range
+    }
+
+    /// 取得した予定を全ての転写先に反映し、転写先ごとの結果を返す
+    pub async fn sync_events(&self) -> Result<Vec<SinkOutcome>, anyhow::Error> {
+        let fetched = self.get_events().await?;
+
+        Ok(write_all(&self.sinks, fetched, &self.fetch_range).await)
+    }
+
+    /// 変換できない予定があっても全体は中断せず、読み飛ばした予定として返す
+    pub async fn get_events(&self) -> Result<FetchedEvents, anyhow::Error> {
+        let mut fetched = fetch_all(&self.sources, &self.fetch_range).await?;
+        fetched.events.retain(|event| self.filter.matches(event));
+
+        Ok(fetched)
     }
 }
 
+fn default_fetch_range() -> DateTimeRange {
+    let now = chrono::Utc::now();
+    DateTimeRange::new(now, now + chrono::Duration::weeks(1))
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::apis::garoon::{GaroonDateTime, GaroonEvent, MockGaroonGetEventsClient};
-    use crate::models::event::Title;
+    use chrono::{TimeZone, Utc};
+
+    use crate::models::event::{Event, EventId, FetchedEvents, Title};
+    use crate::models::event_filter::EventFilter;
     use crate::services::calendar_sync_service::CalendarSyncService;
+    use crate::sinks::event_sink::{EventSink, MockEventSink, SinkCapabilities, SinkReport};
+    use crate::sources::event_source::{EventSource, MockEventSource};
+    use crate::utils::date_time_range::DateTimeRange;
 
-    #[tokio::test]
-    async fn test_sync_events_正常系() {
-        // Setup: GaroonClientのモックを作成し、get_eventsメソッドの戻り値を設定する
-        let mut garoon_client = MockGaroonGetEventsClient::new();
-        garoon_client.expect_get()
+    fn event(id: &str, title: &str) -> Event {
+        Event::new(
+            EventId::new(id.to_string()),
+            Title::new(title.to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+    }
+
+    /// 指定した予定を1回だけ返す取得元
+    fn source(events: Vec<(&'static str, &'static str)>) -> Box<dyn EventSource> {
+        let mut source = MockEventSource::new();
+        source.expect_name().return_const("garoon".to_string());
+        source.expect_fetch()
             .times(1)
-            .return_once(|_| Ok(vec![
-                GaroonEvent {
-                    subject: "会議".to_string(),
-                    attendees: vec![],
-                    start: GaroonDateTime {
-                        dateTime: "2021-01-01T00:00:00+09:00".to_string(),
-                        timeZone: "Asia/Tokyo".to_string(),
-                    },
-                    end: GaroonDateTime {
-                        dateTime: "2021-01-01T01:00:00+09:00".to_string(),
-                        timeZone: "Asia/Tokyo".to_string(),
-                    },
-                }
-            ]));
-        
-        // Exercise: CalendarSyncServiceを作成し、sync_eventsメソッドを呼び出す
-        let service = CalendarSyncService { client: garoon_client };
+            .return_once(move |_| Ok(FetchedEvents::from(events.into_iter().map(|(id, title)| event(id, title)).collect::<Vec<Event>>())));
+
+        Box::new(source)
+    }
+
+    #[tokio::test]
+    async fn test_sync_events_全ての転写先に反映する() {
+        // Setup: 1回だけ取得し、2つの転写先に反映することを設定する
+        let sinks: Vec<Box<dyn EventSink>> = ["csv", "ics"]
+            .into_iter()
+            .map(|name| {
+                let mut sink = MockEventSink::new();
+                sink.expect_name().return_const(name.to_string());
+                sink.expect_capabilities().return_const(SinkCapabilities::default());
+                sink.expect_write()
+                    .withf(|fetched, _| fetched.events().len() == 1)
+                    .times(1)
+                    .returning(|fetched, _| Ok(SinkReport { written: fetched.events().len(), ..Default::default() }));
+                Box::new(sink) as Box<dyn EventSink>
+            })
+            .collect();
+
+        // Exercise
+        let service = CalendarSyncService::new(vec![source(vec![("1", "会議")])]).with_sinks(sinks);
         let result = service.sync_events().await;
-        
-        // Assert: 戻り値がOkであることを検証する
-        assert!(result.is_ok());
+
+        // Asserts
+        let outcomes = result.unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|outcome| outcome.result.as_ref().unwrap().written == 1));
     }
-    
+
     #[tokio::test]
-    async fn test_get_garoon_events_正常系() {
-        // Setup: GaroonClientのモックを作成し、get_eventsメソッドの戻り値を設定する
-        let mut garoon_client = MockGaroonGetEventsClient::new();
-        garoon_client.expect_get()
+    async fn test_get_events_取得期間を指定() {
+        // Setup: 指定した期間で取得することを設定する
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 2, 1, 0, 0, 0).unwrap(),
+        );
+        let expected = period.clone();
+        let mut source = MockEventSource::new();
+        source.expect_fetch()
+            .withf(move |period| *period == expected)
             .times(1)
-            .return_once(|_| Ok(vec![
-                GaroonEvent {
-                    subject: "会議".to_string(),
-                    attendees: vec![],
-                    start: GaroonDateTime {
-                        dateTime: "2021-01-01T09:00:00+09:00".to_string(),
-                        timeZone: "Asia/Tokyo".to_string(),
-                    },
-                    end: GaroonDateTime {
-                        dateTime: "2021-01-01T10:00:00+09:00".to_string(),
-                        timeZone: "Asia/Tokyo".to_string(),
-                    },
-                }
-            ]));
-        
-        // Exercise: CalendarSyncServiceを作成し、get_garoon_eventsメソッドを呼び出す
-        let service = CalendarSyncService { client: garoon_client };
-        let result = service.get_garoon_events().await;
-        
-        // Assert: 戻り値がOkであり、GaroonEventからEventに変換されていることを検証する
-        assert!(result.is_ok());
-        let events = result.unwrap();
-        assert_eq!(events.len(), 1);
-        assert_eq!(events[0].title, Title::new("会議".to_string()));
-        assert_eq!(events[0].duration.start.to_rfc3339(), "2021-01-01T00:00:00+00:00");
-        assert_eq!(events[0].duration.end.to_rfc3339(), "2021-01-01T01:00:00+00:00");
+            .return_once(|_| Ok(FetchedEvents::from(vec![event("1", "会議")])));
+
+        // Exercise
+        let service = CalendarSyncService::new(vec![Box::new(source)]).with_fetch_range(period);
+        let result = service.get_events().await;
+
+        // Assert
+        assert_eq!(result.unwrap().events().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_events_複数の取得元の予定をまとめる() {
+        // Setup
+        let sources = vec![source(vec![("1", "会議")]), source(vec![("ics:holiday", "祝日"), ("ics:anniversary", "創立記念日")])];
+
+        // Exercise
+        let service = CalendarSyncService::new(sources);
+        let result = service.get_events().await;
+
+        // Assert
+        let ids: Vec<String> = result.unwrap().events().iter().map(|event| event.id.as_str().to_string()).collect();
+        assert_eq!(ids, vec!["1", "ics:holiday", "ics:anniversary"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_events_条件に合わない予定は除く() {
+        // Setup: 除外する件名の予定を含めて返すよう設定する
+        let sources = vec![source(vec![("1", "会議"), ("2", "昼休み")])];
+
+        // Exercise: 件名で除外する条件を指定してget_eventsメソッドを呼び出す
+        let service = CalendarSyncService::new(sources)
+            .with_filter(EventFilter::new(vec!["昼休み".to_string()], false));
+        let result = service.get_events().await;
+
+        // Assert
+        let fetched = result.unwrap();
+        assert_eq!(fetched.events().len(), 1);
+        assert_eq!(fetched.events()[0].id, EventId::new("1".to_string()));
     }
-}
\ No newline at end of file
+}
@@ -1,65 +1,160 @@
 use std::fs;
 use std::path::Path;
 
-use chrono::Local;
-use serde::Serialize;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use csv::{Terminator, WriterBuilder};
+use serde::Deserialize;
 
-use crate::models::event::Event;
+use crate::models::event::{Event, EventTime};
+use crate::utils::time_zone::localize;
 
-#[derive(Debug, Serialize)]
+/// GoogleカレンダーのCSVの列。件名と開始日は必須なので常に出力する
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvColumn {
+    StartTime,
+    EndDate,
+    EndTime,
+    AllDayEvent,
+    Description,
+    Location,
+    Private,
+}
+impl CsvColumn {
+    /// 出力する列を指定しない場合に出力する列
+    pub const ALL: [CsvColumn; 7] = [
+        CsvColumn::StartTime,
+        CsvColumn::EndDate,
+        CsvColumn::EndTime,
+        CsvColumn::AllDayEvent,
+        CsvColumn::Description,
+        CsvColumn::Location,
+        CsvColumn::Private,
+    ];
+
+    fn header(&self) -> &'static str {
+        match self {
+            CsvColumn::StartTime => "Start Time",
+            CsvColumn::EndDate => "End Date",
+            CsvColumn::EndTime => "End Time",
+            CsvColumn::AllDayEvent => "All Day Event",
+            CsvColumn::Description => "Description",
+            CsvColumn::Location => "Location",
+            CsvColumn::Private => "Private",
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct ExportedEvent {
     subject: String,
     start_date: String,
     start_time: String,
     end_date: String,
     end_time: String,
+    all_day_event: String,
+    description: String,
+    location: String,
+    private: String,
 }
+impl ExportedEvent {
+    /// `time_zone`を指定しない場合、予定の日時はそれぞれのタイムゾーンで出力する
+    pub fn new(event: &Event, time_zone: Option<&Tz>) -> Self {
+        let zones = &event.time_zones;
+        let (start_date, start_time, end_date, end_time, all_day) = match &event.time {
+            EventTime::Timed(range) => {
+                let (start_date, start_time) = split_date_time(&range.start, &zones.start, time_zone);
+                let (end_date, end_time) = split_date_time(&range.end, &zones.end, time_zone);
+                (start_date, start_time, end_date, end_time, false)
+            }
+            // GoogleカレンダーのCSVでは、終日予定の終了日は最終日を指定する
+            EventTime::AllDay(dates) => (dates.start.to_string(), String::new(), dates.end.to_string(), String::new(), true),
+            EventTime::StartOnly { start } => {
+                let (start_date, start_time) = split_date_time(start, &zones.start, time_zone);
+                (start_date, start_time, String::new(), String::new(), false)
+            }
+        };
 
-pub fn export(events: &Vec<Event>, path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
-    let csv_content = to_csv(events)?;
+        Self {
+            subject: event.title.as_str(),
+            start_date,
+            start_time,
+            end_date,
+            end_time,
+            all_day_event: bool_value(all_day),
+            description: event.description.clone().unwrap_or_default(),
+            location: event.location.clone().unwrap_or_default(),
+            private: bool_value(event.private),
+        }
+    }
+
+    fn value(&self, column: CsvColumn) -> &str {
+        match column {
+            CsvColumn::StartTime => &self.start_time,
+            CsvColumn::EndDate => &self.end_date,
+            CsvColumn::EndTime => &self.end_time,
+            CsvColumn::AllDayEvent => &self.all_day_event,
+            CsvColumn::Description => &self.description,
+            CsvColumn::Location => &self.location,
+            CsvColumn::Private => &self.private,
+        }
+    }
+}
+
+/// GoogleカレンダーのCSVの真偽値
+fn bool_value(value: bool) -> String {
+    if value { "True" } else { "False" }.to_string()
+}
+
+fn split_date_time(date_time: &DateTime<Utc>, event_time_zone: &Tz, time_zone: Option<&Tz>) -> (String, String) {
+    let local = localize(date_time, event_time_zone, time_zone);
+    (local.date_naive().to_string(), local.time().to_string())
+}
+
+/// `columns`の順に、件名と開始日に続けて出力する
+pub fn export(events: &Vec<Event>, path: impl AsRef<Path>, time_zone: Option<Tz>, columns: &[CsvColumn]) -> Result<(), anyhow::Error> {
+    let csv_content = to_csv(events, time_zone.as_ref(), columns)?;
     fs::write(path, csv_content)?;
 
     Ok(())
 }
 
-fn to_csv(events: &Vec<Event>) -> Result<String, anyhow::Error> {
-    let mut csv = String::new();
-    csv.push_str("Subject,Start Date,Start Time,End Date,End Time\n");
+/// カンマや引用符、改行を含む値は引用符で囲む (RFC 4180)
+fn to_csv(events: &Vec<Event>, time_zone: Option<&Tz>, columns: &[CsvColumn]) -> Result<String, anyhow::Error> {
+    let mut writer = WriterBuilder::new().terminator(Terminator::CRLF).from_writer(vec![]);
 
-    for event in events {
-        let exported_event = ExportedEvent {
-            subject: event.title.as_str(),
-            start_date: event.duration.start.with_timezone(&Local).date_naive().to_string(),
-            start_time: event.duration.start.with_timezone(&Local).time().to_string(),
-            end_date: event.duration.end.with_timezone(&Local).date_naive().to_string(),
-            end_time: event.duration.end.with_timezone(&Local).time().to_string(),
-        };
+    let mut header = vec!["Subject", "Start Date"];
+    header.extend(columns.iter().map(CsvColumn::header));
+    writer.write_record(&header)?;
 
-        csv.push_str(&format!(
-            "{},{},{},{},{}\n",
-            exported_event.subject,
-            exported_event.start_date,
-            exported_event.start_time,
-            exported_event.end_date,
-            exported_event.end_time,
-        ));
+    for event in events {
+        let exported_event = ExportedEvent::new(event, time_zone);
+        let mut record = vec![exported_event.subject.as_str(), exported_event.start_date.as_str()];
+        record.extend(columns.iter().map(|column| exported_event.value(*column)));
+        writer.write_record(&record)?;
     }
 
-    Ok(csv)
+    Ok(String::from_utf8(writer.into_inner()?)?)
 }
 
 #[cfg(test)]
 mod tests {
-    use chrono::TimeZone;
+    use chrono::{NaiveDate, TimeZone};
 
-    use crate::models::event::{Attendee, Event, Title};
-    use crate::services::export_events_service::to_csv;
+    use crate::models::event::{Attendee, Event, EventId, EventTime, EventTimeZones, Title};
+    use crate::utils::date_range::DateRange;
+    use crate::services::export_events_service::{to_csv, CsvColumn};
     use crate::utils::date_time_range::DateTimeRange;
 
+    /// 以前から出力していた列
+    const DATE_TIME_COLUMNS: [CsvColumn; 4] = [CsvColumn::StartTime, CsvColumn::EndDate, CsvColumn::EndTime, CsvColumn::AllDayEvent];
+
     #[test]
     fn test_to_csv() {
         let events = vec![
             Event::new(
+                EventId::new("1".to_string()),
                 Title::new("会議".to_string()),
                 DateTimeRange::new(
                     chrono::Utc.with_ymd_and_hms(2021, 1, 1,0, 0, 0).unwrap(),
@@ -68,6 +163,7 @@ mod tests {
                 vec![Attendee::new("user1".to_string())],
             ),
             Event::new(
+                EventId::new("1".to_string()),
                 Title::new("打ち合わせ".to_string()),
                 DateTimeRange::new(
                     chrono::Utc.with_ymd_and_hms(2021, 1, 2, 15, 0, 0).unwrap(),
@@ -75,15 +171,102 @@ mod tests {
                 ),
                 vec![Attendee::new("user2".to_string())],
             ),
+            Event::new(
+                EventId::new("3".to_string()),
+                Title::new("休暇".to_string()),
+                EventTime::AllDay(DateRange::new(
+                    NaiveDate::from_ymd_opt(2021, 1, 4).unwrap(),
+                    NaiveDate::from_ymd_opt(2021, 1, 5).unwrap(),
+                )),
+                vec![],
+            ),
+            Event::new(
+                EventId::new("4".to_string()),
+                Title::new("締め切り".to_string()),
+                EventTime::StartOnly { start: chrono::Utc.with_ymd_and_hms(2021, 1, 6, 9, 0, 0).unwrap() },
+                vec![],
+            ),
         ];
 
-        let csv = to_csv(&events).unwrap();
+        let csv = to_csv(&events, Some(&chrono_tz::Asia::Tokyo), &DATE_TIME_COLUMNS).unwrap();
 
         assert_eq!(
             csv,
-            "Subject,Start Date,Start Time,End Date,End Time\n\
-            会議,2021-01-01,09:00:00,2021-01-01,10:00:00\n\
-            打ち合わせ,2021-01-03,00:00:00,2021-01-03,01:00:00\n"
+            "Subject,Start Date,Start Time,End Date,End Time,All Day Event\r\n\
+            会議,2021-01-01,09:00:00,2021-01-01,10:00:00,False\r\n\
+            打ち合わせ,2021-01-03,00:00:00,2021-01-03,01:00:00,False\r\n\
+            休暇,2021-01-04,,2021-01-05,,True\r\n\
+            締め切り,2021-01-06,18:00:00,,,False\r\n"
         );
     }
+
+    #[test]
+    fn test_to_csv_予定のタイムゾーン() {
+        // Setup: 東京発・ニューヨーク着の予定を作成する
+        let events = vec![Event::new(
+            EventId::new("1".to_string()),
+            Title::new("移動".to_string()),
+            DateTimeRange::new(
+                chrono::Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+                chrono::Utc.with_ymd_and_hms(2021, 1, 1, 14, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+            .with_time_zones(EventTimeZones::new(chrono_tz::Asia::Tokyo, chrono_tz::America::New_York))];
+
+        // Exercise: 出力タイムゾーンを指定せずにCSVに変換する
+        let csv = to_csv(&events, None, &DATE_TIME_COLUMNS).unwrap();
+
+        // Asserts: 開始・終了はそれぞれのタイムゾーンで出力される
+        assert_eq!(
+            csv,
+            "Subject,Start Date,Start Time,End Date,End Time,All Day Event\r\n\
+            移動,2021-01-01,10:00:00,2021-01-01,09:00:00,False\r\n"
+        );
+    }
+
+    #[test]
+    fn test_to_csv_引用符で囲む() {
+        // Setup: カンマ・引用符・改行を含む予定
+        let events = vec![Event::new(
+            EventId::new("1".to_string()),
+            Title::new("定例会議, 第3回".to_string()),
+            DateTimeRange::new(
+                chrono::Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                chrono::Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+            .with_description(Some("資料は\"共有\"フォルダ\n印刷不要".to_string()))
+            .with_location(Some("会議室A, 会議室B".to_string()))
+            .with_private(true)];
+
+        // Exercise
+        let csv = to_csv(&events, Some(&chrono_tz::Asia::Tokyo), &CsvColumn::ALL).unwrap();
+
+        // Asserts
+        assert_eq!(
+            csv,
+            "Subject,Start Date,Start Time,End Date,End Time,All Day Event,Description,Location,Private\r\n\
+            \"定例会議, 第3回\",2021-01-01,09:00:00,2021-01-01,10:00:00,False,\"資料は\"\"共有\"\"フォルダ\n印刷不要\",\"会議室A, 会議室B\",True\r\n"
+        );
+    }
+
+    #[test]
+    fn test_to_csv_列を選ぶ() {
+        let events = vec![Event::new(
+            EventId::new("1".to_string()),
+            Title::new("会議".to_string()),
+            DateTimeRange::new(
+                chrono::Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+                chrono::Utc.with_ymd_and_hms(2021, 1, 1, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+            .with_location(Some("会議室A".to_string()))];
+
+        let csv = to_csv(&events, Some(&chrono_tz::Asia::Tokyo), &[CsvColumn::Location, CsvColumn::StartTime]).unwrap();
+
+        assert_eq!(csv, "Subject,Start Date,Location,Start Time\r\n会議,2021-01-01,会議室A,09:00:00\r\n");
+    }
 }
@@ -0,0 +1,385 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, NaiveDate, Offset, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+
+use crate::models::event::{Event, EventTime};
+
+/// 取得元を表すPRODID
+const PRODUCT_ID: &str = "-//tensha//Garoon export//JA";
+/// UIDのドメイン部分。Garoonの予定IDと組み合わせ、再出力しても同じUIDになるようにする
+const UID_DOMAIN: &str = "tensha.garoon";
+/// 折り返す前の1行の最大オクテット数 (RFC 5545 3.1)
+const MAX_LINE_OCTETS: usize = 75;
+
+/// RFC 5545のiCalendar形式で出力する。`now`はDTSTAMPに使う
+pub fn export_ics(events: &[Event], path: impl AsRef<Path>, now: DateTime<Utc>) -> Result<(), anyhow::Error> {
+    fs::write(path, to_ics(events, now))?;
+
+    Ok(())
+}
+
+pub fn to_ics(events: &[Event], now: DateTime<Utc>) -> String {
+    calendar(events, now, Some("PUBLISH"))
+}
+
+/// CalDAVサーバーに置く1件分のiCalendar。カレンダーオブジェクトリソースにはMETHODを含められない (RFC 4791 4.1)
+pub fn to_calendar_object(event: &Event, now: DateTime<Utc>) -> String {
+    calendar(std::slice::from_ref(event), now, None)
+}
+
+/// 再出力しても変わらないUID
+pub fn uid(event: &Event) -> String {
+    format!("garoon-{}@{}", event.id.as_str(), UID_DOMAIN)
+}
+
+fn calendar(events: &[Event], now: DateTime<Utc>, method: Option<&str>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    lines.extend(method.map(|method| format!("METHOD:{}", method)));
+    if let Some((from, to)) = covered_range(events) {
+        for time_zone in referenced_time_zones(events) {
+            lines.extend(vtimezone(&time_zone, from, to));
+        }
+    }
+    for event in events {
+        lines.extend(vevent(event, now));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+fn vevent(event: &Event, now: DateTime<Utc>) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}", uid(event)),
+        format!("DTSTAMP:{}", utc(&now)),
+    ];
+    if let Some(updated_at) = &event.updated_at {
+        lines.push(format!("LAST-MODIFIED:{}", utc(updated_at)));
+    }
+
+    let zones = &event.time_zones;
+    match &event.time {
+        EventTime::Timed(range) => {
+            lines.push(format!("DTSTART;{}", local(&range.start, &zones.start)));
+            lines.push(format!("DTEND;{}", local(&range.end, &zones.end)));
+        }
+        // 終日予定の終了日は最終日の翌日を指定する
+        EventTime::AllDay(dates) => {
+            lines.push(format!("DTSTART;VALUE=DATE:{}", date(&dates.start)));
+            lines.push(format!("DTEND;VALUE=DATE:{}", date(&dates.exclusive_end())));
+        }
+        // DTENDを省略すると、開始時刻だけの予定になる
+        EventTime::StartOnly { start } => lines.push(format!("DTSTART;{}", local(start, &zones.start))),
+    }
+    lines.extend(event.recurrence_lines());
+
+    lines.push(format!("SUMMARY:{}", escape_text(&event.title.as_str())));
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    if let Some(location) = &event.location {
+        lines.push(format!("LOCATION:{}", escape_text(location)));
+    }
+    lines.push(format!("CLASS:{}", if event.private { "PRIVATE" } else { "PUBLIC" }));
+    // Garoonの参加者はメールアドレスを持たないので、表示名だけを持つ参加者として出力する
+    for attendee in &event.attendees {
+        lines.push(format!("ATTENDEE;CN={};CUTYPE=INDIVIDUAL:invalid:nomail", param_value(&attendee.display_name)));
+    }
+    lines.push("END:VEVENT".to_string());
+
+    lines
+}
+
+/// TZIDで参照するタイムゾーンを、最初に現れた順に並べる。繰り返し予定の除外日が使うタイムゾーンも含む
+fn referenced_time_zones(events: &[Event]) -> Vec<Tz> {
+    let mut time_zones = vec![];
+    for event in events {
+        let referenced = match event.time {
+            EventTime::Timed(_) => vec![event.time_zones.start, event.time_zones.end],
+            EventTime::StartOnly { .. } => vec![event.time_zones.start],
+            EventTime::AllDay(_) => continue,
+        };
+        for time_zone in referenced.into_iter().chain(event.recurrence.as_ref().map(|recurrence| recurrence.time_zone)) {
+            if !time_zones.contains(&time_zone) {
+                time_zones.push(time_zone);
+            }
+        }
+    }
+
+    time_zones
+}
+
+/// 全ての予定が占める期間。VTIMEZONEにはこの間の時差の切り替えを含める
+fn covered_range(events: &[Event]) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let spans: Vec<_> = events.iter().map(|event| event.span()).collect();
+    let from = spans.iter().map(|span| span.start).min()?;
+    let to = spans.iter().map(|span| span.end).max()?;
+
+    Some((from - Duration::days(1), to + Duration::days(1)))
+}
+
+/// `from`から`to`までの時差の切り替えを、切り替えごとのSTANDARD/DAYLIGHTとして並べる
+fn vtimezone(time_zone: &Tz, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<String> {
+    let mut lines = vec!["BEGIN:VTIMEZONE".to_string(), format!("TZID:{}", time_zone.name())];
+    lines.extend(observance(time_zone, from, from));
+
+    let mut day = from;
+    while day < to {
+        let next = day + Duration::days(1);
+        if offset_seconds(time_zone, &day) != offset_seconds(time_zone, &next) {
+            lines.extend(observance(time_zone, day, transition(time_zone, day, next)));
+        }
+        day = next;
+    }
+    lines.push("END:VTIMEZONE".to_string());
+
+    lines
+}
+
+/// `before`の時差から`at`の時差に切り替わるSTANDARDまたはDAYLIGHT
+fn observance(time_zone: &Tz, before: DateTime<Utc>, at: DateTime<Utc>) -> Vec<String> {
+    let offset = time_zone.offset_from_utc_datetime(&at.naive_utc());
+    let offset_from = offset_seconds(time_zone, &before);
+    let kind = if offset.dst_offset().is_zero() { "STANDARD" } else { "DAYLIGHT" };
+    let mut lines = vec![
+        format!("BEGIN:{}", kind),
+        // DTSTARTは切り替え前の時差での現地時刻
+        format!("DTSTART:{}", (at + Duration::seconds(offset_from.into())).naive_utc().format("%Y%m%dT%H%M%S")),
+        format!("TZOFFSETFROM:{}", format_offset(offset_from)),
+        format!("TZOFFSETTO:{}", format_offset(offset.fix().local_minus_utc())),
+    ];
+    if let Some(abbreviation) = offset.abbreviation() {
+        lines.push(format!("TZNAME:{}", escape_text(abbreviation)));
+    }
+    lines.push(format!("END:{}", kind));
+
+    lines
+}
+
+/// `start`から`end`の間で時差が切り替わる時刻を二分探索で求める
+fn transition(time_zone: &Tz, mut start: DateTime<Utc>, mut end: DateTime<Utc>) -> DateTime<Utc> {
+    let before = offset_seconds(time_zone, &start);
+    while end - start > Duration::seconds(1) {
+        let middle = start + (end - start) / 2;
+        if offset_seconds(time_zone, &middle) == before {
+            start = middle;
+        } else {
+            end = middle;
+        }
+    }
+
+    end
+}
+
+fn offset_seconds(time_zone: &Tz, date_time: &DateTime<Utc>) -> i32 {
+    time_zone.offset_from_utc_datetime(&date_time.naive_utc()).fix().local_minus_utc()
+}
+
+/// `+0900`や`-0430`の形式。秒がある場合は`+053328`のように秒も付ける
+fn format_offset(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if seconds == 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+}
+
+fn utc(date_time: &DateTime<Utc>) -> String {
+    date_time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// `TZID=Asia/Tokyo:20240510T090000`の形式
+fn local(date_time: &DateTime<Utc>, time_zone: &Tz) -> String {
+    format!("TZID={}:{}", time_zone.name(), date_time.with_timezone(time_zone).format("%Y%m%dT%H%M%S"))
+}
+
+fn date(date: &NaiveDate) -> String {
+    date.format("%Y%m%d").to_string()
+}
+
+/// TEXT型の値のエスケープ (RFC 5545 3.3.11)
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+/// パラメーターの値。`:`や`;`、`,`を含む場合はダブルクォートで囲む。ダブルクォートと改行は使えないので取り除く
+fn param_value(value: &str) -> String {
+    let value: String = value.chars().filter(|c| !matches!(c, '"' | '\r' | '\n')).collect();
+    if value.contains([':', ';', ',']) {
+        format!("\"{}\"", value)
+    } else {
+        value
+    }
+}
+
+/// 75オクテットを超える行を、UTF-8の文字の途中で切らないように折り返す。行末はCRLF
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut octets = 0;
+    for c in line.chars() {
+        if octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            octets = 1;
+        }
+        folded.push(c);
+        octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc, Weekday};
+    use chrono_tz::{America, Asia};
+
+    use crate::models::event::{Attendee, Event, EventId, EventTime, EventTimeZones, Title};
+    use crate::models::recurrence::{Frequency, Recurrence};
+    use crate::services::export_ics_service::{escape_text, fold, to_ics};
+    use crate::utils::date_range::DateRange;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn now() -> chrono::DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_to_ics() {
+        // Setup
+        let events = vec![
+            Event::new(
+                EventId::new("1".to_string()),
+                Title::new("会議".to_string()),
+                DateTimeRange::new(
+                    Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2024, 5, 10, 1, 0, 0).unwrap(),
+                ),
+                vec![Attendee::new("山田".to_string()), Attendee::new("営業部, 東京".to_string())],
+            )
+                .with_time_zones(EventTimeZones::new(Asia::Tokyo, Asia::Tokyo))
+                .with_description(Some("資料を持参\n議題; 予算".to_string()))
+                .with_location(Some("会議室A, 会議室B".to_string()))
+                .with_private(true)
+                .with_updated_at(Some(Utc.with_ymd_and_hms(2024, 4, 30, 12, 0, 0).unwrap())),
+            Event::new(
+                EventId::new("2".to_string()),
+                Title::new("休暇".to_string()),
+                EventTime::AllDay(DateRange::new(
+                    NaiveDate::from_ymd_opt(2024, 5, 13).unwrap(),
+                    NaiveDate::from_ymd_opt(2024, 5, 14).unwrap(),
+                )),
+                vec![],
+            ),
+        ];
+
+        // Exercise
+        let ics = to_ics(&events, now());
+
+        // Asserts
+        assert_eq!(
+            ics,
+            "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            PRODID:-//tensha//Garoon export//JA\r\n\
+            CALSCALE:GREGORIAN\r\n\
+            METHOD:PUBLISH\r\n\
+            BEGIN:VTIMEZONE\r\n\
+            TZID:Asia/Tokyo\r\n\
+            BEGIN:STANDARD\r\n\
+            DTSTART:20240509T090000\r\n\
+            TZOFFSETFROM:+0900\r\n\
+            TZOFFSETTO:+0900\r\n\
+            TZNAME:JST\r\n\
+            END:STANDARD\r\n\
+            END:VTIMEZONE\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:garoon-1@tensha.garoon\r\n\
+            DTSTAMP:20240501T000000Z\r\n\
+            LAST-MODIFIED:20240430T120000Z\r\n\
+            DTSTART;TZID=Asia/Tokyo:20240510T090000\r\n\
+            DTEND;TZID=Asia/Tokyo:20240510T100000\r\n\
+            SUMMARY:会議\r\n\
+            DESCRIPTION:資料を持参\\n議題\\; 予算\r\n\
+            LOCATION:会議室A\\, 会議室B\r\n\
+            CLASS:PRIVATE\r\n\
+            ATTENDEE;CN=山田;CUTYPE=INDIVIDUAL:invalid:nomail\r\n\
+            ATTENDEE;CN=\"営業部, 東京\";CUTYPE=INDIVIDUAL:invalid:nomail\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:garoon-2@tensha.garoon\r\n\
+            DTSTAMP:20240501T000000Z\r\n\
+            DTSTART;VALUE=DATE:20240513\r\n\
+            DTEND;VALUE=DATE:20240515\r\n\
+            SUMMARY:休暇\r\n\
+            CLASS:PUBLIC\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n"
+        );
+    }
+
+    #[test]
+    fn test_to_ics_夏時間の切り替え() {
+        // Setup: 夏時間の開始をまたぐニューヨークの繰り返し予定
+        let period = DateRange::new(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 3, 31).unwrap());
+        let events = vec![Event::new(
+            EventId::new("1".to_string()),
+            Title::new("定例".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2024, 3, 1, 14, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 3, 1, 15, 0, 0).unwrap(),
+            ),
+            vec![],
+        )
+            .with_time_zones(EventTimeZones::new(America::New_York, America::New_York))
+            .with_recurrence(Some(Recurrence::new(Frequency::Weekly(Weekday::Fri), period, America::New_York)))];
+
+        // Exercise
+        let ics = to_ics(&events, now());
+
+        // Asserts: 2024-03-10 02:00 (EST) に EDT へ切り替わる
+        assert!(ics.contains(
+            "BEGIN:DAYLIGHT\r\n\
+            DTSTART:20240310T020000\r\n\
+            TZOFFSETFROM:-0500\r\n\
+            TZOFFSETTO:-0400\r\n\
+            TZNAME:EDT\r\n\
+            END:DAYLIGHT\r\n"
+        ));
+        assert!(ics.contains("DTSTART;TZID=America/New_York:20240301T090000\r\n"));
+        assert!(ics.contains("RRULE:FREQ=WEEKLY;BYDAY=FR;UNTIL=20240401T035959Z\r\n"));
+    }
+
+    #[test]
+    fn test_escape_text() {
+        assert_eq!(escape_text("a\\b;c,d\r\ne\nf"), "a\\\\b\\;c\\,d\\ne\\nf");
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = format!("DESCRIPTION:{}", "あ".repeat(30));
+
+        let folded = fold(&line);
+
+        // 各行は75オクテット以内で、文字の途中では切らない
+        assert_eq!(folded, format!("DESCRIPTION:{}\r\n {}\r\n", "あ".repeat(21), "あ".repeat(9)));
+        assert!(folded.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(fold("SUMMARY:会議"), "SUMMARY:会議\r\n");
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::Utc;
+#[cfg(test)]
+use mockall::automock;
+use tokio::sync::Mutex;
+
+use crate::models::event::FetchedEvents;
+use crate::services::export_ics_service::to_ics;
+
+/// 配信する予定の取得元。取得期間は取得するたびに現在時刻から決める
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait FeedSource: Send + Sync {
+    async fn fetch(&self) -> Result<FetchedEvents, anyhow::Error>;
+}
+
+/// iCalendar形式で配信する予定。取得した内容は`refresh_interval`の間使い回す
+pub struct IcsFeed<S: FeedSource> {
+    source: S,
+    refresh_interval: Duration,
+    cache: Mutex<Option<CachedFeed>>,
+}
+
+struct CachedFeed {
+    /// 一度も取得できていなければ、最後に失敗した理由
+    body: Result<String, String>,
+    /// 最後に取得を試みた時刻。取得し直せなかった場合も、次の間隔までは前回の内容を返す
+    attempted_at: Instant,
+}
+
+impl<S: FeedSource> IcsFeed<S> {
+    pub fn new(source: S, refresh_interval: Duration) -> Self {
+        Self { source, refresh_interval, cache: Mutex::new(None) }
+    }
+
+    /// 前回の取得から`refresh_interval`が過ぎていれば取得し直す。
+    /// 取得し直せなかった場合は、前回取得した内容があればそれを返し、Garoonに問い合わせ続けないよう次の間隔まで待つ。
+    /// 一度も取得できていない場合も、次の間隔までは問い合わせずにエラーを返す
+    pub async fn body(&self) -> Result<String, anyhow::Error> {
+        // 同時に来たリクエストで何度もGaroonに問い合わせないよう、取得中はロックを持ち続ける
+        let mut cache = self.cache.lock().await;
+        if let Some(cached) = cache.as_ref().filter(|cached| cached.attempted_at.elapsed() < self.refresh_interval) {
+            return cached.body.clone().map_err(|e| anyhow!("failed to fetch the feed: {}", e));
+        }
+
+        match self.source.fetch().await {
+            Ok(fetched) => {
+                let body = to_ics(fetched.merge_recurring().events(), Utc::now());
+                *cache = Some(CachedFeed { body: Ok(body.clone()), attempted_at: Instant::now() });
+                Ok(body)
+            }
+            Err(e) => match cache.as_mut().filter(|cached| cached.body.is_ok()) {
+                Some(cached) => {
+                    eprintln!("warning: failed to refresh the feed, serving the previous one: {}", e);
+                    cached.attempted_at = Instant::now();
+                    cached.body.clone().map_err(|e| anyhow!(e))
+                }
+                None => {
+                    *cache = Some(CachedFeed { body: Err(e.to_string()), attempted_at: Instant::now() });
+                    Err(e)
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+    use chrono::{TimeZone, Utc};
+
+    use crate::models::event::{Event, EventId, FetchedEvents, Title};
+    use crate::services::ics_feed_service::{IcsFeed, MockFeedSource};
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn fetched() -> FetchedEvents {
+        FetchedEvents::from(vec![Event::new(
+            EventId::new("1".to_string()),
+            Title::new("会議".to_string()),
+            DateTimeRange::new(
+                Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 5, 10, 1, 0, 0).unwrap(),
+            ),
+            vec![],
+        )])
+    }
+
+    #[tokio::test]
+    async fn body_取得した内容を使い回す() {
+        // Setup
+        let mut source = MockFeedSource::new();
+        source.expect_fetch().times(1).returning(|| Ok(fetched()));
+        let feed = IcsFeed::new(source, Duration::from_secs(60));
+
+        // Exercise
+        let first = feed.body().await.unwrap();
+        let second = feed.body().await.unwrap();
+
+        // Asserts
+        assert!(first.contains("SUMMARY:会議\r\n"));
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn body_取得し直せなければ前回の内容を返す() {
+        // Setup: 毎回取得し直す
+        let mut source = MockFeedSource::new();
+        let mut calls = 0;
+        source.expect_fetch().times(2).returning(move || {
+            calls += 1;
+            if calls == 1 { Ok(fetched()) } else { Err(anyhow!("Garoon is down")) }
+        });
+        let feed = IcsFeed::new(source, Duration::ZERO);
+
+        // Exercise
+        let first = feed.body().await.unwrap();
+        let second = feed.body().await.unwrap();
+
+        // Asserts
+        assert_eq!(first, second);
+
+        // 一度も取得できていなければエラーにする
+        let mut source = MockFeedSource::new();
+        source.expect_fetch().returning(|| Err(anyhow!("Garoon is down")));
+        assert!(IcsFeed::new(source, Duration::ZERO).body().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn body_取得し直せなかった後は次の間隔まで問い合わせない() {
+        // Setup: 1回目だけ取得でき、2回目の取得は失敗する
+        let mut source = MockFeedSource::new();
+        let mut calls = 0;
+        source.expect_fetch().times(2).returning(move || {
+            calls += 1;
+            if calls == 1 { Ok(fetched()) } else { Err(anyhow!("Garoon is down")) }
+        });
+        let feed = IcsFeed::new(source, Duration::from_millis(200));
+        let first = feed.body().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // Exercise: 間隔が過ぎてから、続けて2回リクエストする
+        let second = feed.body().await.unwrap();
+        let third = feed.body().await.unwrap();
+
+        // Asserts: 失敗した取得は1回だけで、前回の内容を返し続ける
+        assert_eq!(first, second);
+        assert_eq!(first, third);
+    }
+
+    #[tokio::test]
+    async fn body_一度も取得できなければ次の間隔まで問い合わせない() {
+        // Setup: 取得は常に失敗する
+        let mut source = MockFeedSource::new();
+        source.expect_fetch().times(1).returning(|| Err(anyhow!("Garoon is down")));
+        let feed = IcsFeed::new(source, Duration::from_secs(60));
+
+        // Exercise
+        let first = feed.body().await;
+        let second = feed.body().await;
+
+        // Asserts: 失敗した取得は1回だけで、エラーを返し続ける
+        assert!(first.is_err());
+        assert!(second.unwrap_err().to_string().contains("Garoon is down"));
+    }
+}
@@ -1,2 +1,4 @@
 pub mod calendar_sync_service;
-pub mod export_events_service;
\ No newline at end of file
+pub mod export_events_service;
+pub mod export_ics_service;
+pub mod ics_feed_service;
\ No newline at end of file
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use tokio::sync::OnceCell;
+
+use crate::apis::caldav::{CalDavClient, Precondition};
+use crate::error::Error;
+use crate::models::event::{Event, FetchedEvents};
+use crate::models::sync_plan::{DeletionGuard, SyncPlan};
+use crate::models::sync_state::{SyncRecord, SyncState};
+use crate::repositories::sync_state_repository::SyncStateRepository;
+use crate::services::export_ics_service::to_calendar_object;
+use crate::sinks::event_sink::{EventSink, SinkCapabilities, SinkReport};
+use crate::utils::date_time_range::DateTimeRange;
+
+/// サーバー上のリソース名と、そのETag
+type RemoteResources = HashMap<String, Option<String>>;
+
+/// CalDAVのカレンダーコレクション。予定ごとに1つのリソースを置き、同期状態と突き合わせて変わった予定だけを反映する。
+/// 同期状態の`google_event_id`には、コレクション内のリソース名を記録する
+pub struct CalDavSink<S: SyncStateRepository> {
+    client: CalDavClient,
+    url: String,
+    calendar: Option<String>,
+    state_repository: S,
+    deletion_guard: DeletionGuard,
+    collection_url: OnceCell<String>,
+}
+impl<S> CalDavSink<S>
+where
+    S: SyncStateRepository + Send + Sync,
+{
+    /// `url`はカレンダーコレクションか、カレンダーを探す起点になるサーバーやプリンシパルのURL
+    pub fn new(client: CalDavClient, url: String, state_repository: S) -> Self {
+        Self {
+            client,
+            url,
+            calendar: None,
+            state_repository,
+            deletion_guard: DeletionGuard::default(),
+            collection_url: OnceCell::new(),
+        }
+    }
+
+    /// カレンダーを探す場合に使う、カレンダーのパスの末尾か表示名。省略時は最初のカレンダー
+    pub fn with_calendar(mut self, calendar: Option<String>) -> Self {
+        self.calendar = calendar;
+        self
+    }
+
+    pub fn with_deletion_guard(mut self, deletion_guard: DeletionGuard) -> Self {
+        self.deletion_guard = deletion_guard;
+        self
+    }
+
+    async fn collection_url(&self) -> Result<&str, Error> {
+        self.collection_url
+            .get_or_try_init(|| self.client.discover_calendar(&self.url, self.calendar.as_deref()))
+            .await
+            .map(String::as_str)
+    }
+
+    /// 取得期間外に移った予定や削除する予定のリソースも突き合わせられるよう、同期済みの期間も含めて問い合わせる
+    async fn remote_resources(&self, collection_url: &str, plan: &SyncPlan, period: &DateTimeRange) -> Result<RemoteResources, Error> {
+        let range = plan
+            .updates
+            .iter()
+            .filter_map(|update| update.record.duration.as_ref())
+            .chain(plan.deletes.iter().filter_map(|record| record.duration.as_ref()))
+            .fold(period.clone(), |range, duration| {
+                DateTimeRange::new(range.start.min(duration.start), range.end.max(duration.end))
+            });
+        let objects = self.client.calendar_query(collection_url, &range, false).await?;
+
+        Ok(objects.into_iter().map(|object| (resource_name_of(&object.href), object.etag)).collect())
+    }
+
+    async fn apply(
+        &self,
+        collection_url: &str,
+        plan: &SyncPlan,
+        restores: &[Event],
+        remote: &RemoteResources,
+        state: &mut SyncState,
+    ) -> Result<(), anyhow::Error> {
+        let now = Utc::now();
+        for event in plan.creates.iter().chain(restores) {
+            let name = resource_name(event);
+            self.put(collection_url, &name, event, remote, now).await?;
+            state.upsert(SyncRecord::new(event, name));
+        }
+        for update in &plan.updates {
+            let name = update.record.google_event_id.clone();
+            self.put(collection_url, &name, &update.event, remote, now).await?;
+            state.upsert(SyncRecord::new(&update.event, name));
+        }
+        for record in &plan.deletes {
+            // 転写先で既に削除されている場合は記録だけ消す
+            if let Some(etag) = remote.get(&record.google_event_id) {
+                match self.client.delete(&resource_url(collection_url, &record.google_event_id), etag.as_deref()).await {
+                    Ok(()) => {}
+                    Err(Error::Transport(e)) if matches!(e.status(), Some(StatusCode::NOT_FOUND | StatusCode::GONE)) => {}
+                    Err(e) => return Err(Error::sink(record.garoon_id.clone(), e).into()),
+                }
+            }
+            state.remove(&record.garoon_id());
+        }
+
+        Ok(())
+    }
+
+    /// 問い合わせた時点のETagを条件にして、その後に他で変更されたリソースは上書きしない。
+    /// リソースがなければ、転写先で削除されたものとして作り直す
+    async fn put(&self, collection_url: &str, name: &str, event: &Event, remote: &RemoteResources, now: DateTime<Utc>) -> Result<(), Error> {
+        let precondition = match remote.get(name) {
+            Some(Some(etag)) => Precondition::Matches(etag),
+            Some(None) => Precondition::Any,
+            None => Precondition::Absent,
+        };
+
+        match self.client.put(&resource_url(collection_url, name), to_calendar_object(event, now), precondition).await {
+            Ok(_) => Ok(()),
+            Err(Error::Transport(e)) if e.status() == Some(StatusCode::PRECONDITION_FAILED) => {
+                Err(Error::sink(event.id.as_str(), "the event was changed on the CalDAV server during the sync; run sync again"))
+            }
+            Err(e) => Err(Error::sink(event.id.as_str(), e)),
+        }
+    }
+}
+#[async_trait]
+impl<S> EventSink for CalDavSink<S>
+where
+    S: SyncStateRepository + Send + Sync,
+{
+    fn name(&self) -> String {
+        format!("caldav ({})", self.url)
+    }
+
+    fn capabilities(&self) -> SinkCapabilities {
+        SinkCapabilities { update: true, delete: true, recurrence: true }
+    }
+
+    async fn write(&self, fetched: &FetchedEvents, period: &DateTimeRange) -> Result<SinkReport, anyhow::Error> {
+        let collection_url = self.collection_url().await?;
+        let mut state = self.state_repository.load()?;
+        let plan = SyncPlan::new(fetched.clone(), &state, period);
+        plan.check_deletions(&self.deletion_guard)?;
+        plan.refresh(&mut state);
+
+        let remote = self.remote_resources(collection_url, &plan, period).await?;
+        // 変更がなくても、転写先で削除されていた予定は作り直す
+        let restores: Vec<Event> = fetched
+            .events()
+            .iter()
+            .filter(|event| {
+                state.get(&event.id).is_some_and(|record| {
+                    record.content_hash == event.content_hash() && !remote.contains_key(&record.google_event_id)
+                })
+            })
+            .cloned()
+            .collect();
+
+        // 途中で失敗しても反映済みの分は記録し、次回の実行で重複登録しないようにする
+        let result = self.apply(collection_url, &plan, &restores, &remote, &mut state).await;
+        self.state_repository.save(&state)?;
+        result?;
+
+        Ok(SinkReport {
+            created: plan.creates.len() + restores.len(),
+            updated: plan.updates.len(),
+            deleted: plan.deletes.len(),
+            ..Default::default()
+        })
+    }
+
+    /// 同期状態だけと突き合わせるので、転写先で削除されていて作り直す予定は含まない
+    fn plan(&self, fetched: &FetchedEvents, period: &DateTimeRange) -> Result<Option<SyncPlan>, anyhow::Error> {
+        let state = self.state_repository.load()?;
+
+        Ok(Some(SyncPlan::new(fetched.clone(), &state, period)))
+    }
+}
+
+/// 予定IDから決まるリソース名。URLにそのまま使えない文字を含むIDは、ハッシュ値から名前を作る
+fn resource_name(event: &Event) -> String {
+    let id = event.id.as_str();
+    if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) {
+        return format!("garoon-{}.ics", id);
+    }
+
+    let hash: String = Sha256::digest(id.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("tensha-{}.ics", hash)
+}
+
+/// 応答の`href`の末尾
+fn resource_name_of(href: &str) -> String {
+    href.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string()
+}
+
+fn resource_url(collection_url: &str, name: &str) -> String {
+    format!("{}/{}", collection_url.trim_end_matches('/'), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::{body_string_contains, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::apis::caldav::CalDavClient;
+    use crate::apis::garoon::{GaroonDateTime, GaroonEvent};
+    use crate::models::event::{Event, EventId, FetchedEvents, Title};
+    use crate::models::sync_state::{SyncRecord, SyncState};
+    use crate::repositories::sync_state_repository::MockSyncStateRepository;
+    use crate::sinks::caldav_sink::{resource_name, CalDavSink};
+    use crate::sinks::event_sink::EventSink;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    /// 取得期間(現在から1週間)内に開始する予定
+    fn upcoming_event(id: &str, subject: &str) -> Event {
+        let tomorrow = chrono::Utc::now().date_naive().succ_opt().unwrap();
+        let start = tomorrow.and_hms_opt(9, 0, 0).unwrap().and_utc();
+        let garoon_event = GaroonEvent {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            attendees: vec![],
+            start: GaroonDateTime { date_time: start.to_rfc3339(), time_zone: "UTC".to_string() },
+            end: Some(GaroonDateTime {
+                date_time: (start + chrono::Duration::hours(1)).to_rfc3339(),
+                time_zone: "UTC".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        Event::try_from(garoon_event).unwrap()
+    }
+
+    /// 現在から1週間
+    fn period() -> DateTimeRange {
+        let now = chrono::Utc::now();
+        DateTimeRange::new(now, now + chrono::Duration::weeks(1))
+    }
+
+    fn synced_state(events: Vec<Event>) -> SyncState {
+        let mut state = SyncState::default();
+        for event in events {
+            state.upsert(SyncRecord::new(&event, resource_name(&event)));
+        }
+
+        state
+    }
+
+    /// `/work/`をカレンダーコレクションとして応答し、REPORTには`resources`(名前とETag)を返すサーバー
+    async fn calendar_server(resources: &[(&str, &str)]) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("PROPFIND"))
+            .and(path("/work/"))
+            .respond_with(ResponseTemplate::new(207).set_body_string(r#"<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response><d:href>/work/</d:href><d:propstat><d:prop>
+    <d:resourcetype><d:collection/><cal:calendar/></d:resourcetype>
+  </d:prop></d:propstat></d:response>
+</d:multistatus>"#))
+            .mount(&mock_server)
+            .await;
+        let responses: String = resources
+            .iter()
+            .map(|(name, etag)| {
+                format!(
+                    r#"<d:response><d:href>/work/{}</d:href><d:propstat><d:prop><d:getetag>{}</d:getetag><cal:calendar-data>BEGIN:VCALENDAR
+END:VCALENDAR
+</cal:calendar-data></d:prop></d:propstat></d:response>"#,
+                    name, etag
+                )
+            })
+            .collect();
+        Mock::given(method("REPORT"))
+            .and(path("/work/"))
+            .respond_with(ResponseTemplate::new(207).set_body_string(format!(
+                r#"<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">{}</d:multistatus>"#,
+                responses
+            )))
+            .mount(&mock_server)
+            .await;
+
+        mock_server
+    }
+
+    fn state_repository(state: SyncState, saved: impl Fn(&SyncState) -> bool + Send + 'static) -> MockSyncStateRepository {
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).return_once(move || Ok(state));
+        state_repository.expect_save().withf(saved).times(1).returning(|_| Ok(()));
+
+        state_repository
+    }
+
+    #[tokio::test]
+    async fn write_正常系() {
+        // Setup: 変更あり・削除・新規の予定がそれぞれ反映されることを設定する
+        let mock_server = calendar_server(&[("garoon-1.ics", "\"a\""), ("garoon-2.ics", "\"b\"")]).await;
+        Mock::given(method("PUT"))
+            .and(path("/work/garoon-3.ics"))
+            .and(header("If-None-Match", "*"))
+            .and(body_string_contains("UID:garoon-3@tensha.garoon"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/work/garoon-1.ics"))
+            .and(header("If-Match", "\"a\""))
+            .and(body_string_contains("SUMMARY:会議(変更)"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/work/garoon-2.ics"))
+            .and(header("If-Match", "\"b\""))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let state = synced_state(vec![upcoming_event("1", "会議"), upcoming_event("2", "打ち合わせ")]);
+        let state_repository = state_repository(state, |state| {
+            state.len() == 2 && state.get(&EventId::new("3".to_string())).is_some_and(|record| record.google_event_id == "garoon-3.ics")
+        });
+        let fetched = FetchedEvents::from(vec![upcoming_event("1", "会議(変更)"), upcoming_event("3", "面談")]);
+
+        // Exercise
+        let sink = CalDavSink::new(CalDavClient::new(), format!("{}/work/", mock_server.uri()), state_repository);
+        let result = sink.write(&fetched, &period()).await;
+
+        // Asserts
+        let report = result.unwrap();
+        assert_eq!((report.created, report.updated, report.deleted), (1, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn write_転写先で削除された予定は作り直す() {
+        // Setup: 同期済みで変更のない予定が、サーバーにはないよう設定する
+        let mock_server = calendar_server(&[]).await;
+        Mock::given(method("PUT"))
+            .and(path("/work/garoon-1.ics"))
+            .and(header("If-None-Match", "*"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let state_repository = state_repository(synced_state(vec![upcoming_event("1", "会議")]), |state| state.len() == 1);
+        let fetched = FetchedEvents::from(vec![upcoming_event("1", "会議")]);
+
+        // Exercise
+        let sink = CalDavSink::new(CalDavClient::new(), format!("{}/work/", mock_server.uri()), state_repository);
+        let result = sink.write(&fetched, &period()).await;
+
+        // Asserts
+        assert_eq!(result.unwrap().created, 1);
+    }
+
+    #[tokio::test]
+    async fn write_他で変更されたリソースは上書きしない() {
+        // Setup: 問い合わせた後にリソースが変わり、If-Matchの条件に合わないよう設定する
+        let mock_server = calendar_server(&[("garoon-1.ics", "\"a\"")]).await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(412))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        // 反映できなかった予定は、変更前の記録のまま保存する
+        let state_repository = state_repository(synced_state(vec![upcoming_event("1", "会議")]), |state| {
+            state.records().all(|record| record.title == "会議")
+        });
+        let fetched = FetchedEvents::from(vec![upcoming_event("1", "会議(変更)")]);
+
+        // Exercise
+        let sink = CalDavSink::new(CalDavClient::new(), format!("{}/work/", mock_server.uri()), state_repository);
+        let result = sink.write(&fetched, &period()).await;
+
+        // Asserts
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("changed on the CalDAV server"), "{}", message);
+    }
+
+    #[test]
+    fn test_resource_name() {
+        let event = |id: &str| Event::new(EventId::new(id.to_string()), Title::new("予定".to_string()), period(), vec![]);
+
+        assert_eq!(resource_name(&event("123")), "garoon-123.ics");
+        // 取得元の接頭辞や繰り返しの回を含むIDは、URLに使える名前にする
+        let name = resource_name(&event("caldav:standup/20240510T000000Z"));
+        assert!(name.starts_with("tensha-") && name.ends_with(".ics"), "{}", name);
+        assert_eq!(name, resource_name(&event("caldav:standup/20240510T000000Z")));
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono_tz::Tz;
+
+use crate::models::event::FetchedEvents;
+use crate::services::export_events_service::{export, CsvColumn};
+use crate::sinks::event_sink::{EventSink, SinkCapabilities, SinkReport};
+use crate::utils::date_time_range::DateTimeRange;
+
+/// GoogleカレンダーにインポートできるCSVファイル。毎回全ての予定を書き出す
+pub struct CsvSink {
+    path: PathBuf,
+    time_zone: Option<Tz>,
+    columns: Vec<CsvColumn>,
+}
+impl CsvSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), time_zone: None, columns: CsvColumn::ALL.to_vec() }
+    }
+
+    /// 省略時は予定ごとのタイムゾーンで出力する
+    pub fn with_time_zone(mut self, time_zone: Option<Tz>) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<CsvColumn>) -> Self {
+        self.columns = columns;
+        self
+    }
+}
+#[async_trait]
+impl EventSink for CsvSink {
+    fn name(&self) -> String {
+        format!("csv ({})", self.path.display())
+    }
+
+    fn capabilities(&self) -> SinkCapabilities {
+        SinkCapabilities::default()
+    }
+
+    async fn write(&self, fetched: &FetchedEvents, _period: &DateTimeRange) -> Result<SinkReport, anyhow::Error> {
+        export(fetched.events(), &self.path, self.time_zone, &self.columns)?;
+
+        Ok(SinkReport { written: fetched.events().len(), ..Default::default() })
+    }
+}
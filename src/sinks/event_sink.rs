@@ -0,0 +1,211 @@
+use std::fmt;
+
+use async_trait::async_trait;
+use futures::future::join_all;
+#[cfg(test)]
+use mockall::automock;
+
+use crate::models::event::FetchedEvents;
+use crate::models::sync_plan::SyncPlan;
+use crate::utils::date_time_range::DateTimeRange;
+
+/// 転写先ができること
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SinkCapabilities {
+    /// 前回反映した予定を変更できる
+    pub update: bool,
+    /// 前回反映した予定を削除できる
+    pub delete: bool,
+    /// 繰り返し予定を1件の繰り返し予定として扱える。できなければ展開された回ごとに渡す
+    pub recurrence: bool,
+}
+
+/// 1つの転写先に反映した件数
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SinkReport {
+    /// ファイルなど、毎回全ての予定を書き出す転写先に書き出した件数
+    pub written: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
+}
+impl fmt::Display for SinkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts = [("written", self.written), ("created", self.created), ("updated", self.updated), ("deleted", self.deleted)];
+        let parts: Vec<String> = counts.iter().filter(|(_, count)| *count > 0).map(|(label, count)| format!("{} {}", count, label)).collect();
+        if parts.is_empty() {
+            return write!(f, "no changes");
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// 取得した予定の転写先。予定はまとめて渡す
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// 結果の表示に使う名前
+    fn name(&self) -> String;
+
+    fn capabilities(&self) -> SinkCapabilities;
+
+    /// `fetched`は`period`の期間で取得した予定
+    async fn write(&self, fetched: &FetchedEvents, period: &DateTimeRange) -> Result<SinkReport, anyhow::Error>;
+
+    /// 反映はせず、`write`した場合の反映内容だけを求める。同期状態を持たない転写先は`None`
+    fn plan(&self, _fetched: &FetchedEvents, _period: &DateTimeRange) -> Result<Option<SyncPlan>, anyhow::Error> {
+        Ok(None)
+    }
+}
+
+/// 転写先ごとの結果
+#[derive(Debug)]
+pub struct SinkOutcome {
+    pub name: String,
+    pub result: Result<SinkReport, anyhow::Error>,
+}
+
+/// 転写先ごとの反映内容
+#[derive(Debug)]
+pub struct SinkPlan {
+    pub name: String,
+    pub result: Result<Option<SyncPlan>, anyhow::Error>,
+}
+
+/// 全ての転写先に並行して反映する。失敗した転写先があっても、他の転写先には反映する
+pub async fn write_all(sinks: &[Box<dyn EventSink>], fetched: FetchedEvents, period: &DateTimeRange) -> Vec<SinkOutcome> {
+    let merged = fetched.clone().merge_recurring();
+    let writes = sinks.iter().map(|sink| {
+        let events = if sink.capabilities().recurrence { &merged } else { &fetched };
+        async move { SinkOutcome { name: sink.name(), result: sink.write(events, period).await } }
+    });
+
+    join_all(writes).await
+}
+
+/// `write_all`と同じ予定を渡し、転写先ごとの反映内容を求める。同期状態を持たない転写先は含めない
+pub fn plan_all(sinks: &[Box<dyn EventSink>], fetched: FetchedEvents, period: &DateTimeRange) -> Vec<SinkPlan> {
+    let merged = fetched.clone().merge_recurring();
+    sinks
+        .iter()
+        .map(|sink| {
+            let events = if sink.capabilities().recurrence { &merged } else { &fetched };
+            SinkPlan { name: sink.name(), result: sink.plan(events, period) }
+        })
+        .filter(|plan| !matches!(plan.result, Ok(None)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use crate::models::event::{Event, EventId, FetchedEvents, Title};
+    use crate::models::recurrence::{Frequency, Recurrence};
+    use crate::models::sync_plan::SyncPlan;
+    use crate::models::sync_state::SyncState;
+    use crate::sinks::event_sink::{plan_all, write_all, EventSink, MockEventSink, SinkCapabilities, SinkReport};
+    use crate::utils::date_range::DateRange;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    /// 5月10日から3日間、毎日繰り返す予定を展開したもの
+    fn daily_events() -> FetchedEvents {
+        let recurrence = Recurrence::new(
+            Frequency::Daily,
+            DateRange::new(NaiveDate::from_ymd_opt(2024, 5, 10).unwrap(), NaiveDate::from_ymd_opt(2024, 5, 12).unwrap()),
+            chrono_tz::UTC,
+        );
+        let events = (10..=12)
+            .map(|day| {
+                Event::new(
+                    EventId::new("1".to_string()),
+                    Title::new("朝会".to_string()),
+                    DateTimeRange::new(
+                        Utc.with_ymd_and_hms(2024, 5, day, 0, 0, 0).unwrap(),
+                        Utc.with_ymd_and_hms(2024, 5, day, 0, 15, 0).unwrap(),
+                    ),
+                    vec![],
+                )
+                    .with_recurrence(Some(recurrence.clone()))
+            })
+            .collect::<Vec<Event>>();
+
+        FetchedEvents::from(events)
+    }
+
+    fn sink(name: &'static str, recurrence: bool, expected_events: usize) -> MockEventSink {
+        let mut sink = MockEventSink::new();
+        sink.expect_name().return_const(name.to_string());
+        sink.expect_capabilities().return_const(SinkCapabilities { recurrence, ..Default::default() });
+        sink.expect_write()
+            .withf(move |fetched, _| fetched.events().len() == expected_events)
+            .times(1)
+            .returning(move |fetched, _| match name {
+                "broken" => Err(anyhow!("disk full")),
+                _ => Ok(SinkReport { written: fetched.events().len(), ..Default::default() }),
+            });
+
+        sink
+    }
+
+    #[tokio::test]
+    async fn write_all_転写先ごとに反映する() {
+        // Setup: 繰り返し予定を扱える転写先と扱えない転写先、失敗する転写先
+        let sinks: Vec<Box<dyn EventSink>> = vec![
+            Box::new(sink("ics", true, 1)),
+            Box::new(sink("csv", false, 3)),
+            Box::new(sink("broken", false, 3)),
+        ];
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+        );
+
+        // Exercise
+        let outcomes = write_all(&sinks, daily_events(), &period).await;
+
+        // Asserts: 失敗した転写先があっても、他の転写先の結果は返る
+        let names: Vec<&str> = outcomes.iter().map(|outcome| outcome.name.as_str()).collect();
+        assert_eq!(names, vec!["ics", "csv", "broken"]);
+        assert_eq!(outcomes[0].result.as_ref().unwrap().written, 1);
+        assert_eq!(outcomes[1].result.as_ref().unwrap().written, 3);
+        assert!(outcomes[2].result.is_err());
+    }
+
+    #[test]
+    fn plan_all_同期状態を持つ転写先の反映内容を求める() {
+        // Setup: 同期状態を持つ転写先と持たない転写先
+        let mut stateful = MockEventSink::new();
+        stateful.expect_name().return_const("google".to_string());
+        stateful.expect_capabilities().return_const(SinkCapabilities { update: true, delete: true, recurrence: true });
+        stateful.expect_plan()
+            .withf(|fetched, _| fetched.events().len() == 1)
+            .times(1)
+            .returning(|fetched, period| Ok(Some(SyncPlan::new(fetched.clone(), &SyncState::default(), period))));
+        let mut file = MockEventSink::new();
+        file.expect_name().return_const("csv".to_string());
+        file.expect_capabilities().return_const(SinkCapabilities::default());
+        file.expect_plan().times(1).returning(|_, _| Ok(None));
+        let sinks: Vec<Box<dyn EventSink>> = vec![Box::new(stateful), Box::new(file)];
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+        );
+
+        // Exercise
+        let plans = plan_all(&sinks, daily_events(), &period);
+
+        // Asserts: 繰り返し予定を扱える転写先には1件にまとめて渡す
+        assert_eq!(plans.len(), 1);
+        assert_eq!(plans[0].name, "google");
+        assert_eq!(plans[0].result.as_ref().unwrap().as_ref().unwrap().creates.len(), 1);
+    }
+
+    #[test]
+    fn test_sink_report_display() {
+        assert_eq!(SinkReport { written: 3, ..Default::default() }.to_string(), "3 written");
+        assert_eq!(SinkReport { created: 1, updated: 2, ..Default::default() }.to_string(), "1 created, 2 updated");
+        assert_eq!(SinkReport::default().to_string(), "no changes");
+    }
+}
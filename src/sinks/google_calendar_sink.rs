@@ -0,0 +1,348 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+
+use crate::apis::google_calendar::{GoogleCalendarClient, GoogleCalendarEvent};
+use crate::error::Error;
+use crate::models::event::FetchedEvents;
+use crate::models::sync_plan::{DeletionGuard, SyncPlan};
+use crate::models::sync_state::{SyncRecord, SyncState};
+use crate::repositories::sync_state_repository::SyncStateRepository;
+use crate::sinks::event_sink::{EventSink, SinkCapabilities, SinkReport};
+use crate::utils::date_time_range::DateTimeRange;
+
+/// Googleカレンダー。同期状態と突き合わせて、変わった予定だけを反映する
+pub struct GoogleCalendarSink<C: GoogleCalendarClient, S: SyncStateRepository> {
+    client: C,
+    state_repository: S,
+    deletion_guard: DeletionGuard,
+}
+impl<C, S> GoogleCalendarSink<C, S>
+where
+    C: GoogleCalendarClient + Send + Sync,
+    S: SyncStateRepository + Send + Sync,
+{
+    pub fn new(client: C, state_repository: S) -> Self {
+        Self { client, state_repository, deletion_guard: DeletionGuard::default() }
+    }
+
+    pub fn with_deletion_guard(mut self, deletion_guard: DeletionGuard) -> Self {
+        self.deletion_guard = deletion_guard;
+        self
+    }
+
+    async fn apply(&self, plan: &SyncPlan, state: &mut SyncState) -> Result<(), anyhow::Error> {
+        for event in &plan.creates {
+            let created = self.client
+                .insert(GoogleCalendarEvent::from(event))
+                .await
+                .map_err(|e| Error::sink(event.id.as_str(), e))?;
+            let google_event_id = created.id.ok_or_else(|| Error::sink(event.id.as_str(), "Google Calendar returned no event id"))?;
+            state.upsert(SyncRecord::new(event, google_event_id));
+        }
+        for update in &plan.updates {
+            let google_event_id = update.record.google_event_id.clone();
+            self.client
+                .update(google_event_id.clone(), GoogleCalendarEvent::from(&update.event))
+                .await
+                .map_err(|e| Error::sink(update.event.id.as_str(), e))?;
+            state.upsert(SyncRecord::new(&update.event, google_event_id));
+        }
+        for record in &plan.deletes {
+            match self.client.delete(record.google_event_id.clone()).await {
+                Ok(()) => {}
+                // 転写先で既に削除されている場合は記録だけ消す
+                Err(e) if matches!(e.status(), Some(StatusCode::NOT_FOUND | StatusCode::GONE)) => {}
+                Err(e) => return Err(Error::sink(record.garoon_id.clone(), e).into()),
+            }
+            state.remove(&record.garoon_id());
+        }
+
+        Ok(())
+    }
+}
+#[async_trait]
+impl<C, S> EventSink for GoogleCalendarSink<C, S>
+where
+    C: GoogleCalendarClient + Send + Sync,
+    S: SyncStateRepository + Send + Sync,
+{
+    fn name(&self) -> String {
+        "google".to_string()
+    }
+
+    fn capabilities(&self) -> SinkCapabilities {
+        SinkCapabilities { update: true, delete: true, recurrence: true }
+    }
+
+    async fn write(&self, fetched: &FetchedEvents, period: &DateTimeRange) -> Result<SinkReport, anyhow::Error> {
+        let mut state = self.state_repository.load()?;
+        let plan = SyncPlan::new(fetched.clone(), &state, period);
+        plan.check_deletions(&self.deletion_guard)?;
+        plan.refresh(&mut state);
+
+        // 途中で失敗しても反映済みの分は記録し、次回の実行で重複登録しないようにする
+        let result = self.apply(&plan, &mut state).await;
+        self.state_repository.save(&state)?;
+        result?;
+
+        Ok(SinkReport { created: plan.creates.len(), updated: plan.updates.len(), deleted: plan.deletes.len(), ..Default::default() })
+    }
+
+    fn plan(&self, fetched: &FetchedEvents, period: &DateTimeRange) -> Result<Option<SyncPlan>, anyhow::Error> {
+        let state = self.state_repository.load()?;
+
+        Ok(Some(SyncPlan::new(fetched.clone(), &state, period)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::apis::garoon::{GaroonDateTime, GaroonEvent};
+    use crate::apis::google_calendar::MockGoogleCalendarClient;
+    use crate::models::event::{Event, FetchedEvents};
+    use crate::models::sync_plan::DeletionGuard;
+    use crate::models::sync_state::{SyncRecord, SyncState};
+    use crate::repositories::sync_state_repository::MockSyncStateRepository;
+    use crate::sinks::event_sink::EventSink;
+    use crate::sinks::google_calendar_sink::GoogleCalendarSink;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn garoon_event(id: &str, subject: &str) -> GaroonEvent {
+        GaroonEvent {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            attendees: vec![],
+            start: GaroonDateTime {
+                date_time: "2021-01-01T00:00:00+09:00".to_string(),
+                time_zone: "Asia/Tokyo".to_string(),
+            },
+            end: Some(GaroonDateTime {
+                date_time: "2021-01-01T01:00:00+09:00".to_string(),
+                time_zone: "Asia/Tokyo".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// 取得期間(現在から1週間)内に開始する予定
+    fn upcoming_garoon_event(id: &str, subject: &str) -> GaroonEvent {
+        let tomorrow = chrono::Utc::now().date_naive().succ_opt().unwrap();
+        let start = tomorrow.and_hms_opt(9, 0, 0).unwrap().and_utc();
+        GaroonEvent {
+            start: GaroonDateTime {
+                date_time: start.to_rfc3339(),
+                time_zone: "UTC".to_string(),
+            },
+            end: Some(GaroonDateTime {
+                date_time: (start + chrono::Duration::hours(1)).to_rfc3339(),
+                time_zone: "UTC".to_string(),
+            }),
+            ..garoon_event(id, subject)
+        }
+    }
+
+    /// 現在から1週間
+    fn period() -> DateTimeRange {
+        let now = chrono::Utc::now();
+        DateTimeRange::new(now, now + chrono::Duration::weeks(1))
+    }
+
+    fn fetched(events: Vec<GaroonEvent>) -> FetchedEvents {
+        events.into_iter().collect()
+    }
+
+    fn synced_state(events: Vec<GaroonEvent>) -> SyncState {
+        let mut state = SyncState::default();
+        for event in events {
+            let google_event_id = format!("google-{}", event.id);
+            state.upsert(SyncRecord::new(&Event::try_from(event).unwrap(), google_event_id));
+        }
+
+        state
+    }
+
+    #[tokio::test]
+    async fn test_write_正常系() {
+        // Setup: GoogleCalendarClientのモックを作成し、取得した予定が1件登録されることを設定する
+        let mut google_client = MockGoogleCalendarClient::new();
+        google_client.expect_insert()
+            .withf(|event| event.summary == "会議" && event.start.date_time.as_deref() == Some("2020-12-31T15:00:00Z"))
+            .times(1)
+            .returning(|mut event| {
+                event.id = Some("google-1".to_string());
+                Ok(event)
+            });
+        // 同期状態が空で、登録した予定の対応が保存されることを設定する
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).returning(|| Ok(SyncState::default()));
+        state_repository.expect_save()
+            .withf(|state| state.len() == 1 && state.records().all(|record| record.google_event_id == "google-1"))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        // Exercise: GoogleCalendarSinkを作成し、writeメソッドを呼び出す
+        let sink = GoogleCalendarSink::new(google_client, state_repository);
+        let result = sink.write(&fetched(vec![garoon_event("1", "会議")]), &period()).await;
+
+        // Assert: 登録した件数が返ることを検証する
+        assert_eq!(result.unwrap().created, 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_同期済みの予定は重複登録しない() {
+        // Setup: 同期済み・変更あり・新規の予定を反映するよう設定する
+        let mut state = SyncState::default();
+        state.upsert(SyncRecord::new(&Event::try_from(garoon_event("1", "会議")).unwrap(), "google-1".to_string()));
+        state.upsert(SyncRecord::new(&Event::try_from(garoon_event("2", "打ち合わせ")).unwrap(), "google-2".to_string()));
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).return_once(move || Ok(state));
+        state_repository.expect_save().times(1).returning(|_| Ok(()));
+        // 新規の予定だけが登録され、変更された予定だけが更新されることを設定する
+        let mut google_client = MockGoogleCalendarClient::new();
+        google_client.expect_insert()
+            .withf(|event| event.summary == "定例")
+            .times(1)
+            .returning(|mut event| {
+                event.id = Some("google-3".to_string());
+                Ok(event)
+            });
+        google_client.expect_update()
+            .withf(|event_id, event| event_id == "google-2" && event.summary == "打ち合わせ(変更)")
+            .times(1)
+            .returning(|_, event| Ok(event));
+        let events = fetched(vec![
+            garoon_event("1", "会議"),
+            garoon_event("2", "打ち合わせ(変更)"),
+            garoon_event("3", "定例"),
+        ]);
+
+        // Exercise
+        let sink = GoogleCalendarSink::new(google_client, state_repository);
+        let result = sink.write(&events, &period()).await;
+
+        // Asserts
+        let report = result.unwrap();
+        assert_eq!((report.created, report.updated, report.deleted), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_write_期間を記録していない予定を記録し直す() {
+        // Setup: 期間を記録する前の同期状態
+        let mut record = SyncRecord::new(&Event::try_from(garoon_event("1", "会議")).unwrap(), "google-1".to_string());
+        record.duration = None;
+        let mut state = SyncState::default();
+        state.upsert(record);
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).return_once(move || Ok(state));
+        state_repository.expect_save()
+            .withf(|state| state.records().all(|record| record.duration.is_some()))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        // Exercise
+        let sink = GoogleCalendarSink::new(MockGoogleCalendarClient::new(), state_repository);
+        let result = sink.write(&fetched(vec![garoon_event("1", "会議")]), &period()).await;
+
+        // Asserts: 転写先は変更しない
+        let report = result.unwrap();
+        assert_eq!((report.created, report.updated, report.deleted), (0, 0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_write_登録失敗時も反映済みの状態を保存する() {
+        // Setup: 2件目の登録結果にIDが含まれず、失敗扱いになるよう設定する
+        let mut google_client = MockGoogleCalendarClient::new();
+        google_client.expect_insert()
+            .times(2)
+            .returning(|mut event| {
+                if event.summary == "打ち合わせ" {
+                    return Ok(event);
+                }
+                event.id = Some("google-1".to_string());
+                Ok(event)
+            });
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).returning(|| Ok(SyncState::default()));
+        state_repository.expect_save()
+            .withf(|state| state.len() == 1)
+            .times(1)
+            .returning(|_| Ok(()));
+
+        // Exercise
+        let sink = GoogleCalendarSink::new(google_client, state_repository);
+        let result = sink.write(&fetched(vec![garoon_event("1", "会議"), garoon_event("2", "打ち合わせ")]), &period()).await;
+
+        // Asserts
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_削除された予定を転写先からも削除する() {
+        // Setup: 同期済みの2件のうち1件だけを反映するよう設定する
+        let state = synced_state(vec![upcoming_garoon_event("1", "会議"), upcoming_garoon_event("2", "打ち合わせ")]);
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).return_once(move || Ok(state));
+        state_repository.expect_save()
+            .withf(|state| state.len() == 1 && state.records().all(|record| record.google_event_id == "google-1"))
+            .times(1)
+            .returning(|_| Ok(()));
+        // 取得できなかった予定だけが削除されることを設定する
+        let mut google_client = MockGoogleCalendarClient::new();
+        google_client.expect_delete()
+            .withf(|event_id| event_id == "google-2")
+            .times(1)
+            .returning(|_| Ok(()));
+
+        // Exercise
+        let sink = GoogleCalendarSink::new(google_client, state_repository)
+            .with_deletion_guard(DeletionGuard::new(0.5, 3));
+        let result = sink.write(&fetched(vec![upcoming_garoon_event("1", "会議")]), &period()).await;
+
+        // Asserts
+        assert_eq!(result.unwrap().deleted, 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_削除件数が閾値を超えたら中止する() {
+        // Setup: 同期済みの予定があるのに、取得結果が空になるよう設定する
+        let state = synced_state((0..5).map(|i| upcoming_garoon_event(&i.to_string(), "会議")).collect());
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).return_once(move || Ok(state));
+        state_repository.expect_save().times(0);
+        let mut google_client = MockGoogleCalendarClient::new();
+        google_client.expect_delete().times(0);
+
+        // Exercise
+        let sink = GoogleCalendarSink::new(google_client, state_repository)
+            .with_deletion_guard(DeletionGuard::new(0.5, 3));
+        let result = sink.write(&FetchedEvents::default(), &period()).await;
+
+        // Asserts
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plan_転写先に反映しない() {
+        // Setup: 同期済みの予定の変更と新規の予定を取得したことにする
+        let state = synced_state(vec![upcoming_garoon_event("1", "会議")]);
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).return_once(move || Ok(state));
+        state_repository.expect_save().times(0);
+        let mut google_client = MockGoogleCalendarClient::new();
+        google_client.expect_insert().times(0);
+        google_client.expect_update().times(0);
+        google_client.expect_delete().times(0);
+        let events = fetched(vec![upcoming_garoon_event("1", "会議(変更)"), upcoming_garoon_event("2", "打ち合わせ")]);
+
+        // Exercise
+        let sink = GoogleCalendarSink::new(google_client, state_repository);
+        let result = sink.plan(&events, &period());
+
+        // Asserts
+        let plan = result.unwrap().unwrap();
+        assert_eq!(plan.creates.len(), 1);
+        assert_eq!(plan.updates.len(), 1);
+        assert_eq!(plan.updates[0].changes().len(), 1);
+        assert!(plan.deletes.is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::models::event::FetchedEvents;
+use crate::services::export_ics_service::export_ics;
+use crate::sinks::event_sink::{EventSink, SinkCapabilities, SinkReport};
+use crate::utils::date_time_range::DateTimeRange;
+
+/// iCalendar形式のファイル。毎回全ての予定を書き出す
+pub struct IcsSink {
+    path: PathBuf,
+}
+impl IcsSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+#[async_trait]
+impl EventSink for IcsSink {
+    fn name(&self) -> String {
+        format!("ics ({})", self.path.display())
+    }
+
+    fn capabilities(&self) -> SinkCapabilities {
+        SinkCapabilities { recurrence: true, ..Default::default() }
+    }
+
+    async fn write(&self, fetched: &FetchedEvents, _period: &DateTimeRange) -> Result<SinkReport, anyhow::Error> {
+        export_ics(fetched.events(), &self.path, Utc::now())?;
+
+        Ok(SinkReport { written: fetched.events().len(), ..Default::default() })
+    }
+}
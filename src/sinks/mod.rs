@@ -0,0 +1,6 @@
+pub mod caldav_sink;
+pub mod csv_sink;
+pub mod event_sink;
+pub mod google_calendar_sink;
+pub mod ics_sink;
+pub mod outlook_sink;
\ No newline at end of file
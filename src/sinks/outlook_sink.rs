@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+
+use crate::apis::microsoft_graph::{GraphEvent, GraphRequest, GraphResponse, MicrosoftGraphClient};
+use crate::error::Error;
+use crate::models::event::{Event, FetchedEvents};
+use crate::models::sync_plan::{DeletionGuard, SyncPlan};
+use crate::models::sync_state::{SyncRecord, SyncState};
+use crate::repositories::sync_state_repository::SyncStateRepository;
+use crate::sinks::event_sink::{EventSink, SinkCapabilities, SinkReport};
+use crate::utils::date_time_range::DateTimeRange;
+
+/// Microsoft Graph経由のOutlookカレンダー。同期状態と突き合わせ、変わった予定だけをバッチでまとめて反映する。
+/// 同期状態の`google_event_id`には、Graphの予定IDを記録する
+pub struct OutlookSink<C: MicrosoftGraphClient, S: SyncStateRepository> {
+    client: C,
+    state_repository: S,
+    deletion_guard: DeletionGuard,
+}
+
+/// 1件分の反映内容
+enum Change<'a> {
+    Create(&'a Event),
+    Update(&'a Event, String),
+    Delete(&'a SyncRecord),
+}
+impl Change<'_> {
+    fn request(&self) -> GraphRequest {
+        match self {
+            Change::Create(event) => GraphRequest::Create(GraphEvent::from(*event)),
+            Change::Update(event, event_id) => GraphRequest::Update { event_id: event_id.clone(), event: GraphEvent::from(*event) },
+            Change::Delete(record) => GraphRequest::Delete { event_id: record.google_event_id.clone() },
+        }
+    }
+}
+
+impl<C, S> OutlookSink<C, S>
+where
+    C: MicrosoftGraphClient + Send + Sync,
+    S: SyncStateRepository + Send + Sync,
+{
+    pub fn new(client: C, state_repository: S) -> Self {
+        Self { client, state_repository, deletion_guard: DeletionGuard::default() }
+    }
+
+    pub fn with_deletion_guard(mut self, deletion_guard: DeletionGuard) -> Self {
+        self.deletion_guard = deletion_guard;
+        self
+    }
+
+    /// 成功した分を同期状態に記録し、失敗した分のエラーを返す。
+    /// Outlookで削除されていて更新できなかった予定は、作り直すために`recreates`に入れる
+    fn record<'a>(
+        changes: Vec<Change<'a>>,
+        responses: Vec<GraphResponse>,
+        state: &mut SyncState,
+        recreates: &mut Vec<&'a Event>,
+    ) -> Vec<Error> {
+        let mut errors = vec![];
+        for (change, response) in changes.into_iter().zip(responses) {
+            let gone = matches!(response.status, StatusCode::NOT_FOUND | StatusCode::GONE);
+            match change {
+                Change::Create(event) if response.status.is_success() => match response.event_id() {
+                    Some(event_id) => state.upsert(SyncRecord::new(event, event_id)),
+                    None => errors.push(Error::sink(event.id.as_str(), "Microsoft Graph returned no event id")),
+                },
+                Change::Update(event, event_id) if response.status.is_success() => state.upsert(SyncRecord::new(event, event_id)),
+                Change::Update(event, _) if gone => recreates.push(event),
+                // 転写先で既に削除されている場合は記録だけ消す
+                Change::Delete(record) if response.status.is_success() || gone => {
+                    state.remove(&record.garoon_id());
+                }
+                Change::Create(event) | Change::Update(event, _) => errors.push(Error::sink(event.id.as_str(), response.error_message())),
+                Change::Delete(record) => errors.push(Error::sink(record.garoon_id.clone(), response.error_message())),
+            }
+        }
+
+        errors
+    }
+}
+#[async_trait]
+impl<C, S> EventSink for OutlookSink<C, S>
+where
+    C: MicrosoftGraphClient + Send + Sync,
+    S: SyncStateRepository + Send + Sync,
+{
+    fn name(&self) -> String {
+        "outlook".to_string()
+    }
+
+    /// Graphの繰り返し予定はRRULEと形式が異なるので、展開された回ごとに反映する。
+    /// 回ごとに別の予定として同期状態に記録し、Garoonの予定IDの拡張プロパティにも回の開始日時を付ける
+    fn capabilities(&self) -> SinkCapabilities {
+        SinkCapabilities { update: true, delete: true, recurrence: false }
+    }
+
+    async fn write(&self, fetched: &FetchedEvents, period: &DateTimeRange) -> Result<SinkReport, anyhow::Error> {
+        let mut state = self.state_repository.load()?;
+        let plan = SyncPlan::new(fetched.clone().key_instances(), &state, period);
+        plan.check_deletions(&self.deletion_guard)?;
+        plan.refresh(&mut state);
+
+        // 同期状態を失った場合などに重複登録しないよう、Garoonの予定IDを記録した予定があれば更新にする
+        let synced: HashMap<String, String> = match plan.creates.is_empty() {
+            true => HashMap::new(),
+            false => self.client.list_synced().await?,
+        };
+        let changes: Vec<Change> = plan
+            .creates
+            .iter()
+            .map(|event| match synced.get(event.id.as_str()) {
+                Some(event_id) => Change::Update(event, event_id.clone()),
+                None => Change::Create(event),
+            })
+            .chain(plan.updates.iter().map(|update| Change::Update(&update.event, update.record.google_event_id.clone())))
+            .chain(plan.deletes.iter().map(Change::Delete))
+            .collect();
+
+        let mut errors = vec![];
+        let mut recreates = vec![];
+        if !changes.is_empty() {
+            let responses = self.client.batch(changes.iter().map(Change::request).collect()).await?;
+            errors.extend(Self::record(changes, responses, &mut state, &mut recreates));
+        }
+        if !recreates.is_empty() {
+            let changes: Vec<Change> = recreates.iter().map(|event| Change::Create(event)).collect();
+            // 作り直しは新規登録なので、ここで`recreates`が増えることはない
+            let responses = self.client.batch(changes.iter().map(Change::request).collect()).await?;
+            errors.extend(Self::record(changes, responses, &mut state, &mut vec![]));
+        }
+
+        // 一部が失敗しても反映済みの分は記録し、次回の実行で重複登録しないようにする
+        self.state_repository.save(&state)?;
+        if let Some(error) = errors.into_iter().next() {
+            return Err(error.into());
+        }
+
+        Ok(SinkReport { created: plan.creates.len(), updated: plan.updates.len(), deleted: plan.deletes.len(), ..Default::default() })
+    }
+
+    fn plan(&self, fetched: &FetchedEvents, period: &DateTimeRange) -> Result<Option<SyncPlan>, anyhow::Error> {
+        let state = self.state_repository.load()?;
+
+        Ok(Some(SyncPlan::new(fetched.clone().key_instances(), &state, period)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::StatusCode;
+
+    use crate::apis::garoon::{GaroonDateTime, GaroonEvent};
+    use crate::apis::microsoft_graph::{GraphRequest, GraphResponse, MockMicrosoftGraphClient};
+    use crate::models::event::{Event, EventId, FetchedEvents, Title};
+    use crate::models::recurrence::{Frequency, Recurrence};
+    use crate::models::sync_state::{SyncRecord, SyncState};
+    use crate::repositories::sync_state_repository::MockSyncStateRepository;
+    use crate::sinks::event_sink::{EventSink, SinkReport};
+    use crate::sinks::outlook_sink::OutlookSink;
+    use crate::utils::date_range::DateRange;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    /// 取得期間(現在から1週間)内に開始する予定
+    fn upcoming_event(id: &str, subject: &str) -> Event {
+        let tomorrow = chrono::Utc::now().date_naive().succ_opt().unwrap();
+        let start = tomorrow.and_hms_opt(9, 0, 0).unwrap().and_utc();
+        let garoon_event = GaroonEvent {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            attendees: vec![],
+            start: GaroonDateTime { date_time: start.to_rfc3339(), time_zone: "UTC".to_string() },
+            end: Some(GaroonDateTime {
+                date_time: (start + chrono::Duration::hours(1)).to_rfc3339(),
+                time_zone: "UTC".to_string(),
+            }),
+            ..Default::default()
+        };
+
+        Event::try_from(garoon_event).unwrap()
+    }
+
+    /// 現在から1週間
+    fn period() -> DateTimeRange {
+        let now = chrono::Utc::now();
+        DateTimeRange::new(now, now + chrono::Duration::weeks(1))
+    }
+
+    fn synced_state(events: Vec<Event>) -> SyncState {
+        let mut state = SyncState::default();
+        for event in events {
+            let graph_event_id = format!("graph-{}", event.id.as_str());
+            state.upsert(SyncRecord::new(&event, graph_event_id));
+        }
+
+        state
+    }
+
+    fn response(status: StatusCode, body: serde_json::Value) -> GraphResponse {
+        GraphResponse::new(status, body)
+    }
+
+    fn state_repository(state: SyncState, saved: impl Fn(&SyncState) -> bool + Send + 'static) -> MockSyncStateRepository {
+        let mut state_repository = MockSyncStateRepository::new();
+        state_repository.expect_load().times(1).return_once(move || Ok(state));
+        state_repository.expect_save().withf(saved).times(1).returning(|_| Ok(()));
+
+        state_repository
+    }
+
+    #[tokio::test]
+    async fn test_write_正常系() {
+        // Setup: 新規・変更あり・削除の予定を1回のバッチで反映することを設定する
+        let mut graph_client = MockMicrosoftGraphClient::new();
+        graph_client.expect_list_synced().times(1).returning(|| Ok(HashMap::new()));
+        graph_client.expect_batch()
+            .withf(|requests| {
+                matches!(
+                    requests.as_slice(),
+                    [
+                        GraphRequest::Create(created),
+                        GraphRequest::Update { event_id: updated, .. },
+                        GraphRequest::Delete { event_id: deleted },
+                    ] if created.subject == "面談" && updated == "graph-1" && deleted == "graph-2"
+                )
+            })
+            .times(1)
+            .returning(|_| Ok(vec![
+                response(StatusCode::CREATED, serde_json::json!({ "id": "graph-3" })),
+                response(StatusCode::OK, serde_json::json!({ "id": "graph-1" })),
+                response(StatusCode::NO_CONTENT, serde_json::Value::Null),
+            ]));
+        let state = synced_state(vec![upcoming_event("1", "会議"), upcoming_event("2", "打ち合わせ")]);
+        let state_repository = state_repository(state, |state| {
+            state.len() == 2 && state.get(&EventId::new("3".to_string())).is_some_and(|record| record.google_event_id == "graph-3")
+        });
+        let fetched = FetchedEvents::from(vec![upcoming_event("1", "会議(変更)"), upcoming_event("3", "面談")]);
+
+        // Exercise
+        let sink = OutlookSink::new(graph_client, state_repository);
+        let result = sink.write(&fetched, &period()).await;
+
+        // Asserts
+        let report = result.unwrap();
+        assert_eq!((report.created, report.updated, report.deleted), (1, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_write_同期状態がなくても重複登録しない() {
+        // Setup: 同期状態は空だが、Garoonの予定IDを記録した予定がOutlookにあるよう設定する
+        let mut graph_client = MockMicrosoftGraphClient::new();
+        graph_client.expect_list_synced()
+            .times(1)
+            .returning(|| Ok(HashMap::from([("1".to_string(), "graph-1".to_string())])));
+        graph_client.expect_batch()
+            .withf(|requests| matches!(requests.as_slice(), [GraphRequest::Update { event_id, .. }] if event_id == "graph-1"))
+            .times(1)
+            .returning(|_| Ok(vec![response(StatusCode::OK, serde_json::json!({ "id": "graph-1" }))]));
+        let state_repository = state_repository(SyncState::default(), |state| state.len() == 1);
+
+        // Exercise
+        let sink = OutlookSink::new(graph_client, state_repository);
+        let result = sink.write(&FetchedEvents::from(vec![upcoming_event("1", "会議")]), &period()).await;
+
+        // Asserts
+        assert_eq!(result.unwrap().created, 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_outlookで削除された予定は作り直す() {
+        // Setup: 更新すると404が返り、作り直すと新しいIDが返るよう設定する
+        let mut graph_client = MockMicrosoftGraphClient::new();
+        graph_client.expect_batch()
+            .withf(|requests| matches!(requests.as_slice(), [GraphRequest::Update { .. }]))
+            .times(1)
+            .returning(|_| Ok(vec![response(StatusCode::NOT_FOUND, serde_json::Value::Null)]));
+        graph_client.expect_batch()
+            .withf(|requests| matches!(requests.as_slice(), [GraphRequest::Create(_)]))
+            .times(1)
+            .returning(|_| Ok(vec![response(StatusCode::CREATED, serde_json::json!({ "id": "graph-new" }))]));
+        let state_repository = state_repository(synced_state(vec![upcoming_event("1", "会議")]), |state| {
+            state.records().all(|record| record.google_event_id == "graph-new")
+        });
+
+        // Exercise
+        let sink = OutlookSink::new(graph_client, state_repository);
+        let result = sink.write(&FetchedEvents::from(vec![upcoming_event("1", "会議(変更)")]), &period()).await;
+
+        // Asserts
+        assert_eq!(result.unwrap().updated, 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_繰り返し予定は回ごとに同期する() {
+        // Setup: 明日から3日間、毎日繰り返す予定を展開した3回分
+        let tomorrow = chrono::Utc::now().date_naive().succ_opt().unwrap();
+        let recurrence = Recurrence::new(Frequency::Daily, DateRange::new(tomorrow, tomorrow + chrono::Duration::days(2)), chrono_tz::UTC);
+        let instances: Vec<Event> = (0..3)
+            .map(|day| {
+                let start = (tomorrow + chrono::Duration::days(day)).and_hms_opt(9, 0, 0).unwrap().and_utc();
+                Event::new(
+                    EventId::new("1".to_string()),
+                    Title::new("朝会".to_string()),
+                    DateTimeRange::new(start, start + chrono::Duration::minutes(15)),
+                    vec![],
+                )
+                    .with_recurrence(Some(recurrence.clone()))
+            })
+            .collect();
+        let fetched = FetchedEvents::from(instances);
+        // 1回目の同期で3回分を登録し、2回目の同期では何も送らないことを設定する
+        let mut graph_client = MockMicrosoftGraphClient::new();
+        graph_client.expect_list_synced().times(1).returning(|| Ok(HashMap::new()));
+        graph_client.expect_batch()
+            .withf(|requests| requests.len() == 3 && requests.iter().all(|request| matches!(request, GraphRequest::Create(_))))
+            .times(1)
+            .returning(|requests| {
+                Ok((0..requests.len()).map(|i| response(StatusCode::CREATED, serde_json::json!({ "id": format!("graph-{}", i) }))).collect())
+            });
+        let saved = Arc::new(Mutex::new(SyncState::default()));
+        let mut state_repository = MockSyncStateRepository::new();
+        let loaded = saved.clone();
+        state_repository.expect_load().times(2).returning(move || Ok(loaded.lock().unwrap().clone()));
+        let stored = saved.clone();
+        state_repository.expect_save().times(2).returning(move |state| {
+            *stored.lock().unwrap() = state.clone();
+            Ok(())
+        });
+        let sink = OutlookSink::new(graph_client, state_repository);
+
+        // Exercise
+        let first = sink.write(&fetched, &period()).await.unwrap();
+        let second = sink.write(&fetched, &period()).await.unwrap();
+
+        // Asserts: 回ごとに別のGraphの予定IDを記録する
+        assert_eq!(first.created, 3);
+        assert_eq!(second, SinkReport::default());
+        let state = saved.lock().unwrap();
+        let mut event_ids: Vec<&str> = state.records().map(|record| record.google_event_id.as_str()).collect();
+        event_ids.sort();
+        assert_eq!(event_ids, vec!["graph-0", "graph-1", "graph-2"]);
+        assert!(state.records().all(|record| record.garoon_id.starts_with("1/")));
+    }
+
+    #[tokio::test]
+    async fn test_write_一部が失敗しても反映した分は記録する() {
+        // Setup: 新規登録は失敗し、削除は成功するよう設定する
+        let mut graph_client = MockMicrosoftGraphClient::new();
+        graph_client.expect_list_synced().returning(|| Ok(HashMap::new()));
+        graph_client.expect_batch()
+            .times(1)
+            .returning(|_| Ok(vec![
+                response(StatusCode::BAD_REQUEST, serde_json::json!({ "error": { "message": "bad request" } })),
+                response(StatusCode::NO_CONTENT, serde_json::Value::Null),
+            ]));
+        let state_repository = state_repository(synced_state(vec![upcoming_event("2", "打ち合わせ")]), |state| state.is_empty());
+
+        // Exercise
+        let sink = OutlookSink::new(graph_client, state_repository);
+        let result = sink.write(&FetchedEvents::from(vec![upcoming_event("3", "面談")]), &period()).await;
+
+        // Asserts
+        let message = result.unwrap_err().to_string();
+        assert_eq!(message, "failed to write `3` to the sink: Microsoft Graph returned 400 Bad Request: bad request");
+    }
+}
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use chrono_tz::Tz;
+
+use crate::apis::caldav::CalDavClient;
+use crate::models::event::FetchedEvents;
+use crate::sources::event_source::EventSource;
+use crate::sources::ics_parser::parse_ics;
+use crate::utils::date_time_range::DateTimeRange;
+
+/// CalDAVのカレンダーコレクションの予定
+pub struct CalDavSource {
+    client: CalDavClient,
+    collection_url: String,
+    time_zone: Tz,
+}
+impl CalDavSource {
+    pub fn new(client: CalDavClient, collection_url: String) -> Self {
+        Self { client, collection_url, time_zone: Tz::UTC }
+    }
+
+    /// タイムゾーンの指定がない日時を解釈するタイムゾーン。省略時はUTC
+    pub fn with_time_zone(mut self, time_zone: Tz) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+}
+#[async_trait]
+impl EventSource for CalDavSource {
+    fn name(&self) -> String {
+        format!("caldav ({})", self.collection_url)
+    }
+
+    async fn fetch(&self, period: &DateTimeRange) -> Result<FetchedEvents, anyhow::Error> {
+        let mut fetched = FetchedEvents::default();
+        for object in self.client.calendar_query(&self.collection_url, period, true).await? {
+            let parsed = parse_ics(&object.calendar_data, "caldav:", self.time_zone);
+            fetched.events.extend(parsed.events);
+            fetched.skipped.extend(parsed.skipped);
+        }
+
+        Ok(fetched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use crate::apis::caldav::CalDavClient;
+    use crate::sources::caldav_source::CalDavSource;
+    use crate::sources::event_source::EventSource;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    #[tokio::test]
+    async fn fetch_展開された繰り返し予定() {
+        // Setup: サーバーが展開した繰り返し予定の2回分を返すよう設定する
+        let mock_server = MockServer::start().await;
+        let multistatus = r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/work/standup.ics</d:href>
+    <d:propstat><d:prop><c:calendar-data>BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:standup
+RECURRENCE-ID:20240510T000000Z
+DTSTART:20240510T000000Z
+DURATION:PT15M
+SUMMARY:朝会
+END:VEVENT
+BEGIN:VEVENT
+UID:standup
+RECURRENCE-ID:20240513T000000Z
+DTSTART:20240513T000000Z
+DURATION:PT15M
+SUMMARY:朝会
+END:VEVENT
+END:VCALENDAR
+</c:calendar-data></d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        Mock::given(method("REPORT"))
+            .respond_with(ResponseTemplate::new(207).set_body_string(multistatus))
+            .mount(&mock_server)
+            .await;
+        let source = CalDavSource::new(CalDavClient::new(), format!("{}/work/", mock_server.uri()));
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+        );
+
+        // Exercise
+        let result = source.fetch(&period).await;
+
+        // Asserts: 回ごとに別の予定になる
+        let ids: Vec<String> = result.unwrap().events().iter().map(|event| event.id.as_str().to_string()).collect();
+        assert_eq!(ids, vec!["caldav:standup/20240510T000000Z", "caldav:standup/20240513T000000Z"]);
+    }
+}
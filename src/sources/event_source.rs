@@ -0,0 +1,95 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::future::try_join_all;
+#[cfg(test)]
+use mockall::automock;
+
+use crate::models::event::FetchedEvents;
+use crate::utils::date_time_range::DateTimeRange;
+
+/// 予定の取得元
+#[cfg_attr(test, automock)]
+#[async_trait]
+pub trait EventSource: Send + Sync {
+    /// エラーの表示に使う名前
+    fn name(&self) -> String;
+
+    /// `period`と重なる予定。繰り返し予定は展開された回ごとに返す
+    async fn fetch(&self, period: &DateTimeRange) -> Result<FetchedEvents, anyhow::Error>;
+}
+
+/// 全ての取得元から並行して取得し、1つにまとめる。
+/// 一部だけ取得できた結果で同期すると、取得できなかった予定を削除してしまうので、1つでも失敗したらエラーにする
+pub async fn fetch_all(sources: &[Box<dyn EventSource>], period: &DateTimeRange) -> Result<FetchedEvents, anyhow::Error> {
+    let fetches = sources.iter().map(|source| async move {
+        source.fetch(period).await.with_context(|| format!("failed to fetch events from {}", source.name()))
+    });
+
+    let mut fetched = FetchedEvents::default();
+    for source_fetched in try_join_all(fetches).await? {
+        fetched.events.extend(source_fetched.events);
+        fetched.skipped.extend(source_fetched.skipped);
+    }
+
+    Ok(fetched)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use chrono::{TimeZone, Utc};
+
+    use crate::models::event::{Event, EventId, FetchedEvents, Title};
+    use crate::sources::event_source::{fetch_all, EventSource, MockEventSource};
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn period() -> DateTimeRange {
+        DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+        )
+    }
+
+    fn source(name: &'static str, ids: &'static [&'static str]) -> MockEventSource {
+        let mut source = MockEventSource::new();
+        source.expect_name().return_const(name.to_string());
+        source.expect_fetch().times(1).returning(move |period| {
+            if ids.is_empty() {
+                return Err(anyhow!("connection refused"));
+            }
+            let events = ids
+                .iter()
+                .map(|id| Event::new(EventId::new(id.to_string()), Title::new("予定".to_string()), period.clone(), vec![]))
+                .collect::<Vec<Event>>();
+            Ok(FetchedEvents::from(events))
+        });
+
+        source
+    }
+
+    #[tokio::test]
+    async fn fetch_all_取得元の予定をまとめる() {
+        // Setup
+        let sources: Vec<Box<dyn EventSource>> = vec![Box::new(source("garoon", &["1", "2"])), Box::new(source("ics", &["ics:holiday"]))];
+
+        // Exercise
+        let result = fetch_all(&sources, &period()).await;
+
+        // Asserts
+        let ids: Vec<String> = result.unwrap().events().iter().map(|event| event.id.as_str().to_string()).collect();
+        assert_eq!(ids, vec!["1", "2", "ics:holiday"]);
+    }
+
+    #[tokio::test]
+    async fn fetch_all_取得できない取得元があればエラー() {
+        // Setup
+        let sources: Vec<Box<dyn EventSource>> = vec![Box::new(source("garoon", &["1"])), Box::new(source("caldav", &[]))];
+
+        // Exercise
+        let result = fetch_all(&sources, &period()).await;
+
+        // Asserts
+        let message = format!("{:#}", result.unwrap_err());
+        assert!(message.starts_with("failed to fetch events from caldav"), "{}", message);
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use chrono::Duration;
+use futures::{stream, StreamExt, TryStreamExt};
+
+use crate::apis::garoon::{GaroonEvent, GaroonGetEventsClient, GaroonGetEventsOptions, GaroonGetEventsRequest, GaroonTarget};
+use crate::error::Error;
+use crate::models::event::FetchedEvents;
+use crate::sources::event_source::EventSource;
+use crate::utils::date_time_range::DateTimeRange;
+
+/// GaroonのREST APIから予定を取得する
+pub struct GaroonSource<G: GaroonGetEventsClient> {
+    client: G,
+    target: Option<GaroonTarget>,
+    options: GaroonGetEventsOptions,
+    chunking: Option<FetchChunking>,
+}
+
+/// 長い期間を一度に取得するとタイムアウトする場合に、期間を分けて並行して取得する
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FetchChunking {
+    /// 1回の問い合わせで取得する期間
+    pub size: Duration,
+    /// 同時に問い合わせる数の上限
+    pub concurrency: usize,
+}
+impl<G> GaroonSource<G>
+where
+    G: GaroonGetEventsClient + Send + Sync,
+{
+    pub fn new(client: G) -> Self {
+        Self { client, target: None, options: GaroonGetEventsOptions::default(), chunking: None }
+    }
+
+    /// ログインユーザー以外のユーザーや、組織・施設の予定を取得する
+    pub fn with_target(mut self, target: Option<GaroonTarget>) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// キーワードや件数など、Garoon側で絞り込む条件
+    pub fn with_options(mut self, options: GaroonGetEventsOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_chunking(mut self, chunking: Option<FetchChunking>) -> Self {
+        self.chunking = chunking;
+        self
+    }
+
+    /// 複数の期間にまたがる予定は、同じ予定(IDと開始日時が同じ)を1件にまとめる
+    async fn fetch_chunks(&self, period: &DateTimeRange, chunking: &FetchChunking) -> Result<Vec<GaroonEvent>, Error> {
+        let chunks: Vec<Vec<GaroonEvent>> = stream::iter(period.split(chunking.size))
+            .map(|chunk| self.client.get(self.request(chunk)))
+            .buffered(chunking.concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        let mut seen = HashSet::new();
+        let mut events: Vec<GaroonEvent> = chunks
+            .into_iter()
+            .flatten()
+            .filter(|event| seen.insert((event.id.clone(), event.start.date_time.clone())))
+            .collect();
+        if let Some(limit) = self.options.limit {
+            events.truncate(limit);
+        }
+
+        Ok(events)
+    }
+
+    fn request(&self, period: DateTimeRange) -> GaroonGetEventsRequest {
+        GaroonGetEventsRequest::new(period)
+            .with_target(self.target.clone())
+            .with_options(self.options.clone())
+    }
+}
+#[async_trait]
+impl<G> EventSource for GaroonSource<G>
+where
+    G: GaroonGetEventsClient + Send + Sync,
+{
+    fn name(&self) -> String {
+        "garoon".to_string()
+    }
+
+    /// 変換できない予定があっても全体は中断せず、読み飛ばした予定として返す
+    async fn fetch(&self, period: &DateTimeRange) -> Result<FetchedEvents, anyhow::Error> {
+        let garoon_events = match &self.chunking {
+            Some(chunking) => self.fetch_chunks(period, chunking).await?,
+            None => self.client.get(self.request(period.clone())).await?,
+        };
+
+        Ok(garoon_events.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::apis::garoon::{GaroonDateTime, GaroonEvent, GaroonGetEventsOptions, GaroonTarget, GaroonTargetType, MockGaroonGetEventsClient};
+    use crate::models::event::{EventId, Title};
+    use crate::sources::event_source::EventSource;
+    use crate::sources::garoon_source::{FetchChunking, GaroonSource};
+    use crate::utils::date_time_range::DateTimeRange;
+
+    fn garoon_event(id: &str, subject: &str) -> GaroonEvent {
+        GaroonEvent {
+            id: id.to_string(),
+            subject: subject.to_string(),
+            attendees: vec![],
+            start: GaroonDateTime {
+                date_time: "2021-01-01T00:00:00+09:00".to_string(),
+                time_zone: "Asia/Tokyo".to_string(),
+            },
+            end: Some(GaroonDateTime {
+                date_time: "2021-01-01T01:00:00+09:00".to_string(),
+                time_zone: "Asia/Tokyo".to_string(),
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn period() -> DateTimeRange {
+        DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 2, 1, 0, 0, 0).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fetch_正常系() {
+        // Setup: GaroonClientのモックを作成し、指定した期間で問い合わせることを設定する
+        let mut garoon_client = MockGaroonGetEventsClient::new();
+        garoon_client.expect_get()
+            .withf(|request| request.period == period())
+            .times(1)
+            .return_once(|_| Ok(vec![
+                GaroonEvent {
+                    start: GaroonDateTime {
+                        date_time: "2021-01-01T09:00:00+09:00".to_string(),
+                        time_zone: "Asia/Tokyo".to_string(),
+                    },
+                    end: Some(GaroonDateTime {
+                        date_time: "2021-01-01T10:00:00+09:00".to_string(),
+                        time_zone: "Asia/Tokyo".to_string(),
+                    }),
+                    ..garoon_event("1", "会議")
+                }
+            ]));
+
+        // Exercise: GaroonSourceを作成し、fetchメソッドを呼び出す
+        let source = GaroonSource::new(garoon_client);
+        let result = source.fetch(&period()).await;
+
+        // Assert: GaroonEventからEventに変換されていることを検証する
+        let fetched = result.unwrap();
+        let events = fetched.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, Title::new("会議".to_string()));
+        assert_eq!(events[0].span().start.to_rfc3339(), "2021-01-01T00:00:00+00:00");
+        assert_eq!(events[0].span().end.to_rfc3339(), "2021-01-01T01:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_不正な予定は読み飛ばす() {
+        // Setup: 日時が解釈できない予定を含めて返すよう設定する
+        let mut garoon_client = MockGaroonGetEventsClient::new();
+        garoon_client.expect_get()
+            .times(1)
+            .return_once(|_| Ok(vec![
+                GaroonEvent {
+                    start: GaroonDateTime {
+                        date_time: "not a date".to_string(),
+                        time_zone: "Asia/Tokyo".to_string(),
+                    },
+                    ..garoon_event("1", "会議")
+                },
+                garoon_event("2", "打ち合わせ"),
+            ]));
+
+        // Exercise
+        let source = GaroonSource::new(garoon_client);
+        let result = source.fetch(&period()).await;
+
+        // Assert: 不正な予定だけが読み飛ばされていることを検証する
+        let fetched = result.unwrap();
+        assert_eq!(fetched.events().len(), 1);
+        assert_eq!(fetched.events()[0].id, EventId::new("2".to_string()));
+        assert_eq!(fetched.skipped().len(), 1);
+        assert_eq!(fetched.skipped()[0].id, EventId::new("1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_対象を指定() {
+        // Setup: 指定した施設の予定をGaroonに問い合わせることを設定する
+        let target = GaroonTarget::new(GaroonTargetType::Facility, "12".to_string());
+        let expected = Some(target.clone());
+        let mut garoon_client = MockGaroonGetEventsClient::new();
+        garoon_client.expect_get()
+            .withf(move |request| request.target == expected)
+            .times(1)
+            .return_once(|_| Ok(vec![garoon_event("1", "会議")]));
+
+        // Exercise: 対象を指定してfetchメソッドを呼び出す
+        let source = GaroonSource::new(garoon_client).with_target(Some(target));
+        let result = source.fetch(&period()).await;
+
+        // Assert
+        assert_eq!(result.unwrap().events().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_絞り込み条件を指定() {
+        // Setup: 指定した条件でGaroonに問い合わせることを設定する
+        let options = GaroonGetEventsOptions { keyword: Some("定例".to_string()), limit: Some(10), ..Default::default() };
+        let expected = options.clone();
+        let mut garoon_client = MockGaroonGetEventsClient::new();
+        garoon_client.expect_get()
+            .withf(move |request| request.options == expected)
+            .times(1)
+            .return_once(|_| Ok(vec![garoon_event("1", "定例会議")]));
+
+        // Exercise: 条件を指定してfetchメソッドを呼び出す
+        let source = GaroonSource::new(garoon_client).with_options(options);
+        let result = source.fetch(&period()).await;
+
+        // Assert
+        assert_eq!(result.unwrap().events().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_期間を分けて取得() {
+        // Setup: 3日間を1日ずつ取得し、日をまたぐ予定は2日分の問い合わせの両方で返るよう設定する
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2021, 1, 4, 0, 0, 0).unwrap(),
+        );
+        let mut garoon_client = MockGaroonGetEventsClient::new();
+        garoon_client.expect_get()
+            .times(3)
+            .returning(|request| {
+                let events = match request.period.start.format("%d").to_string().as_str() {
+                    "01" => vec![garoon_event("1", "会議"), garoon_event("2", "合宿")],
+                    "02" => vec![garoon_event("2", "合宿")],
+                    _ => vec![garoon_event("3", "打ち上げ")],
+                };
+                Ok(events)
+            });
+
+        // Exercise
+        let source = GaroonSource::new(garoon_client)
+            .with_chunking(Some(FetchChunking { size: chrono::Duration::days(1), concurrency: 2 }));
+        let result = source.fetch(&period).await;
+
+        // Assert: 期間の順に並び、重複した予定は1件にまとまる
+        let ids: Vec<String> = result.unwrap().events().iter().map(|event| event.id.as_str().to_string()).collect();
+        assert_eq!(ids, vec!["1", "2", "3"]);
+    }
+}
@@ -0,0 +1,86 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono_tz::Tz;
+
+use crate::models::event::FetchedEvents;
+use crate::sources::event_source::EventSource;
+use crate::sources::ics_parser::parse_ics;
+use crate::utils::date_time_range::DateTimeRange;
+
+/// 祝日のカレンダーなど、手元のiCalendarファイルの予定
+pub struct IcsFileSource {
+    path: PathBuf,
+    time_zone: Tz,
+}
+impl IcsFileSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), time_zone: Tz::UTC }
+    }
+
+    /// タイムゾーンの指定がない日時を解釈するタイムゾーン。省略時はUTC
+    pub fn with_time_zone(mut self, time_zone: Tz) -> Self {
+        self.time_zone = time_zone;
+        self
+    }
+}
+#[async_trait]
+impl EventSource for IcsFileSource {
+    fn name(&self) -> String {
+        format!("ics ({})", self.path.display())
+    }
+
+    async fn fetch(&self, period: &DateTimeRange) -> Result<FetchedEvents, anyhow::Error> {
+        let content = fs::read_to_string(&self.path).with_context(|| format!("failed to read {}", self.path.display()))?;
+        let mut fetched = parse_ics(&content, "ics:", self.time_zone);
+        fetched.events.retain(|event| period.overlaps(&event.span()));
+
+        Ok(fetched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::sources::event_source::EventSource;
+    use crate::sources::ics_file_source::IcsFileSource;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    #[tokio::test]
+    async fn fetch_期間内の予定だけを返す() {
+        // Setup: 5月と6月の祝日を書いたファイル
+        let path = std::env::temp_dir().join(format!("tensha_ics_source_{}.ics", std::process::id()));
+        fs::write(&path, "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\nUID:20240503\r\nDTSTART;VALUE=DATE:20240503\r\nSUMMARY:憲法記念日\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nUID:20240715\r\nDTSTART;VALUE=DATE:20240715\r\nSUMMARY:海の日\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n").unwrap();
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+        );
+
+        // Exercise
+        let result = IcsFileSource::new(&path).with_time_zone(chrono_tz::Asia::Tokyo).fetch(&period).await;
+        fs::remove_file(&path).unwrap();
+
+        // Asserts
+        let fetched = result.unwrap();
+        assert_eq!(fetched.events().len(), 1);
+        assert_eq!(fetched.events()[0].title.as_str(), "憲法記念日");
+        assert_eq!(fetched.events()[0].id.as_str(), "ics:20240503");
+    }
+
+    #[tokio::test]
+    async fn fetch_ファイルがない() {
+        let period = DateTimeRange::new(Utc::now(), Utc::now() + chrono::Duration::days(1));
+
+        let result = IcsFileSource::new("./no_such_calendar.ics").fetch(&period).await;
+
+        assert!(result.unwrap_err().to_string().starts_with("failed to read ./no_such_calendar.ics"));
+    }
+}
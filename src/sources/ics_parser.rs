@@ -0,0 +1,351 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::error::ParseError;
+use crate::models::event::{Attendee, Event, EventId, EventTime, EventTimeZones, FetchedEvents, SkippedEvent, Title};
+use crate::utils::date_range::DateRange;
+use crate::utils::date_time_range::DateTimeRange;
+use crate::utils::time_zone::parse_time_zone;
+
+/// iCalendar (RFC 5545) のVEVENTを予定に変換する。予定IDはUIDに`id_prefix`を付けたもの。
+/// TZIDもUTCの指定もない日時は`time_zone`の日時として扱う。
+/// 取り消された予定は含めず、展開されていない繰り返し予定は読み飛ばす
+pub fn parse_ics(content: &str, id_prefix: &str, time_zone: Tz) -> FetchedEvents {
+    let mut fetched = FetchedEvents::default();
+    for properties in vevents(content) {
+        let id = event_id(&properties, id_prefix);
+        if property(&properties, "STATUS").is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED")) {
+            continue;
+        }
+        match to_event(id.clone(), &properties, time_zone) {
+            Ok(event) => fetched.events.push(event),
+            Err(error) => fetched.skipped.push(SkippedEvent { id, error }),
+        }
+    }
+
+    fetched
+}
+
+/// 折り返しを戻した1行 (`名前;パラメーター=値:値`)
+#[derive(Debug, PartialEq, Eq)]
+struct ContentLine {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+impl ContentLine {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+}
+
+/// VALARMなど、VEVENTの中の部品のプロパティは含めない
+fn vevents(content: &str) -> Vec<Vec<ContentLine>> {
+    let mut events = vec![];
+    let mut components: Vec<String> = vec![];
+    let mut current = vec![];
+    for line in unfold(content).iter().filter_map(|line| parse_line(line)) {
+        match line.name.as_str() {
+            "BEGIN" => components.push(line.value.to_ascii_uppercase()),
+            "END" => {
+                let ended = components.pop();
+                if ended.as_deref() == Some("VEVENT") {
+                    events.push(std::mem::take(&mut current));
+                }
+            }
+            _ if components.last().map(String::as_str) == Some("VEVENT") => current.push(line),
+            _ => {}
+        }
+    }
+
+    events
+}
+
+/// 行頭の空白で折り返された行をつなげる
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in content.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if line.is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// 引用符の中の`:`や`;`は区切りとして扱わない
+fn parse_line(line: &str) -> Option<ContentLine> {
+    let mut quoted = false;
+    let mut parts = vec![];
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&line[start..i]);
+                start = i + 1;
+            }
+            ':' if !quoted => {
+                parts.push(&line[start..i]);
+                let mut parts = parts.into_iter();
+                let name = parts.next()?.to_ascii_uppercase();
+                let params = parts
+                    .filter_map(|param| param.split_once('='))
+                    .map(|(key, value)| (key.to_ascii_uppercase(), value.trim_matches('"').to_string()))
+                    .collect();
+                return Some(ContentLine { name, params, value: line[i + 1..].to_string() });
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn property<'a>(properties: &'a [ContentLine], name: &str) -> Option<&'a ContentLine> {
+    properties.iter().find(|line| line.name == name)
+}
+
+fn text(properties: &[ContentLine], name: &str) -> Option<String> {
+    property(properties, name).map(|line| unescape(&line.value)).filter(|value| !value.is_empty())
+}
+
+/// 展開された繰り返し予定は回ごとに別の予定として扱うため、RECURRENCE-IDも含める
+fn event_id(properties: &[ContentLine], id_prefix: &str) -> EventId {
+    let uid = property(properties, "UID").map(|line| line.value.as_str()).unwrap_or_default();
+    match property(properties, "RECURRENCE-ID") {
+        Some(recurrence_id) => EventId::new(format!("{}{}/{}", id_prefix, uid, recurrence_id.value)),
+        None => EventId::new(format!("{}{}", id_prefix, uid)),
+    }
+}
+
+fn to_event(id: EventId, properties: &[ContentLine], time_zone: Tz) -> Result<Event, ParseError> {
+    if property(properties, "RRULE").is_some() {
+        return Err(ParseError::Recurrence("RRULE is not supported; import an expanded calendar instead".to_string()));
+    }
+    let start = property(properties, "DTSTART")
+        .ok_or_else(|| ParseError::Missing("DTSTART".to_string()))
+        .and_then(|line| parse_time(line, time_zone))?;
+    let end = property(properties, "DTEND").map(|line| parse_time(line, time_zone)).transpose()?;
+    let duration = property(properties, "DURATION").and_then(|line| parse_duration(&line.value));
+
+    // 終日予定は`time_zone`での日付として扱う
+    let all_day_zones = EventTimeZones::new(time_zone, time_zone);
+    let (time, time_zones) = match (start, end) {
+        // DTENDの日付は含まないので、最終日はその前日
+        (IcsTime::Date(start), Some(IcsTime::Date(end))) if end > start => {
+            (EventTime::AllDay(DateRange::try_new(start, end.pred_opt().unwrap())?), all_day_zones)
+        }
+        (IcsTime::Date(start), None) => {
+            let end = duration.map(|duration| start + duration - Duration::days(1)).filter(|end| *end > start).unwrap_or(start);
+            (EventTime::AllDay(DateRange::try_new(start, end)?), all_day_zones)
+        }
+        (IcsTime::Date(start), Some(_)) => (EventTime::AllDay(DateRange::new(start, start)), all_day_zones),
+        (IcsTime::DateTime(start, start_zone), Some(IcsTime::DateTime(end, end_zone))) => {
+            (EventTime::Timed(DateTimeRange::try_new(start, end)?), EventTimeZones::new(start_zone, end_zone))
+        }
+        (IcsTime::DateTime(start, zone), _) => match duration {
+            Some(duration) => (EventTime::Timed(DateTimeRange::try_new(start, start + duration)?), EventTimeZones::new(zone, zone)),
+            None => (EventTime::StartOnly { start }, EventTimeZones::new(zone, zone)),
+        },
+    };
+    let attendees = properties
+        .iter()
+        .filter(|line| line.name == "ATTENDEE")
+        .map(|line| {
+            let name = line.param("CN").map(str::to_string);
+            Attendee::new(name.unwrap_or_else(|| line.value.trim_start_matches("mailto:").to_string()))
+        })
+        .collect();
+    let private = property(properties, "CLASS").is_some_and(|class| !class.value.eq_ignore_ascii_case("PUBLIC"));
+    let updated_at = property(properties, "LAST-MODIFIED")
+        .and_then(|line| NaiveDateTime::parse_from_str(&line.value, "%Y%m%dT%H%M%SZ").ok())
+        .map(|updated_at| updated_at.and_utc());
+
+    Ok(Event::new(id, Title::new(text(properties, "SUMMARY").unwrap_or_default()), time, attendees)
+        .with_time_zones(time_zones)
+        .with_description(text(properties, "DESCRIPTION"))
+        .with_location(text(properties, "LOCATION"))
+        .with_private(private)
+        .with_updated_at(updated_at))
+}
+
+enum IcsTime {
+    Date(NaiveDate),
+    DateTime(DateTime<Utc>, Tz),
+}
+
+fn parse_time(line: &ContentLine, time_zone: Tz) -> Result<IcsTime, ParseError> {
+    let value = line.value.as_str();
+    let parse_error = |source| ParseError::DateTime { value: value.to_string(), source };
+    if line.param("VALUE").is_some_and(|kind| kind.eq_ignore_ascii_case("DATE")) || value.len() == 8 {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").map(IcsTime::Date).map_err(parse_error);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let date_time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(parse_error)?;
+        return Ok(IcsTime::DateTime(date_time.and_utc(), Tz::UTC));
+    }
+
+    let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(parse_error)?;
+    let time_zone = line.param("TZID").map(parse_time_zone).transpose()?.unwrap_or(time_zone);
+    // 夏時間の切り替えで存在しない時刻は、切り替え前の時差で解釈する
+    let date_time = time_zone
+        .from_local_datetime(&local)
+        .earliest()
+        .unwrap_or_else(|| time_zone.from_utc_datetime(&(local - Duration::hours(1))));
+
+    Ok(IcsTime::DateTime(date_time.with_timezone(&Utc), time_zone))
+}
+
+/// `P1D`や`PT1H30M`などの期間。月や年は使えない
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(value) => (true, value),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => {}
+            _ => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                duration += match c {
+                    'W' => Duration::weeks(n),
+                    'D' => Duration::days(n),
+                    'H' => Duration::hours(n),
+                    'M' => Duration::minutes(n),
+                    'S' => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+
+    Some(if negative { -duration } else { duration })
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(escaped) => unescaped.push(escaped),
+            None => {}
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+    use crate::models::event::{EventId, EventTime};
+    use crate::sources::ics_parser::{parse_duration, parse_ics, unescape};
+    use crate::utils::date_range::DateRange;
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VEVENT\r\n\
+UID:meeting@example.com\r\n\
+DTSTART;TZID=Asia/Tokyo:20240510T090000\r\n\
+DTEND;TZID=Asia/Tokyo:20240510T100000\r\n\
+SUMMARY:定例\\, 全体\r\n\
+DESCRIPTION:議題\\n資料は前日ま\r\n\
+\x20でに共有\r\n\
+LOCATION:会議室A\r\n\
+CLASS:PRIVATE\r\n\
+ATTENDEE;CN=\"山田: 太郎\";CUTYPE=INDIVIDUAL:mailto:taro@example.com\r\n\
+ATTENDEE:mailto:hanako@example.com\r\n\
+BEGIN:VALARM\r\n\
+DESCRIPTION:通知\r\n\
+END:VALARM\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:holiday@example.com\r\n\
+DTSTART;VALUE=DATE:20240503\r\n\
+DTEND;VALUE=DATE:20240504\r\n\
+SUMMARY:憲法記念日\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:cancelled@example.com\r\n\
+DTSTART:20240510T000000Z\r\n\
+STATUS:CANCELLED\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:weekly@example.com\r\n\
+DTSTART:20240510T000000Z\r\n\
+DURATION:PT30M\r\n\
+RRULE:FREQ=WEEKLY\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:weekly@example.com\r\n\
+RECURRENCE-ID:20240517T000000Z\r\n\
+DTSTART:20240517T010000Z\r\n\
+DURATION:PT30M\r\n\
+SUMMARY:朝会(変更)\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_ics() {
+        let fetched = parse_ics(CALENDAR, "ics:", chrono_tz::UTC);
+
+        let events = fetched.events();
+        assert_eq!(events.len(), 3);
+        let meeting = &events[0];
+        assert_eq!(meeting.id, EventId::new("ics:meeting@example.com".to_string()));
+        assert_eq!(meeting.title.as_str(), "定例, 全体");
+        assert_eq!(meeting.span().start, Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap());
+        assert_eq!(meeting.time_zones.start, chrono_tz::Asia::Tokyo);
+        assert_eq!(meeting.description.as_deref(), Some("議題\n資料は前日までに共有"));
+        assert_eq!(meeting.location.as_deref(), Some("会議室A"));
+        assert!(meeting.private);
+        let attendees: Vec<&str> = meeting.attendees.iter().map(|attendee| attendee.display_name.as_str()).collect();
+        assert_eq!(attendees, vec!["山田: 太郎", "hanako@example.com"]);
+        // 終日予定のDTENDは含まない日付
+        let date = NaiveDate::from_ymd_opt(2024, 5, 3).unwrap();
+        assert_eq!(events[1].time, EventTime::AllDay(DateRange::new(date, date)));
+        // 展開された回はRECURRENCE-IDで区別する
+        assert_eq!(events[2].id, EventId::new("ics:weekly@example.com/20240517T000000Z".to_string()));
+        assert_eq!(events[2].span().end, Utc.with_ymd_and_hms(2024, 5, 17, 1, 30, 0).unwrap());
+
+        // 展開されていない繰り返し予定は読み飛ばす
+        assert_eq!(fetched.skipped().len(), 1);
+        assert_eq!(fetched.skipped()[0].id, EventId::new("ics:weekly@example.com".to_string()));
+    }
+
+    #[test]
+    fn test_parse_ics_タイムゾーンのない日時() {
+        let content = "BEGIN:VEVENT\nUID:1\nDTSTART:20240510T090000\nDTEND:20240510T100000\nEND:VEVENT\n";
+
+        let fetched = parse_ics(content, "", chrono_tz::Asia::Tokyo);
+
+        assert_eq!(fetched.events()[0].span().start, Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("P1W2D"), Some(Duration::days(9)));
+        assert_eq!(parse_duration("-PT15M"), Some(Duration::minutes(-15)));
+        assert_eq!(parse_duration("P1Y"), None);
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("a\\,b\\;c\\\\d\\Ne"), "a,b;c\\d\ne");
+    }
+}
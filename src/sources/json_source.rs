@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::apis::garoon::GaroonEvent;
+use crate::models::event::FetchedEvents;
+use crate::sources::event_source::EventSource;
+use crate::utils::date_time_range::DateTimeRange;
+
+/// Garoonの予定取得APIのレスポンスを保存したJSONファイル。予定の配列だけでもよい
+pub struct JsonSource {
+    path: PathBuf,
+}
+impl JsonSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GaroonDump {
+    Response { events: Vec<GaroonEvent> },
+    Events(Vec<GaroonEvent>),
+}
+
+#[async_trait]
+impl EventSource for JsonSource {
+    fn name(&self) -> String {
+        format!("json ({})", self.path.display())
+    }
+
+    async fn fetch(&self, period: &DateTimeRange) -> Result<FetchedEvents, anyhow::Error> {
+        let content = fs::read_to_string(&self.path).with_context(|| format!("failed to read {}", self.path.display()))?;
+        let (GaroonDump::Response { events } | GaroonDump::Events(events)) =
+            serde_json::from_str(&content).with_context(|| format!("failed to parse {}", self.path.display()))?;
+
+        let mut fetched: FetchedEvents = events.into_iter().collect();
+        fetched.events.retain(|event| period.overlaps(&event.span()));
+
+        Ok(fetched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::sources::event_source::EventSource;
+    use crate::sources::json_source::JsonSource;
+    use crate::utils::date_time_range::DateTimeRange;
+
+    #[tokio::test]
+    async fn fetch_保存したレスポンスを読み込む() {
+        // Setup: APIのレスポンスの形と、予定の配列だけの形
+        let dir = std::env::temp_dir();
+        let response_path = dir.join(format!("tensha_json_source_response_{}.json", std::process::id()));
+        let events_path = dir.join(format!("tensha_json_source_events_{}.json", std::process::id()));
+        let events = r#"[
+            {"id": "1", "subject": "会議", "start": {"dateTime": "2024-05-10T09:00:00+09:00", "timeZone": "Asia/Tokyo"}, "end": {"dateTime": "2024-05-10T10:00:00+09:00", "timeZone": "Asia/Tokyo"}},
+            {"id": "2", "subject": "来月の会議", "start": {"dateTime": "2024-06-10T09:00:00+09:00", "timeZone": "Asia/Tokyo"}, "end": {"dateTime": "2024-06-10T10:00:00+09:00", "timeZone": "Asia/Tokyo"}}
+        ]"#;
+        fs::write(&response_path, format!(r#"{{"events": {}, "hasNext": false}}"#, events)).unwrap();
+        fs::write(&events_path, events).unwrap();
+        let period = DateTimeRange::new(
+            Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap(),
+        );
+
+        // Exercise
+        let from_response = JsonSource::new(&response_path).fetch(&period).await;
+        let from_events = JsonSource::new(&events_path).fetch(&period).await;
+        fs::remove_file(&response_path).unwrap();
+        fs::remove_file(&events_path).unwrap();
+
+        // Asserts: 期間内の予定だけを返す
+        for fetched in [from_response.unwrap(), from_events.unwrap()] {
+            assert_eq!(fetched.events().len(), 1);
+            assert_eq!(fetched.events()[0].id.as_str(), "1");
+        }
+    }
+}
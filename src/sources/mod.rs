@@ -0,0 +1,6 @@
+pub mod caldav_source;
+pub mod event_source;
+pub mod garoon_source;
+pub mod ics_file_source;
+pub mod ics_parser;
+pub mod json_source;
\ No newline at end of file
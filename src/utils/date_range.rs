@@ -0,0 +1,54 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ParseError;
+
+/// 日付の範囲。終了日も範囲に含む
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DateRange {
+    pub(crate) start: NaiveDate,
+    pub(crate) end: NaiveDate,
+}
+impl DateRange {
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Self {
+        Self::try_new(start, end).expect("start must be before end")
+    }
+
+    pub fn try_new(start: NaiveDate, end: NaiveDate) -> Result<Self, ParseError> {
+        if start > end {
+            return Err(ParseError::InvalidRange { start: start.to_string(), end: end.to_string() });
+        }
+
+        Ok(Self { start, end })
+    }
+
+    /// 終了日の翌日。iCalendarやGoogle Calendar APIの終日予定の終了日として使う
+    pub fn exclusive_end(&self) -> NaiveDate {
+        self.end.succ_opt().unwrap_or(self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::DateRange;
+
+    #[test]
+    fn test_exclusive_end() {
+        let range = DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 12, 30).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+        );
+        assert_eq!(range.exclusive_end(), NaiveDate::from_ymd_opt(2025, 1, 1).unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "start must be before end")]
+    fn test_new_date_range_invalid() {
+        let _range = DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 5, 11).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 5, 10).unwrap(),
+        );
+    }
+}
@@ -1,14 +1,34 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+use crate::error::ParseError;
+use crate::utils::date_range::DateRange;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct DateTimeRange {
     pub(crate) start: DateTime<Utc>,
     pub(crate) end: DateTime<Utc>,
 }
 impl DateTimeRange {
     pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
-        assert!(start <= end, "start must be before end");
-        Self { start, end }
+        Self::try_new(start, end).expect("start must be before end")
+    }
+
+    pub fn try_new(start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Self, ParseError> {
+        if start > end {
+            return Err(ParseError::InvalidRange { start: start.to_rfc3339(), end: end.to_rfc3339() });
+        }
+
+        Ok(Self { start, end })
+    }
+
+    /// `time_zone`での日付の範囲を、その全体を覆う日時の範囲にする
+    pub fn from_dates(dates: &DateRange, time_zone: &Tz) -> Self {
+        Self::new(
+            start_of_day(dates.start, time_zone),
+            start_of_day(dates.exclusive_end(), time_zone),
+        )
     }
 
     pub fn contains(&self, other: &DateTimeRange) -> bool {
@@ -18,6 +38,34 @@ impl DateTimeRange {
     pub fn overlaps(&self, other: &DateTimeRange) -> bool {
         self.start < other.end && self.end > other.start
     }
+
+    /// `size`ごとの連続した範囲に分ける。最後の範囲は`size`より短くなることがある
+    pub fn split(&self, size: Duration) -> Vec<DateTimeRange> {
+        assert!(size > Duration::zero(), "size must be positive");
+
+        let mut chunks = vec![];
+        let mut start = self.start;
+        while start < self.end {
+            let end = (start + size).min(self.end);
+            chunks.push(Self { start, end });
+            start = end;
+        }
+        if chunks.is_empty() {
+            chunks.push(self.clone());
+        }
+
+        chunks
+    }
+}
+
+/// `time_zone`での`date`の0時
+pub(crate) fn start_of_day(date: NaiveDate, time_zone: &Tz) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    match time_zone.from_local_datetime(&midnight) {
+        LocalResult::Single(date_time) | LocalResult::Ambiguous(date_time, _) => date_time.with_timezone(&Utc),
+        // 夏時間の切り替えで0時が存在しない日は、UTCの0時で近似する
+        LocalResult::None => midnight.and_utc(),
+    }
 }
 
 #[cfg(test)]
@@ -26,6 +74,7 @@ mod tests {
         use chrono::{TimeZone, Utc};
 
         use super::super::DateTimeRange;
+        use crate::error::ParseError;
 
         #[test]
         fn test_new_datetime_range() {
@@ -44,6 +93,30 @@ mod tests {
             let _range = DateTimeRange::new(start, end);
         }
 
+        #[test]
+        fn test_try_new_datetime_range_invalid() {
+            let start = Utc.with_ymd_and_hms(2024, 5, 10, 17, 0, 0).unwrap();
+            let end = Utc.with_ymd_and_hms(2024, 5, 10, 9, 0, 0).unwrap();
+            let result = DateTimeRange::try_new(start, end);
+            assert_eq!(result, Err(ParseError::InvalidRange {
+                start: "2024-05-10T17:00:00+00:00".to_string(),
+                end: "2024-05-10T09:00:00+00:00".to_string(),
+            }));
+        }
+
+        #[test]
+        fn test_from_dates() {
+            let dates = crate::utils::date_range::DateRange::new(
+                chrono::NaiveDate::from_ymd_opt(2024, 5, 10).unwrap(),
+                chrono::NaiveDate::from_ymd_opt(2024, 5, 11).unwrap(),
+            );
+
+            let range = DateTimeRange::from_dates(&dates, &chrono_tz::Asia::Tokyo);
+
+            assert_eq!(range.start, Utc.with_ymd_and_hms(2024, 5, 9, 15, 0, 0).unwrap());
+            assert_eq!(range.end, Utc.with_ymd_and_hms(2024, 5, 11, 15, 0, 0).unwrap());
+        }
+
         #[test]
         fn test_contains() {
             let start = Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap();
@@ -81,5 +154,29 @@ mod tests {
             );
             assert!(!range.overlaps(&not_overlapping_range));
         }
+
+        #[test]
+        fn test_split() {
+            let range = DateTimeRange::new(
+                Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 5, 17, 0, 0, 0).unwrap(),
+            );
+
+            let chunks = range.split(chrono::Duration::weeks(1));
+
+            assert_eq!(chunks, vec![
+                DateTimeRange::new(Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(), Utc.with_ymd_and_hms(2024, 5, 8, 0, 0, 0).unwrap()),
+                DateTimeRange::new(Utc.with_ymd_and_hms(2024, 5, 8, 0, 0, 0).unwrap(), Utc.with_ymd_and_hms(2024, 5, 15, 0, 0, 0).unwrap()),
+                DateTimeRange::new(Utc.with_ymd_and_hms(2024, 5, 15, 0, 0, 0).unwrap(), Utc.with_ymd_and_hms(2024, 5, 17, 0, 0, 0).unwrap()),
+            ]);
+        }
+
+        #[test]
+        fn test_split_空の範囲() {
+            let start = Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap();
+            let range = DateTimeRange::new(start, start);
+
+            assert_eq!(range.split(chrono::Duration::days(1)), vec![range]);
+        }
     }
 }
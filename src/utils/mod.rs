@@ -1 +1,3 @@
-pub mod date_time_range;
\ No newline at end of file
+pub mod date_range;
+pub mod date_time_range;
+pub mod time_zone;
\ No newline at end of file
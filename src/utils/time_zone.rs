@@ -0,0 +1,39 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+
+use crate::error::ParseError;
+
+/// IANAのタイムゾーン名 (`Asia/Tokyo`など) を解釈する
+pub fn parse_time_zone(name: &str) -> Result<Tz, ParseError> {
+    name.parse::<Tz>().map_err(|_| ParseError::TimeZone(name.to_string()))
+}
+
+/// 出力用のタイムゾーンで表した日時。出力用の指定がなければ予定自身のタイムゾーンを使う
+pub fn localize(date_time: &DateTime<Utc>, event_time_zone: &Tz, output_time_zone: Option<&Tz>) -> DateTime<Tz> {
+    date_time.with_timezone(output_time_zone.unwrap_or(event_time_zone))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use chrono_tz::{America, Asia};
+
+    use super::{localize, parse_time_zone};
+
+    #[test]
+    fn test_parse_time_zone() {
+        assert_eq!(parse_time_zone("Asia/Tokyo").unwrap(), Asia::Tokyo);
+        assert!(parse_time_zone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_localize() {
+        let date_time = Utc.with_ymd_and_hms(2024, 5, 10, 0, 0, 0).unwrap();
+
+        assert_eq!(localize(&date_time, &Asia::Tokyo, None).to_rfc3339(), "2024-05-10T09:00:00+09:00");
+        assert_eq!(
+            localize(&date_time, &Asia::Tokyo, Some(&America::New_York)).to_rfc3339(),
+            "2024-05-09T20:00:00-04:00",
+        );
+    }
+}